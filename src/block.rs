@@ -1,72 +1,338 @@
 mod builder;
+mod hash_index;
 mod iterator;
+pub(crate) mod varint;
 
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use anyhow::{anyhow, bail, ensure, Result};
 pub use builder::BlockBuilder;
 /// You may want to check `bytes::BufMut` out when manipulating continuous chunks of memory
-use bytes::Bytes;
+use bytes::{Buf, BufMut, Bytes};
 pub use iterator::BlockIterator;
 
+use self::varint::try_get_varint;
+use crate::comparator::{BytewiseComparator, Comparator};
+
+/// The format version of blocks written by `BlockBuilder`, version 3 if they have a hash index.
+pub const BLOCK_VERSION: u16 = 2;
+
+/// How the entries of a block are laid out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockFormat {
+    /// The original format: every entry stores its full key, and the offset section points at
+    /// every entry.
+    ///
+    /// ----------------------------------------------------------------------------------------------------
+    /// |             Data Section             |              Offset Section             |      Extra      |
+    /// ----------------------------------------------------------------------------------------------------
+    /// | Entry #1 | Entry #2 | ... | Entry #N | Offset #1 | Offset #2 | ... | Offset #N | num_of_elements |
+    /// ----------------------------------------------------------------------------------------------------
+    ///
+    /// Entry: | key_len (u16) | key | value_len (u16) | value |
+    Legacy,
+    /// Version 1: entries only store the part of their key not shared with the previous entry.
+    /// Every `restart_interval` entries, a restart point stores the full key so that seeks can
+    /// binary search over restart points.
+    ///
+    /// --------------------------------------------------------------------------------------------------
+    /// |    Data Section    |          Restart Section           |                Extra                 |
+    /// --------------------------------------------------------------------------------------------------
+    /// | Entry #1 | ... | Entry #N | Restart #1 | ... | Restart #R | num_restarts | version | 0 (u16) |
+    /// --------------------------------------------------------------------------------------------------
+    ///
+    /// Entry: | shared_len (u16) | suffix_len (u16) | value_len (u16) | key suffix | value |
+    ///
+    /// Restarts and `num_restarts` are u16. A legacy block ending with 0 has no entries and is 2
+    /// bytes long, so the trailing 0 tells the formats apart.
+    PrefixU16,
+    /// Version 2: the layout of version 1 without its 64 KiB limits. Lengths in entries are
+    /// varints, and restarts and `num_restarts` are u32.
+    ///
+    /// Version 3 is version 2 with a hash index for point lookups, see `Block::get`. It is
+    /// inserted before the version:
+    ///
+    /// | Bucket #1 (u8) | ... | Bucket #B (u8) | num_buckets (u16) |
+    ///
+    /// Each key hashes to a bucket holding the index of its restart interval. A bucket no key
+    /// hashes to holds 255, a bucket keys of different restart intervals hash to holds 254.
+    PrefixVarint,
+}
+
+impl BlockFormat {
+    /// Get the size of a restart offset, which is also the size of the number of restarts.
+    fn restart_size(self) -> usize {
+        match self {
+            BlockFormat::Legacy | BlockFormat::PrefixU16 => 2,
+            BlockFormat::PrefixVarint => 4,
+        }
+    }
+}
+
 /// A block is the smallest unit of read and caching in LSM tree.
-/// It is a collection of sorted key-value pairs.
-/// The `actual` storage format is as below (After `Block::encode`):
-///
-/// ----------------------------------------------------------------------------------------------------
-/// |             Data Section             |              Offset Section             |      Extra      |
-/// ----------------------------------------------------------------------------------------------------
-/// | Entry #1 | Entry #2 | ... | Entry #N | Offset #1 | Offset #2 | ... | Offset #N | num_of_elements |
-/// ----------------------------------------------------------------------------------------------------
+/// It is a collection of sorted key-value pairs, stored in one of the `BlockFormat`s.
 pub struct Block {
     data: Vec<u8>,
-    offsets: Vec<u16>,
+    /// The offsets of the restart points, whose entries store their full key. Every entry is a
+    /// restart point in the legacy format.
+    restarts: Vec<u32>,
+    format: BlockFormat,
+    /// The buckets of the hash index, empty if the block has none.
+    hash_index: Vec<u8>,
+}
+
+/// The location of an entry in the block data.
+struct Entry {
+    /// The length of the key prefix shared with the previous entry.
+    shared_len: usize,
+    /// The rest of the key.
+    suffix: Range<usize>,
+    value: Range<usize>,
 }
 
 impl Block {
     /// Encode the internal data to the data layout illustrated in the tutorial
     /// Note: You may want to recheck if any of the expected field is missing from your output
     pub fn encode(&self) -> Bytes {
-        let mut encoded: Vec<u8> = Vec::new();
-        for i in 0..self.data.len() {
-            encoded.push(self.data[i]);
+        let mut encoded = self.data.clone();
+        let restart_size = self.format.restart_size();
+        for restart in &self.restarts {
+            encoded.put_uint(*restart as u64, restart_size);
+        }
+        encoded.put_uint(self.restarts.len() as u64, restart_size);
+        if !self.hash_index.is_empty() {
+            encoded.put_slice(&self.hash_index);
+            encoded.put_u16(self.hash_index.len() as u16);
+        }
+        let version = match self.format {
+            BlockFormat::Legacy => None,
+            BlockFormat::PrefixU16 => Some(1),
+            BlockFormat::PrefixVarint if self.hash_index.is_empty() => Some(2),
+            BlockFormat::PrefixVarint => Some(3),
+        };
+        if let Some(version) = version {
+            encoded.put_u16(version);
+            encoded.put_u16(0);
+        }
+        encoded.into()
+    }
+
+    /// Decode from the data layout, transform the input `data` to a single `Block`. Blocks of any
+    /// known format are accepted, anything else is an error.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let truncated = || anyhow!("block of {} bytes is truncated", data.len());
+        let mut end = data.len();
+        let mut format = BlockFormat::Legacy;
+        let mut hash_index = Vec::new();
+        if data.len() > 2 && (&data[end - 2..]).get_u16() == 0 {
+            end = end.checked_sub(4).ok_or_else(truncated)?;
+            let version = (&data[end..]).get_u16();
+            format = match version {
+                1 => BlockFormat::PrefixU16,
+                2 | 3 => BlockFormat::PrefixVarint,
+                version => bail!("unknown block version {}", version),
+            };
+            if version == 3 {
+                end = end.checked_sub(2).ok_or_else(truncated)?;
+                let num_buckets = (&data[end..]).get_u16() as usize;
+                end = end.checked_sub(num_buckets).ok_or_else(truncated)?;
+                hash_index = data[end..end + num_buckets].to_vec();
+            }
+        }
+        let restart_size = format.restart_size();
+        end = end.checked_sub(restart_size).ok_or_else(truncated)?;
+        let num = (&data[end..]).get_uint(restart_size) as usize;
+        let data_end = num
+            .checked_mul(restart_size)
+            .and_then(|len| end.checked_sub(len))
+            .ok_or_else(truncated)?;
+        let restarts = data[data_end..end]
+            .chunks(restart_size)
+            .map(|mut offset| offset.get_uint(restart_size) as u32)
+            .collect();
+        let block = Block {
+            data: data[..data_end].to_vec(),
+            restarts,
+            format,
+            hash_index,
+        };
+        block.check_entries()?;
+        Ok(block)
+    }
+
+    /// Check that the entries fill the block data and the restart points are entries storing
+    /// their full key, so that reading the block cannot go out of bounds.
+    fn check_entries(&self) -> Result<()> {
+        let mut restarts = self
+            .restarts
+            .iter()
+            .map(|offset| *offset as usize)
+            .peekable();
+        let mut offset = 0;
+        let mut key_len = 0;
+        while offset < self.data.len() {
+            let entry = self
+                .try_entry_at(offset)
+                .ok_or_else(|| anyhow!("entry at offset {} is truncated", offset))?;
+            ensure!(
+                entry.shared_len <= key_len,
+                "entry at offset {} shares more than the previous key",
+                offset
+            );
+            if restarts.next_if_eq(&offset).is_some() {
+                ensure!(
+                    entry.shared_len == 0,
+                    "restart point at offset {} does not store its full key",
+                    offset
+                );
+            }
+            key_len = entry.shared_len + entry.suffix.len();
+            offset = entry.value.end;
         }
-        for i in 0..self.offsets.len() {
-            encoded.push((self.offsets[i] >> 8) as u8);
-            encoded.push((self.offsets[i]) as u8);
+        ensure!(
+            restarts.next().is_none(),
+            "restart points are not ordered entry offsets"
+        );
+        Ok(())
+    }
+
+    pub fn format(&self) -> BlockFormat {
+        self.format
+    }
+
+    /// Check if the block has a hash index.
+    pub fn has_hash_index(&self) -> bool {
+        !self.hash_index.is_empty()
+    }
+
+    /// Get the value of `key`, `None` if the block does not hold it. The hash index, if any,
+    /// locates the restart interval of the key. Without it, or when the key shares its bucket with
+    /// keys of other restart intervals, restart points are binary searched.
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.get_by(key, &BytewiseComparator)
+    }
+
+    /// Get the value of `key` in a block whose keys are ordered by `comparator`, see `get`.
+    pub fn get_by(&self, key: &[u8], comparator: &dyn Comparator) -> Option<&[u8]> {
+        if !self.hash_index.is_empty() {
+            let bucket = hash_index::bucket_of(hash_index::hash(key), self.hash_index.len());
+            match self.hash_index[bucket] {
+                hash_index::NO_ENTRY => return None,
+                hash_index::COLLISION => {}
+                restart_idx => return self.get_from_restart(restart_idx as usize, key, comparator),
+            }
         }
-        encoded.push((self.offsets.len() >> 8) as u8);
-
-        encoded.push((self.offsets.len()) as u8);
-        let encoded_bytes: &[u8] = &encoded;
-        // if let Ok(array) = my_array {
-        //     // Use array
-        // }
-        Bytes::copy_from_slice(encoded_bytes)
+        let restart_idx = self.seek_restart(key, comparator);
+        self.get_from_restart(restart_idx, key, comparator)
     }
 
-    /// Decode from the data layout, transform the input `data` to a single `Block`
-    pub fn decode(data: &[u8]) -> Self {
-        let num = *data.last().unwrap() as usize; // Get the number of elements
+    /// Find the last restart point before `key`, 0 if there is none.
+    fn seek_restart(&self, key: &[u8], comparator: &dyn Comparator) -> usize {
+        self.restarts
+            .partition_point(|offset| {
+                comparator
+                    .compare(self.restart_key(*offset as usize), key)
+                    .is_lt()
+            })
+            .saturating_sub(1)
+    }
 
-        let mut decoded_data = Vec::new();
-        let mut decoded_offsets = Vec::new();
-        let data_end = data.len() - num * 2 - 2;
-        // Extract the data
-        for i in 0..data_end {
-            decoded_data.push(data[i]);
+    /// Get the number of entries from a restart point to the next one. `BlockBuilder` places a
+    /// restart point every `restart_interval` entries, so the entries before the first two restart
+    /// points tell it, or all the entries if there is a single one.
+    fn restart_interval(&self) -> usize {
+        let Some(&start) = self.restarts.first() else {
+            return 1;
+        };
+        let end = self
+            .restarts
+            .get(1)
+            .map_or(self.data.len(), |offset| *offset as usize);
+        let mut offset = start as usize;
+        let mut num_entries = 0;
+        while offset < end {
+            offset = self.entry_at(offset).value.end;
+            num_entries += 1;
         }
+        num_entries.max(1)
+    }
 
-        // Extract the offsets
-        for i in 0..num {
-            let offset_index = data_end + i * 2; // Calculate the index for the offsets
-            let offset = ((data[offset_index] as u16) << 8) | (data[offset_index + 1] as u16);
-            decoded_offsets.push(offset);
+    /// Scan the entries from the restart point `restart_idx` for `key`.
+    fn get_from_restart(
+        &self,
+        restart_idx: usize,
+        key: &[u8],
+        comparator: &dyn Comparator,
+    ) -> Option<&[u8]> {
+        let mut offset = *self.restarts.get(restart_idx)? as usize;
+        let mut current = Vec::new();
+        while offset < self.data.len() {
+            let entry = self.entry_at(offset);
+            current.truncate(entry.shared_len);
+            current.extend_from_slice(&self.data[entry.suffix]);
+            match comparator.compare(&current, key) {
+                Ordering::Less => offset = entry.value.end,
+                Ordering::Equal => return Some(&self.data[entry.value]),
+                Ordering::Greater => return None,
+            }
         }
+        None
+    }
+
+    /// Get the full key stored by the restart point at `offset`.
+    fn restart_key(&self, offset: usize) -> &[u8] {
+        &self.data[self.entry_at(offset).suffix]
+    }
 
-        Block {
-            data: decoded_data,
-            offsets: decoded_offsets,
+    /// Locate the entry at `offset`. `Block::decode` checked that every entry is in bounds.
+    fn entry_at(&self, offset: usize) -> Entry {
+        self.try_entry_at(offset).expect("entry out of bounds")
+    }
+
+    /// Locate the entry at `offset`, `None` if it does not fit in the block data.
+    fn try_entry_at(&self, offset: usize) -> Option<Entry> {
+        let mut buf = self.data.get(offset..)?;
+        let (shared_len, suffix_len, value_len) = match self.format {
+            BlockFormat::Legacy => {
+                let key_len = try_get_u16(&mut buf)? as usize;
+                let value_len = try_get_u16(&mut buf.get(key_len..)?)? as usize;
+                (0, key_len, value_len)
+            }
+            BlockFormat::PrefixU16 => (
+                try_get_u16(&mut buf)? as usize,
+                try_get_u16(&mut buf)? as usize,
+                try_get_u16(&mut buf)? as usize,
+            ),
+            BlockFormat::PrefixVarint => (
+                try_get_varint(&mut buf)? as usize,
+                try_get_varint(&mut buf)? as usize,
+                try_get_varint(&mut buf)? as usize,
+            ),
+        };
+        let suffix_start = self.data.len() - buf.remaining();
+        let suffix = suffix_start..suffix_start.checked_add(suffix_len)?;
+        // Legacy entries store the value length between the key and the value.
+        let value_start = match self.format {
+            BlockFormat::Legacy => suffix.end + 2,
+            BlockFormat::PrefixU16 | BlockFormat::PrefixVarint => suffix.end,
+        };
+        let value = value_start..value_start.checked_add(value_len)?;
+        if value.end > self.data.len() {
+            return None;
         }
+        Some(Entry {
+            shared_len,
+            suffix,
+            value,
+        })
     }
 }
 
+/// Read a u16, `None` if `buf` ends before it.
+fn try_get_u16(buf: &mut &[u8]) -> Option<u16> {
+    (buf.remaining() >= 2).then(|| buf.get_u16())
+}
+
 #[cfg(test)]
 mod tests;
@@ -1,61 +1,130 @@
-use super::Block;
+use bytes::BufMut;
 
-/// Builds a block.
+use super::hash_index;
+use super::varint::{put_varint, varint_len};
+use super::{Block, BlockFormat};
+
+/// Restart points are placed every this many entries unless configured otherwise.
+pub const DEFAULT_RESTART_INTERVAL: usize = 16;
+
+/// Builds a block in the current format, `BlockFormat::PrefixVarint`.
 pub struct BlockBuilder {
-    block: Block,
-    curr_size: u16,
-    block_size: u16,
+    data: Vec<u8>,
+    restarts: Vec<u32>,
+    /// The key of the last entry added, the next one is stored relative to it.
+    last_key: Vec<u8>,
+    num_entries: usize,
+    restart_interval: usize,
+    block_size: usize,
+    /// The hash of the key of each entry and the index of its restart interval, `None` if the
+    /// block gets no hash index.
+    hashes: Option<Vec<(u32, usize)>>,
 }
 
 impl BlockBuilder {
     /// Creates a new block builder.
     pub fn new(block_size: usize) -> Self {
+        Self::with_restart_interval(block_size, DEFAULT_RESTART_INTERVAL)
+    }
+
+    /// Creates a new block builder placing a restart point every `restart_interval` entries.
+    pub fn with_restart_interval(block_size: usize, restart_interval: usize) -> Self {
+        assert!(restart_interval > 0, "restart interval cannot be 0");
         BlockBuilder {
-            block: Block {
-                data: Vec::new(),
-                offsets: Vec::new(),
-            },
-            curr_size: 0u16,
-            block_size: block_size as u16,
+            data: Vec::new(),
+            restarts: Vec::new(),
+            last_key: Vec::new(),
+            num_entries: 0,
+            restart_interval,
+            block_size,
+            hashes: None,
         }
     }
 
-    /// Adds a key-value pair to the block. Returns false when the block is full.
+    /// Write a hash index speeding up `Block::get`. Blocks of more than 254 restart intervals get
+    /// none.
+    pub fn enable_hash_index(&mut self) {
+        self.hashes.get_or_insert_with(Vec::new);
+    }
+
+    /// Adds a key-value pair to the block. Returns false when the block is full. The first
+    /// key-value pair is always accepted, however large it is.
     #[must_use]
     pub fn add(&mut self, key: &[u8], value: &[u8]) -> bool {
-        if self.curr_size + (key.len() + 2) as u16 + (value.len() + 2) as u16 + 2 > self.block_size
-        {
+        let is_restart = self.num_entries.is_multiple_of(self.restart_interval);
+        let shared_len = if is_restart {
+            0
+        } else {
+            self.last_key
+                .iter()
+                .zip(key)
+                .take_while(|(a, b)| a == b)
+                .count()
+        };
+        let suffix = &key[shared_len..];
+        let header_size = varint_len(shared_len as u64)
+            + varint_len(suffix.len() as u64)
+            + varint_len(value.len() as u64);
+        let mut entry_size =
+            header_size + suffix.len() + value.len() + if is_restart { 4 } else { 0 };
+        if self.hashes.is_some() {
+            entry_size += hash_index::num_buckets(self.num_entries + 1)
+                - hash_index::num_buckets(self.num_entries);
+        }
+        if !self.is_empty() && self.estimated_size() + entry_size > self.block_size {
             return false;
         }
-        self.block.offsets.push(self.block.data.len() as u16);
-        self.block.data.push((key.len() >> 8) as u8);
-        self.block.data.push((key.len()) as u8);
-        for i in 0..key.len() {
-            self.block.data.push(key[i]);
+
+        if is_restart {
+            self.restarts.push(self.data.len() as u32);
         }
-        self.block.data.push((value.len() >> 8) as u8);
-        self.block.data.push((value.len()) as u8);
-        for i in 0..value.len() {
-            self.block.data.push(value[i]);
+        put_varint(&mut self.data, shared_len as u64);
+        put_varint(&mut self.data, suffix.len() as u64);
+        put_varint(&mut self.data, value.len() as u64);
+        self.data.put_slice(suffix);
+        self.data.put_slice(value);
+        if let Some(hashes) = &mut self.hashes {
+            hashes.push((hash_index::hash(key), self.restarts.len() - 1));
         }
-        self.curr_size += (key.len() + 2) as u16 + (value.len() + 2) as u16 + 2;
+        self.last_key.clear();
+        self.last_key.extend_from_slice(key);
+        self.num_entries += 1;
         true
     }
 
     /// Check if there is no key-value pair in the block.
     pub fn is_empty(&self) -> bool {
-        if self.curr_size == 0 {
-            return true;
+        self.num_entries == 0
+    }
+
+    /// Get the size of the block once encoded.
+    pub fn estimated_size(&self) -> usize {
+        // The restart section is followed by the number of restarts (u32), the version and a 0.
+        let mut size = self.data.len() + self.restarts.len() * 4 + 8;
+        if self.hashes.is_some() {
+            // The buckets and their number (u16).
+            size += hash_index::num_buckets(self.num_entries) + 2;
         }
-        false
+        size
     }
 
     /// Finalize the block.
     pub fn build(self) -> Block {
-        self.block
+        let hash_index = match self.hashes {
+            Some(hashes) if self.restarts.len() <= hash_index::MAX_RESTARTS => {
+                hash_index::build(&hashes)
+            }
+            _ => Vec::new(),
+        };
+        Block {
+            data: self.data,
+            restarts: self.restarts,
+            format: BlockFormat::PrefixVarint,
+            hash_index,
+        }
     }
 
     pub fn full(self) -> bool {
-        return self.curr_size >= self.block_size;
+        self.estimated_size() >= self.block_size
     }
 }
@@ -0,0 +1,41 @@
+/// A bucket no key hashes to: the key is not in the block.
+pub(crate) const NO_ENTRY: u8 = 255;
+/// A bucket keys of several restart intervals hash to: the key has to be searched for.
+pub(crate) const COLLISION: u8 = 254;
+/// The number of restart intervals a hash index can point at, the other bucket values are
+/// markers.
+pub(crate) const MAX_RESTARTS: usize = COLLISION as usize;
+
+/// Get the number of buckets of the hash index of a block with `num_entries` entries, about 4 for
+/// every 3 entries.
+pub(crate) fn num_buckets(num_entries: usize) -> usize {
+    (num_entries * 4).div_ceil(3).clamp(1, u16::MAX as usize)
+}
+
+/// Hash a key with 32-bit FNV-1a.
+pub(crate) fn hash(key: &[u8]) -> u32 {
+    key.iter().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+/// Get the bucket of the key hashed to `hash` in a hash index of `num_buckets` buckets.
+pub(crate) fn bucket_of(hash: u32, num_buckets: usize) -> usize {
+    hash as usize % num_buckets
+}
+
+/// Build the buckets of a hash index, from the hash of the key of each entry and the index of the
+/// restart interval holding it.
+pub(crate) fn build(entries: &[(u32, usize)]) -> Vec<u8> {
+    let mut buckets = vec![NO_ENTRY; num_buckets(entries.len())];
+    let num_buckets = buckets.len();
+    for (hash, restart_idx) in entries {
+        let bucket = &mut buckets[bucket_of(*hash, num_buckets)];
+        *bucket = match *bucket {
+            NO_ENTRY => *restart_idx as u8,
+            idx if idx as usize == *restart_idx => idx,
+            _ => COLLISION,
+        };
+    }
+    buckets
+}
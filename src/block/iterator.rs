@@ -1,6 +1,8 @@
+use std::ops::Range;
 use std::sync::Arc;
 
 use super::Block;
+use crate::comparator::{BytewiseComparator, Comparator};
 
 /// Iterates on a block.
 pub struct BlockIterator {
@@ -8,10 +10,12 @@ pub struct BlockIterator {
     block: Arc<Block>,
     /// The current key, empty represents the iterator is invalid
     key: Vec<u8>,
-    /// The corresponding value, can be empty
-    value: Vec<u8>,
-    /// Current index of the key-value pair, should be in range of [0, num_of_elements)
-    idx: usize,
+    /// The range of the corresponding value in the block data, can be empty
+    value: Range<usize>,
+    /// The offset of the current entry
+    offset: usize,
+    /// The offset of the entry after the current one
+    next_offset: usize,
 }
 
 impl BlockIterator {
@@ -19,8 +23,9 @@ impl BlockIterator {
         Self {
             block,
             key: Vec::new(),
-            value: Vec::new(),
-            idx: 0,
+            value: 0..0,
+            offset: 0,
+            next_offset: 0,
         }
     }
 
@@ -38,6 +43,25 @@ impl BlockIterator {
         bi
     }
 
+    /// Creates a block iterator and seek to the first key that >= `key` in the order of
+    /// `comparator`, which the keys of the block are sorted by.
+    pub fn create_and_seek_to_key_by(
+        block: Arc<Block>,
+        key: &[u8],
+        comparator: &dyn Comparator,
+    ) -> Self {
+        let mut bi = BlockIterator::new(block);
+        bi.seek_to_key_by(key, comparator);
+        bi
+    }
+
+    /// Creates a block iterator and seek to the entry at position `pos`, see `seek_to_position`.
+    pub fn create_and_seek_to_position(block: Arc<Block>, pos: usize) -> Self {
+        let mut bi = BlockIterator::new(block);
+        bi.seek_to_position(pos);
+        bi
+    }
+
     /// Returns the key of the current entry.
     pub fn key(&self) -> &[u8] {
         &self.key
@@ -45,7 +69,7 @@ impl BlockIterator {
 
     /// Returns the value of the current entry.
     pub fn value(&self) -> &[u8] {
-        &self.value
+        &self.block.data[self.value.clone()]
     }
 
     /// Returns true if the iterator is valid.
@@ -56,66 +80,137 @@ impl BlockIterator {
 
     /// Seeks to the first key in the block.
     pub fn seek_to_first(&mut self) {
-        self.idx = 0;
-        self.set_kv();
+        self.seek_to_offset(0);
     }
 
     /// Move to the next key in the block.
     pub fn next(&mut self) {
-        self.idx += 1;
-        self.set_kv();
+        if self.next_offset >= self.block.data.len() {
+            self.key.clear();
+            self.value = 0..0;
+            return;
+        }
+        let entry = self.block.entry_at(self.next_offset);
+        self.offset = self.next_offset;
+        self.key.truncate(entry.shared_len);
+        self.key.extend_from_slice(&self.block.data[entry.suffix]);
+        self.next_offset = entry.value.end;
+        self.value = entry.value;
+    }
+
+    /// Move to the previous key in the block. Entries only store the part of their key not shared
+    /// with the previous one, so the entries are scanned from the restart point before.
+    pub fn prev(&mut self) {
+        if !self.is_valid() {
+            return;
+        }
+        let current = self.offset;
+        if current == 0 {
+            self.key.clear();
+            self.value = 0..0;
+            return;
+        }
+        let restart_idx = self
+            .block
+            .restarts
+            .partition_point(|offset| (*offset as usize) < current)
+            - 1;
+        self.seek_to_offset(self.block.restarts[restart_idx] as usize);
+        while self.next_offset < current {
+            self.next();
+        }
+    }
+
+    /// Seeks to the last key in the block.
+    pub fn seek_to_last(&mut self) {
+        let Some(restart) = self.block.restarts.last() else {
+            self.seek_to_offset(self.block.data.len());
+            return;
+        };
+        self.seek_to_offset(*restart as usize);
+        while self.next_offset < self.block.data.len() {
+            self.next();
+        }
+    }
+
+    /// Seek to the last key that <= `key`.
+    pub fn seek_for_prev(&mut self, key: &[u8]) {
+        self.seek_for_prev_by(key, &BytewiseComparator);
+    }
+
+    /// Seek to the last key that <= `key` in the order of `comparator`.
+    pub fn seek_for_prev_by(&mut self, key: &[u8], comparator: &dyn Comparator) {
+        self.seek_to_key_by(key, comparator);
+        if !self.is_valid() {
+            self.seek_to_last();
+        } else if comparator.compare(self.key(), key).is_gt() {
+            self.prev();
+        }
     }
 
     /// Seek to the first key that >= `key`.
     /// Note: You should assume the key-value pairs in the block are sorted when being added by callers.
     pub fn seek_to_key(&mut self, key: &[u8]) {
-        self.idx = 0;
-
-        let data = &self.block.data;
-        let mut key_start = self.block.offsets[self.idx];
-        let mut key_end = key_start
-            + ((data[key_start as usize] as u16) << 8)
-            + data[key_start as usize + 1] as u16
-            + 2;
-
-        while !self.compare_bytes(&data[key_start as usize + 2..key_end as usize], key) {
-            self.idx += 1;
-            if self.idx >= self.block.offsets.len() {
-                break;
-            }
-            key_start = self.block.offsets[self.idx];
-            key_end = key_start
-                + ((data[key_start as usize] as u16) << 8)
-                + data[key_start as usize + 1] as u16
-                + 2;
+        self.seek_to_key_by(key, &BytewiseComparator);
+    }
+
+    /// Seek to the first key that >= `key` in the order of `comparator`.
+    pub fn seek_to_key_by(&mut self, key: &[u8], comparator: &dyn Comparator) {
+        // Find the last restart point before `key`, then scan the entries following it.
+        let restart_idx = self.block.seek_restart(key, comparator);
+        self.seek_to_restart(restart_idx);
+        while self.is_valid() && comparator.compare(self.key(), key).is_lt() {
+            self.next();
         }
-        self.set_kv();
     }
 
-    fn compare_bytes(&self, left: &[u8], right: &[u8]) -> bool {
-        return String::from_utf8(left.to_vec()).unwrap()
-            >= String::from_utf8(right.to_vec()).unwrap();
+    /// Seek to the first key that >= `key` in the order of `comparator`, like `seek_to_key_by`,
+    /// returning the position of the entry found in the block, the number of entries if there is
+    /// none.
+    pub fn seek_to_key_with_position_by(
+        &mut self,
+        key: &[u8],
+        comparator: &dyn Comparator,
+    ) -> usize {
+        let restart_idx = self.block.seek_restart(key, comparator);
+        self.seek_to_restart(restart_idx);
+        let mut pos = match restart_idx {
+            0 => 0,
+            _ => restart_idx * self.block.restart_interval(),
+        };
+        while self.is_valid() && comparator.compare(self.key(), key).is_lt() {
+            self.next();
+            pos += 1;
+        }
+        pos
     }
 
-    // Once index updated, set key and value by accessing data via idx
-    fn set_kv(&mut self) {
-        if self.idx >= self.block.offsets.len() {
-            self.key.clear();
-            self.value.clear();
-            return;
+    /// Seek to the entry at position `pos` in the block, the iterator is invalid if there is none.
+    /// The restart point before it is found from the restart interval, then the entries following
+    /// it are scanned.
+    pub fn seek_to_position(&mut self, pos: usize) {
+        let interval = self.block.restart_interval();
+        self.seek_to_restart(pos / interval);
+        let mut current = pos / interval * interval;
+        while self.is_valid() && current < pos {
+            self.next();
+            current += 1;
         }
-        let key_start = self.block.offsets[self.idx];
-        let data: &Vec<u8> = &self.block.data;
-        let key_end = key_start
-            + ((data[key_start as usize] as u16) << 8)
-            + data[key_start as usize + 1] as u16
-            + 2;
-        let val_start = key_end;
-        let val_end = val_start
-            + ((data[val_start as usize] as u16) << 8)
-            + data[val_start as usize + 1] as u16
-            + 2;
-        self.key = data[key_start as usize + 2..key_end as usize].to_vec();
-        self.value = data[val_start as usize + 2..val_end as usize].to_vec();
+    }
+
+    /// Move to the restart point `restart_idx`, past the last entry if there is none.
+    fn seek_to_restart(&mut self, restart_idx: usize) {
+        let offset = match self.block.restarts.get(restart_idx) {
+            Some(offset) => *offset as usize,
+            None => self.block.data.len(),
+        };
+        self.seek_to_offset(offset);
+    }
+
+    /// Move to the entry at `offset`, which must store its full key.
+    fn seek_to_offset(&mut self, offset: usize) {
+        self.key.clear();
+        self.next_offset = offset;
+        self.next();
     }
 }
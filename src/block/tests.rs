@@ -2,6 +2,8 @@ use std::sync::Arc;
 
 use super::builder::BlockBuilder;
 use super::iterator::BlockIterator;
+use bytes::BufMut;
+
 use super::*;
 
 #[test]
@@ -56,9 +58,204 @@ fn test_block_encode() {
 fn test_block_decode() {
     let block = generate_block();
     let encoded = block.encode();
-    let decoded_block = Block::decode(&encoded);
-    assert_eq!(block.offsets, decoded_block.offsets);
+    let decoded_block = Block::decode(&encoded).unwrap();
+    assert_eq!(block.restarts, decoded_block.restarts);
     assert_eq!(block.data, decoded_block.data);
+    assert_eq!(decoded_block.format(), BlockFormat::PrefixVarint);
+}
+
+/// Encode entries in the legacy format, as written before prefix compression.
+fn encode_legacy(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut offsets = Vec::new();
+    for (key, value) in entries {
+        offsets.push(data.len() as u16);
+        data.put_u16(key.len() as u16);
+        data.put_slice(key);
+        data.put_u16(value.len() as u16);
+        data.put_slice(value);
+    }
+    for offset in &offsets {
+        data.put_u16(*offset);
+    }
+    data.put_u16(offsets.len() as u16);
+    data
+}
+
+#[test]
+fn test_block_decode_legacy() {
+    let entries: Vec<_> = (0..num_of_keys())
+        .map(|idx| (key_of(idx), value_of(idx)))
+        .collect();
+    let encoded = encode_legacy(&entries);
+    let block = Block::decode(&encoded).unwrap();
+    assert_eq!(block.format(), BlockFormat::Legacy);
+    assert_eq!(block.encode(), encoded);
+    assert!(generate_block().encode().len() < encoded.len());
+
+    let block = Arc::new(block);
+    let mut iter = BlockIterator::create_and_seek_to_first(block.clone());
+    for (key, value) in &entries {
+        assert_eq!(iter.key(), key);
+        assert_eq!(iter.value(), value);
+        iter.next();
+    }
+    assert!(!iter.is_valid());
+    let iter = BlockIterator::create_and_seek_to_key(block, b"key_011");
+    assert_eq!(iter.key(), key_of(3));
+
+    let block = Block::decode(&encode_legacy(&[])).unwrap();
+    assert_eq!(block.format(), BlockFormat::Legacy);
+    assert!(!BlockIterator::create_and_seek_to_first(Arc::new(block)).is_valid());
+}
+
+#[test]
+fn test_block_decode_v1() {
+    // Version 1 stores lengths and restarts as u16, with a restart every 2 entries here.
+    let mut encoded = Vec::new();
+    let mut restarts = Vec::new();
+    for (shared_len, suffix, value) in [
+        (0, &b"key_1"[..], &b"a"[..]),
+        (4, b"2", b"bb"),
+        (0, b"key_3", b"ccc"),
+    ] {
+        if shared_len == 0 {
+            restarts.push(encoded.len() as u16);
+        }
+        encoded.put_u16(shared_len);
+        encoded.put_u16(suffix.len() as u16);
+        encoded.put_u16(value.len() as u16);
+        encoded.put_slice(suffix);
+        encoded.put_slice(value);
+    }
+    for restart in &restarts {
+        encoded.put_u16(*restart);
+    }
+    encoded.put_u16(restarts.len() as u16);
+    encoded.put_u16(1);
+    encoded.put_u16(0);
+
+    let block = Block::decode(&encoded).unwrap();
+    assert_eq!(block.format(), BlockFormat::PrefixU16);
+    assert_eq!(block.encode(), encoded);
+    let mut iter = BlockIterator::create_and_seek_to_key(Arc::new(block), b"key_2");
+    assert_eq!(iter.key(), b"key_2");
+    assert_eq!(iter.value(), b"bb");
+    iter.next();
+    assert_eq!(iter.key(), b"key_3");
+    assert_eq!(iter.value(), b"ccc");
+    iter.next();
+    assert!(!iter.is_valid());
+}
+
+#[test]
+fn test_block_oversized_entry() {
+    let large = vec![b'x'; 5 << 20];
+    let mut builder = BlockBuilder::new(4096);
+    assert!(builder.add(b"large", &large));
+    assert!(!builder.add(b"small", b"value"));
+    let block = Arc::new(Block::decode(&builder.build().encode()).unwrap());
+    let iter = BlockIterator::create_and_seek_to_first(block);
+    assert_eq!(iter.key(), b"large");
+    assert_eq!(iter.value(), large);
+
+    let mut builder = BlockBuilder::new(200_000);
+    for idx in 0..num_of_keys() {
+        assert!(builder.add(&key_of(idx), &large[..1000]));
+    }
+    let block = Arc::new(Block::decode(&builder.build().encode()).unwrap());
+    let iter = BlockIterator::create_and_seek_to_key(block, &key_of(99));
+    assert_eq!(iter.key(), key_of(99));
+    assert_eq!(iter.value(), &large[..1000]);
+}
+
+#[test]
+fn test_block_restart_interval() {
+    let mut builder = BlockBuilder::with_restart_interval(10000, 3);
+    for idx in 0..num_of_keys() {
+        assert!(builder.add(&key_of(idx), &value_of(idx)));
+    }
+    let block = Arc::new(Block::decode(&builder.build().encode()).unwrap());
+    assert_eq!(block.restarts.len(), num_of_keys().div_ceil(3));
+    for idx in 0..num_of_keys() {
+        let iter = BlockIterator::create_and_seek_to_key(block.clone(), &key_of(idx));
+        assert_eq!(iter.key(), key_of(idx));
+        assert_eq!(iter.value(), value_of(idx));
+        let iter = BlockIterator::create_and_seek_to_key(
+            block.clone(),
+            format!("key_{:03}", idx * 5 + 1).as_bytes(),
+        );
+        if idx + 1 < num_of_keys() {
+            assert_eq!(iter.key(), key_of(idx + 1));
+        } else {
+            assert!(!iter.is_valid());
+        }
+    }
+}
+
+#[test]
+fn test_block_seek_position() {
+    // Restart points every entry, every few entries, and a single one.
+    for interval in [1, 3, 1000] {
+        let mut builder = BlockBuilder::with_restart_interval(10000, interval);
+        for idx in 0..num_of_keys() {
+            assert!(builder.add(&key_of(idx), &value_of(idx)));
+        }
+        let block = Arc::new(Block::decode(&builder.build().encode()).unwrap());
+        for idx in 0..num_of_keys() {
+            let iter = BlockIterator::create_and_seek_to_position(block.clone(), idx);
+            assert_eq!(iter.key(), key_of(idx));
+            assert_eq!(iter.value(), value_of(idx));
+            let mut iter = BlockIterator::create_and_seek_to_position(block.clone(), 0);
+            let key = format!("key_{:03}", idx * 5 + 1).into_bytes();
+            assert_eq!(
+                iter.seek_to_key_with_position_by(&key, &BytewiseComparator),
+                idx + 1
+            );
+            assert_eq!(
+                iter.seek_to_key_with_position_by(&key_of(idx), &BytewiseComparator),
+                idx
+            );
+            assert_eq!(iter.key(), key_of(idx));
+        }
+        let iter = BlockIterator::create_and_seek_to_position(block, num_of_keys());
+        assert!(!iter.is_valid());
+    }
+}
+
+#[test]
+fn test_block_hash_index() {
+    let mut builder = BlockBuilder::with_restart_interval(10000, 4);
+    builder.enable_hash_index();
+    for idx in 0..num_of_keys() {
+        assert!(builder.add(&key_of(idx), &value_of(idx)));
+    }
+    let encoded = builder.build().encode();
+    let block = Block::decode(&encoded).unwrap();
+    assert!(block.has_hash_index());
+    assert_eq!(block.encode(), encoded);
+    // Some keys of different restart intervals share a bucket and are binary searched.
+    assert!(block.hash_index.contains(&hash_index::COLLISION));
+    let plain = generate_block();
+    for idx in 0..num_of_keys() {
+        assert_eq!(block.get(&key_of(idx)), Some(&value_of(idx)[..]));
+        assert_eq!(plain.get(&key_of(idx)), Some(&value_of(idx)[..]));
+        let missing = format!("key_{:03}", idx * 5 + 1);
+        assert_eq!(block.get(missing.as_bytes()), None);
+        assert_eq!(plain.get(missing.as_bytes()), None);
+    }
+    assert_eq!(block.get(b"a"), None);
+    assert_eq!(block.get(b"z"), None);
+
+    // Restart intervals past the ones a bucket can point at leave the block without a hash index.
+    let mut builder = BlockBuilder::with_restart_interval(100000, 1);
+    builder.enable_hash_index();
+    for idx in 0..300 {
+        assert!(builder.add(format!("key_{:04}", idx).as_bytes(), &value_of(idx)));
+    }
+    let block = Block::decode(&builder.build().encode()).unwrap();
+    assert!(!block.has_hash_index());
+    assert_eq!(block.get(b"key_0299"), Some(&value_of(299)[..]));
 }
 
 fn as_bytes(x: &[u8]) -> Bytes {
@@ -120,3 +317,58 @@ fn test_block_seek_key() {
         iter.seek_to_key(b"k");
     }
 }
+
+#[test]
+fn test_block_reverse_iterator() {
+    let mut builder = BlockBuilder::with_restart_interval(10000, 3);
+    for idx in 0..num_of_keys() {
+        assert!(builder.add(&key_of(idx), &value_of(idx)));
+    }
+    let block = Arc::new(Block::decode(&builder.build().encode()).unwrap());
+    let mut iter = BlockIterator::create_and_seek_to_first(block);
+    iter.seek_to_last();
+    for idx in (0..num_of_keys()).rev() {
+        assert_eq!(iter.key(), key_of(idx));
+        assert_eq!(iter.value(), value_of(idx));
+        iter.prev();
+    }
+    assert!(!iter.is_valid());
+
+    for idx in 0..num_of_keys() {
+        iter.seek_for_prev(&key_of(idx));
+        assert_eq!(iter.key(), key_of(idx));
+        iter.seek_for_prev(format!("key_{:03}", idx * 5 + 1).as_bytes());
+        assert_eq!(iter.key(), key_of(idx));
+    }
+    iter.seek_for_prev(b"key");
+    assert!(!iter.is_valid());
+    iter.seek_for_prev(b"l");
+    assert_eq!(iter.key(), key_of(num_of_keys() - 1));
+}
+
+#[test]
+fn test_block_decode_corrupted() {
+    let encoded = generate_block().encode().to_vec();
+    let len = encoded.len();
+
+    // An unknown version.
+    let mut data = encoded.clone();
+    data[len - 4..len - 2].copy_from_slice(&9u16.to_be_bytes());
+    assert!(Block::decode(&data).is_err());
+
+    // More restart points than the block holds.
+    let mut data = encoded.clone();
+    data[len - 8..len - 4].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert!(Block::decode(&data).is_err());
+
+    // An entry longer than the data section.
+    let mut builder = BlockBuilder::new(16);
+    assert!(builder.add(b"k", b"v"));
+    let mut data = builder.build().encode().to_vec();
+    assert_eq!(&data[..5], &[0, 1, 1, b'k', b'v']);
+    data[2] = 0x7f;
+    assert!(Block::decode(&data).is_err());
+
+    assert!(Block::decode(&encoded[len - 3..]).is_err());
+    assert!(Block::decode(&[0]).is_err());
+}
@@ -0,0 +1,32 @@
+use bytes::{Buf, BufMut};
+
+/// Write `value` in LEB128: 7 bits per byte, least significant first, with the high bit set on
+/// every byte but the last.
+pub(crate) fn put_varint(buf: &mut impl BufMut, mut value: u64) {
+    while value >= 0x80 {
+        buf.put_u8(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.put_u8(value as u8);
+}
+
+/// Read a value written by `put_varint`, `None` if `buf` ends before it or it does not fit in a
+/// u64.
+pub(crate) fn try_get_varint(buf: &mut impl Buf) -> Option<u64> {
+    let mut value = 0;
+    let mut shift = 0;
+    while buf.has_remaining() && shift < 64 {
+        let byte = buf.get_u8();
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+    None
+}
+
+/// Get the number of bytes `put_varint` writes for `value`.
+pub(crate) fn varint_len(value: u64) -> usize {
+    (64 - (value | 1).leading_zeros() as usize).div_ceil(7)
+}
@@ -0,0 +1,314 @@
+use std::cmp;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::compaction_filter::{CompactionFilter, CompactionFilterContext, FilterDecision};
+use crate::iterators::merge_iterator::MergeIterator;
+use crate::iterators::StorageIterator;
+use crate::key;
+use crate::lsm_storage::LsmStorage;
+use crate::merge_operator::MergeOperands;
+use crate::range_tombstone::RangeTombstoneList;
+use crate::table::{SsTable, SsTableBuilder, SsTableIterator};
+use crate::value::{self, Record, ValueKind};
+
+impl LsmStorage {
+    /// Compact all L0 and L1 SSTs into a new sorted run at L1. As every SST takes part, the
+    /// output is the bottommost level and deletions, including range tombstones, can be dropped.
+    pub fn force_full_compaction(&self) -> Result<()> {
+        let _compaction_lock = self.compaction_lock.lock();
+
+        let snapshot = self.snapshot();
+        let l0_sstables = snapshot.l0_sstables.clone();
+        let l1_sstables = snapshot.levels.first().cloned().unwrap_or_default();
+        if l0_sstables.is_empty() && l1_sstables.is_empty() {
+            return Ok(());
+        }
+
+        // L1 SSTs outside of the key range the compaction rewrites keep their place. The range
+        // spans L0 and the L1 SSTs the compaction would still change, so that its output does not
+        // overlap the SSTs kept around it.
+        let pinned: Vec<_> = l0_sstables
+            .iter()
+            .chain(l1_sstables.iter().filter(|table| !self.may_move(table)))
+            .cloned()
+            .collect();
+        let comparator = &*self.options.comparator;
+        let range = self.key_range(&pinned);
+        let (mut moved_before, mut moved_after) = (Vec::new(), Vec::new());
+        let mut rewritten = Vec::new();
+        for table in &l1_sstables {
+            let properties = table.properties();
+            match &range {
+                None => moved_before.push(table.clone()),
+                Some((first, _)) if comparator.compare(&properties.last_key, first).is_lt() => {
+                    moved_before.push(table.clone())
+                }
+                Some((_, last)) if comparator.compare(&properties.first_key, last).is_gt() => {
+                    moved_after.push(table.clone())
+                }
+                Some(_) => rewritten.push(table.clone()),
+            }
+        }
+
+        // L0 SSTs overlap each other, the newest one must win.
+        let tables: Vec<_> = l0_sstables.iter().rev().chain(rewritten.iter()).collect();
+        let range_tombstones = RangeTombstoneList::new_with_comparator(
+            tables.iter().flat_map(|table| table.range_tombstones()),
+            self.options.comparator.clone(),
+        );
+        let mut iters = Vec::with_capacity(tables.len());
+        for (idx, table) in tables.iter().enumerate() {
+            // Every key of an SST is older than the range tombstones of the newer SSTs. Skip the
+            // SST without reading it if one of them covers its whole key range.
+            let covered = !table.first_key().is_empty()
+                && tables[..idx]
+                    .iter()
+                    .flat_map(|newer| newer.range_tombstones())
+                    .any(|t| {
+                        t.contains_by(table.first_key(), comparator)
+                            && t.contains_by(table.last_key(), comparator)
+                    });
+            if covered {
+                continue;
+            }
+            iters.push(Box::new(SsTableIterator::create_and_seek_to_first(
+                (*table).clone(),
+            )?));
+        }
+        let iter = MergeIterator::create_all_versions_with_comparator(
+            iters,
+            self.options.comparator.clone(),
+        );
+        let new_sstables = self.compact_iter(iter, &range_tombstones)?;
+
+        {
+            let mut guard = self.inner.write();
+            let mut snapshot = guard.as_ref().clone();
+            // L0 SSTs flushed during the compaction are kept.
+            snapshot
+                .l0_sstables
+                .retain(|table| !l0_sstables.iter().any(|x| Arc::ptr_eq(x, table)));
+            if snapshot.levels.is_empty() {
+                snapshot.levels.push(Vec::new());
+            }
+            moved_before.extend(new_sstables);
+            moved_before.extend(moved_after);
+            snapshot.levels[0] = moved_before;
+            *guard = Arc::new(snapshot);
+        }
+        Ok(())
+    }
+
+    /// Write the entries of `iter` into new SSTs of about `target_sst_size` bytes, dropping the
+    /// ones that are no longer visible to any reader. `iter` yields every version of a key, newest
+    /// first.
+    fn compact_iter(
+        &self,
+        mut iter: impl StorageIterator,
+        range_tombstones: &RangeTombstoneList,
+    ) -> Result<Vec<Arc<SsTable>>> {
+        let gc_watermark = self.gc_watermark.load(Ordering::SeqCst);
+        let now = value::unix_millis();
+        // Only full compactions exist, they write L1, the last level, and nothing lies below it.
+        let mut output = CompactionOutput::new(
+            self,
+            CompactionFilterContext {
+                level: 1,
+                is_bottommost: true,
+            },
+        );
+
+        let mut prev_user_key = Vec::new();
+        let mut shadowed = false;
+        while iter.is_valid() {
+            let key = iter.key().to_vec();
+            let record = Record::decode(iter.value())?.expire(now);
+            let record = range_tombstones.mask(&key, record);
+            if self.options.enable_user_timestamp {
+                // Versions of a user key are ordered by timestamp descending. Keep everything
+                // above the watermark and the newest version at or below it.
+                let user_key = key::user_key_prefix(&key);
+                if user_key != prev_user_key {
+                    prev_user_key = user_key.to_vec();
+                    shadowed = false;
+                }
+                let keep = if key::ts_of(&key) > gc_watermark {
+                    true
+                } else if shadowed {
+                    false
+                } else {
+                    shadowed = true;
+                    !record.is_tombstone()
+                };
+                if keep {
+                    output.add(&key, iter.value())?;
+                }
+            } else {
+                match record.kind {
+                    ValueKind::Put => output.add(&key, iter.value())?,
+                    ValueKind::Delete => {}
+                    ValueKind::Merge => {
+                        // Nothing older survives the compaction, fold the operands into a put.
+                        let seq = record.seq;
+                        let mut operands = MergeOperands::default();
+                        while iter.is_valid() && iter.key() == key {
+                            let record = Record::decode(iter.value())?.expire(now);
+                            if !operands.push(&range_tombstones.mask(&key, record))? {
+                                break;
+                            }
+                            iter.next()?;
+                        }
+                        let expire_at = operands.expire_at();
+                        if let Some(value) =
+                            operands.finish(&key, self.options.merge_operator.as_deref())?
+                        {
+                            let record =
+                                Record::encode_with_expiry(seq, ValueKind::Put, expire_at, &value);
+                            output.add(&key, &record)?;
+                        }
+                    }
+                }
+            }
+
+            // Older versions of the key are shadowed by the one handled above.
+            while iter.is_valid() && iter.key() == key {
+                iter.next()?;
+            }
+        }
+        output.finish()
+    }
+
+    /// Get the smallest and largest key the keys and range tombstones of `tables` span, `None` if
+    /// they have neither.
+    fn key_range(&self, tables: &[Arc<SsTable>]) -> Option<(Bytes, Bytes)> {
+        let comparator = &*self.options.comparator;
+        let mut range: Option<(Bytes, Bytes)> = None;
+        let mut extend = |first: &Bytes, last: &Bytes| {
+            range = Some(match range.take() {
+                Some((min, max)) => (
+                    cmp::min_by(min, first.clone(), |a, b| comparator.compare(a, b)),
+                    cmp::max_by(max, last.clone(), |a, b| comparator.compare(a, b)),
+                ),
+                None => (first.clone(), last.clone()),
+            });
+        };
+        for table in tables {
+            let properties = table.properties();
+            if !properties.first_key.is_empty() {
+                extend(&properties.first_key, &properties.last_key);
+            }
+            for tombstone in table.range_tombstones() {
+                extend(&tombstone.start, &tombstone.end);
+            }
+        }
+        range
+    }
+
+    /// Check if a full compaction would leave the bottommost SST `table` as it is: it holds no
+    /// deletions or expiring entries to drop, and there is no compaction filter or timestamp
+    /// watermark to apply.
+    fn may_move(&self, table: &SsTable) -> bool {
+        let properties = table.properties();
+        // SSTs of format version 1 did not record their counts.
+        table.format_version() > 1
+            && properties.num_tombstones == 0
+            && properties.num_expiring_entries == 0
+            && properties.num_range_tombstones == 0
+            && self.options.compaction_filter_factory.is_none()
+            && !self.options.enable_user_timestamp
+    }
+
+    fn build_sst(&self, builder: SsTableBuilder) -> Result<Arc<SsTable>> {
+        let sst_id = self.next_sst_id();
+        Ok(Arc::new(builder.build(
+            sst_id,
+            Some(self.block_cache.clone()),
+            self.path_of_sst(sst_id),
+        )?))
+    }
+}
+
+/// Splits the output of a compaction into SSTs of about `target_sst_size` bytes, passing values
+/// through the compaction filter on the way.
+struct CompactionOutput<'a> {
+    storage: &'a LsmStorage,
+    filter: Option<Box<dyn CompactionFilter>>,
+    level: usize,
+    builder: Option<SsTableBuilder>,
+    sstables: Vec<Arc<SsTable>>,
+}
+
+impl<'a> CompactionOutput<'a> {
+    fn new(storage: &'a LsmStorage, context: CompactionFilterContext) -> Self {
+        let filter = storage
+            .options
+            .compaction_filter_factory
+            .as_ref()
+            .map(|factory| factory.create(&context));
+        Self {
+            storage,
+            filter,
+            level: context.level,
+            builder: None,
+            sstables: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        let enable_user_timestamp = self.storage.options.enable_user_timestamp;
+        let record = Record::decode(value)?;
+        let decision = match self.filter.as_mut() {
+            Some(filter) if record.kind == ValueKind::Put => {
+                if enable_user_timestamp {
+                    filter.filter(&key::user_key_of(key), record.value)
+                } else {
+                    filter.filter(key, record.value)
+                }
+            }
+            _ => FilterDecision::Keep,
+        };
+        match decision {
+            FilterDecision::Keep => self.add_to_builder(key, value),
+            // The compaction writes the bottommost level, so nothing older survives below the
+            // output. With user timestamps, older versions of the user key may still follow in
+            // the output itself, and a tombstone is needed to hide them.
+            FilterDecision::Remove if enable_user_timestamp => {
+                let tombstone = Record::encode(record.seq, ValueKind::Delete, &[]);
+                self.add_to_builder(key, &tombstone)
+            }
+            FilterDecision::Remove => Ok(()),
+            FilterDecision::ChangeValue(value) => {
+                let record = Record::encode_with_expiry(
+                    record.seq,
+                    ValueKind::Put,
+                    record.expire_at,
+                    &value,
+                );
+                self.add_to_builder(key, &record)
+            }
+        }
+    }
+
+    fn add_to_builder(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        let builder = self
+            .builder
+            .get_or_insert_with(|| self.storage.sst_builder(self.level));
+        builder.add(key, value);
+        if builder.estimated_size() >= self.storage.options.target_sst_size {
+            let builder = self.builder.take().unwrap();
+            self.sstables.push(self.storage.build_sst(builder)?);
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<Arc<SsTable>>> {
+        if let Some(builder) = self.builder.take() {
+            self.sstables.push(self.storage.build_sst(builder)?);
+        }
+        Ok(self.sstables)
+    }
+}
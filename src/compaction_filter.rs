@@ -0,0 +1,46 @@
+use std::fmt;
+
+/// What to do with an entry handed to a `CompactionFilter`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FilterDecision {
+    /// Write the entry unchanged.
+    Keep,
+    /// Drop the entry, the key reads as deleted afterwards.
+    Remove,
+    /// Write the entry with a new value.
+    ChangeValue(Vec<u8>),
+}
+
+/// Describes the compaction job a `CompactionFilter` is created for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompactionFilterContext {
+    /// The level the compaction writes to, 1 for the first level below L0.
+    pub level: usize,
+    /// Whether no older data lies below the output of the compaction.
+    pub is_bottommost: bool,
+}
+
+/// Decides, while compaction writes its output, whether to keep, drop or rewrite each value.
+/// Filters only see values, deletions and versions shadowed by newer ones are handled by the
+/// compaction itself.
+pub trait CompactionFilter {
+    /// Called with each value about to be added to an output SST, in key order. With user
+    /// timestamps enabled, `key` is the user key and each version is passed separately.
+    fn filter(&mut self, key: &[u8], value: &[u8]) -> FilterDecision;
+}
+
+/// Creates a `CompactionFilter` for each compaction job. Registered through
+/// `LsmStorageOptions::compaction_filter_factory`.
+pub trait CompactionFilterFactory: Send + Sync {
+    /// The name of the filter.
+    fn name(&self) -> &str;
+
+    /// Create the filter of a compaction job.
+    fn create(&self, context: &CompactionFilterContext) -> Box<dyn CompactionFilter>;
+}
+
+impl fmt::Debug for dyn CompactionFilterFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
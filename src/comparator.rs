@@ -0,0 +1,150 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Bound;
+
+use bytes::Bytes;
+
+/// Orders the keys of the storage. Configured through `LsmStorageOptions::comparator` and recorded
+/// in the manifest and in each SST under its name, opening either with another comparator fails.
+///
+/// Two keys may only compare equal if their bytes are equal, as versions of a key, hash indexes
+/// and filters find keys by their bytes.
+pub trait Comparator: Send + Sync {
+    /// The name identifying the ordering. Comparators ordering keys differently must have
+    /// different names.
+    fn name(&self) -> &str;
+
+    /// Compare two keys.
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering;
+
+    /// Get a key at or after `last_key` and before `next_key`, which follows it, as short as
+    /// possible. It separates two data blocks in the index of an SST. The default does not
+    /// shorten `last_key`.
+    fn shortest_separator(&self, last_key: &[u8], next_key: &[u8]) -> Bytes {
+        let _ = next_key;
+        Bytes::copy_from_slice(last_key)
+    }
+
+    /// Get a key at or after `key`, as short as possible. It separates the last data block of an
+    /// SST from the keys after it. The default does not shorten `key`.
+    fn shortest_successor(&self, key: &[u8]) -> Bytes {
+        Bytes::copy_from_slice(key)
+    }
+}
+
+impl fmt::Debug for dyn Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl dyn Comparator + '_ {
+    /// Check if `key` is at or after `lower`.
+    pub fn is_above(&self, key: &[u8], lower: Bound<&[u8]>) -> bool {
+        match lower {
+            Bound::Included(lower) => self.compare(key, lower).is_ge(),
+            Bound::Excluded(lower) => self.compare(key, lower).is_gt(),
+            Bound::Unbounded => true,
+        }
+    }
+
+    /// Check if `key` is at or before `upper`.
+    pub fn is_below(&self, key: &[u8], upper: Bound<&[u8]>) -> bool {
+        match upper {
+            Bound::Included(upper) => self.compare(key, upper).is_le(),
+            Bound::Excluded(upper) => self.compare(key, upper).is_lt(),
+            Bound::Unbounded => true,
+        }
+    }
+}
+
+/// Orders keys by their bytes, the default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BytewiseComparator;
+
+impl Comparator for BytewiseComparator {
+    fn name(&self) -> &str {
+        "minilsm.BytewiseComparator"
+    }
+
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        a.cmp(b)
+    }
+
+    fn shortest_separator(&self, last_key: &[u8], next_key: &[u8]) -> Bytes {
+        let shared = last_key
+            .iter()
+            .zip(next_key)
+            .take_while(|(a, b)| a == b)
+            .count();
+        if shared == last_key.len() {
+            // `last_key` is a prefix of `next_key`.
+            return Bytes::copy_from_slice(last_key);
+        }
+        // Incrementing a byte of `last_key` and dropping the ones after it gives a key after it.
+        // It stays before `next_key` if the byte is past the shared prefix or still below the
+        // byte of `next_key`.
+        let pos = (shared..last_key.len()).find(|pos| match pos.cmp(&shared) {
+            Ordering::Equal => last_key[shared] as u16 + 1 < next_key[shared] as u16,
+            _ => last_key[*pos] < 0xff,
+        });
+        match pos {
+            Some(pos) if pos + 1 < last_key.len() => {
+                let mut separator = last_key[..=pos].to_vec();
+                separator[pos] += 1;
+                separator.into()
+            }
+            _ => Bytes::copy_from_slice(last_key),
+        }
+    }
+
+    fn shortest_successor(&self, key: &[u8]) -> Bytes {
+        match key.iter().position(|byte| *byte < 0xff) {
+            Some(pos) => {
+                let mut successor = key[..=pos].to_vec();
+                successor[pos] += 1;
+                successor.into()
+            }
+            None => Bytes::copy_from_slice(key),
+        }
+    }
+}
+
+/// Orders keys by their bytes, descending.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReverseBytewiseComparator;
+
+impl Comparator for ReverseBytewiseComparator {
+    fn name(&self) -> &str {
+        "minilsm.ReverseBytewiseComparator"
+    }
+
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        b.cmp(a)
+    }
+}
+
+/// Orders keys as unsigned big-endian integers, such as `u64::to_be_bytes`. Keys of any length are
+/// accepted: leading zero bytes do not change the value, keys of the same value are ordered by
+/// length.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct U64BigEndianComparator;
+
+impl Comparator for U64BigEndianComparator {
+    fn name(&self) -> &str {
+        "minilsm.U64BigEndianComparator"
+    }
+
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        let trim = |key: &[u8]| -> usize { key.iter().take_while(|byte| **byte == 0).count() };
+        let (digits_a, digits_b) = (&a[trim(a)..], &b[trim(b)..]);
+        digits_a
+            .len()
+            .cmp(&digits_b.len())
+            .then_with(|| digits_a.cmp(digits_b))
+            .then(a.len().cmp(&b.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,43 @@
+use super::*;
+
+#[test]
+fn test_bytewise_separator() {
+    let separator =
+        |last: &[u8], next: &[u8]| BytewiseComparator.shortest_separator(last, next).to_vec();
+    assert_eq!(separator(b"abc1999", b"abd"), b"abc2");
+    assert_eq!(separator(b"abc1", b"abe"), b"abd");
+    assert_eq!(separator(b"abcxyz", b"abd"), b"abcy");
+    // Nothing shorter between the two keys.
+    assert_eq!(separator(b"abc", b"abd"), b"abc");
+    assert_eq!(separator(b"ab", b"abc"), b"ab");
+    assert_eq!(separator(b"a\xff\xff", b"b"), b"a\xff\xff");
+    let successor = |key: &[u8]| BytewiseComparator.shortest_successor(key).to_vec();
+    assert_eq!(successor(b"abc"), b"b");
+    assert_eq!(successor(b"\xff\xffa"), b"\xff\xffb");
+    assert_eq!(successor(b"\xff\xff"), b"\xff\xff");
+}
+
+#[test]
+fn test_comparators() {
+    assert!(BytewiseComparator.compare(b"a", b"b").is_lt());
+    assert!(ReverseBytewiseComparator.compare(b"a", b"b").is_gt());
+    assert!(ReverseBytewiseComparator.compare(b"ab", b"a").is_lt());
+    assert_eq!(
+        ReverseBytewiseComparator.shortest_separator(b"b", b"a"),
+        Bytes::from_static(b"b")
+    );
+
+    let u64 = U64BigEndianComparator;
+    let (one, two, big) = (1u64.to_be_bytes(), 2u64.to_be_bytes(), 256u64.to_be_bytes());
+    assert!(u64.compare(&one, &two).is_lt());
+    assert!(u64.compare(&big, &two).is_gt());
+    assert!(u64.compare(&[2], &[1, 0]).is_lt());
+    assert!(u64.compare(&[1, 0], &big).is_lt());
+    assert!(u64.compare(&big, &big).is_eq());
+
+    let comparator: &dyn Comparator = &ReverseBytewiseComparator;
+    assert!(comparator.is_above(b"a", Bound::Included(b"b")));
+    assert!(!comparator.is_above(b"b", Bound::Excluded(b"b")));
+    assert!(comparator.is_below(b"c", Bound::Excluded(b"b")));
+    assert!(comparator.is_below(b"c", Bound::Unbounded));
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+
+/// The type byte of blocks stored without compression.
+pub const NO_COMPRESSION: u8 = 0;
+
+/// Compresses SST data blocks. Every block records the `id` of the codec it was compressed with,
+/// so a codec must keep its id and stay able to decompress what it wrote. Codecs are configured
+/// per level through `LsmStorageOptions::compression_per_level`. SSTs with blocks compressed by a
+/// codec that is not built-in are opened with `SsTable::open_with_codecs`.
+pub trait CompressionCodec: Send + Sync {
+    /// The type byte of the blocks compressed by this codec. Built-in codecs use ids below 128,
+    /// `NO_COMPRESSION` is reserved.
+    fn id(&self) -> u8;
+
+    /// The name of the codec.
+    fn name(&self) -> &str;
+
+    fn compress(&self, data: &[u8]) -> Vec<u8>;
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>>;
+}
+
+impl fmt::Debug for dyn CompressionCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// LZ4 block compression, fast to compress and to decompress.
+pub struct Lz4Codec;
+
+impl CompressionCodec for Lz4Codec {
+    fn id(&self) -> u8 {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "lz4"
+    }
+
+    fn compress(&self, data: &[u8]) -> Vec<u8> {
+        lz4_flex::compress_prepend_size(data)
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        lz4_flex::decompress_size_prepended(data).map_err(|e| anyhow!("lz4: {}", e))
+    }
+}
+
+/// Get the built-in codec with type byte `id`.
+pub fn builtin_codec(id: u8) -> Option<Arc<dyn CompressionCodec>> {
+    match id {
+        1 => Some(Arc::new(Lz4Codec)),
+        _ => None,
+    }
+}
+
+/// Compress a block with `codec`, returning its type byte and stored bytes. Blocks compressing
+/// to more than 7/8 of their size are kept uncompressed, decompressing them would not pay off.
+pub(crate) fn compress_block(codec: Option<&dyn CompressionCodec>, data: &[u8]) -> (u8, Vec<u8>) {
+    if let Some(codec) = codec {
+        let compressed = codec.compress(data);
+        if compressed.len() <= data.len() - data.len() / 8 {
+            return (codec.id(), compressed);
+        }
+    }
+    (NO_COMPRESSION, data.to_vec())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,28 @@
+use super::*;
+
+#[test]
+fn test_lz4_codec() {
+    let data = br#"{"name":"value","name2":"value","name3":"value"}"#.repeat(20);
+    let compressed = Lz4Codec.compress(&data);
+    assert!(compressed.len() < data.len() / 4);
+    assert_eq!(Lz4Codec.decompress(&compressed).unwrap(), data);
+    assert!(Lz4Codec
+        .decompress(&compressed[..compressed.len() / 2])
+        .is_err());
+    assert_eq!(builtin_codec(Lz4Codec.id()).unwrap().name(), "lz4");
+}
+
+#[test]
+fn test_compress_block_fallback() {
+    let data = br#"{"name":"value"}"#.repeat(20);
+    let (kind, compressed) = compress_block(Some(&Lz4Codec), &data);
+    assert_eq!(kind, Lz4Codec.id());
+    assert!(compressed.len() < data.len());
+
+    // Bytes that do not repeat are not worth compressing.
+    let data: Vec<u8> = (0..=255).collect();
+    let (kind, stored) = compress_block(Some(&Lz4Codec), &data);
+    assert_eq!(kind, NO_COMPRESSION);
+    assert_eq!(stored, data);
+    assert_eq!(compress_block(None, &data).0, NO_COMPRESSION);
+}
@@ -0,0 +1,165 @@
+mod bloom;
+mod xor;
+
+use std::f64::consts::LN_2;
+use std::fmt;
+use std::sync::Arc;
+
+/// Builds the filters of SSTs, which tell that a key is absent or may be present: the filter of
+/// the keys checked by point lookups, and the filter of the prefixes checked by
+/// `LsmStorage::scan_prefix`. Every filter records the `id` of the policy it was built with, so a
+/// policy must keep its id and stay able to read what it built. Policies are configured per level
+/// through `LsmStorageOptions::filter_policy_per_level`.
+pub trait FilterPolicy: Send + Sync {
+    /// The id recorded with the filters built by this policy. Built-in policies use ids below 128.
+    fn id(&self) -> u8;
+
+    /// The name of the policy.
+    fn name(&self) -> &str;
+
+    /// Build a filter of the keys hashed to `hashes` by `hash`, with about `bits_per_key` bits for
+    /// each if the policy can trade its size for its false positive rate.
+    fn build(&self, hashes: &[u64], bits_per_key: f64) -> Vec<u8>;
+
+    /// Check if the key hashed to `hash` may be in `filter`. Filters that cannot be read may
+    /// contain any key.
+    fn may_contain(&self, filter: &[u8], hash: u64) -> bool;
+}
+
+impl fmt::Debug for dyn FilterPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Bloom filters, with a false positive rate of about 1% at 10 bits per key.
+pub struct BloomFilterPolicy;
+
+impl FilterPolicy for BloomFilterPolicy {
+    fn id(&self) -> u8 {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "bloom"
+    }
+
+    fn build(&self, hashes: &[u64], bits_per_key: f64) -> Vec<u8> {
+        bloom::build(hashes, bits_per_key)
+    }
+
+    fn may_contain(&self, filter: &[u8], hash: u64) -> bool {
+        bloom::may_contain(filter, hash)
+    }
+}
+
+/// Xor filters with 8-bit fingerprints, taking about 9.9 bits per key for a false positive rate
+/// of about 0.4%, which a bloom filter needs about 12 bits per key for. They are built in a single
+/// size, ignoring the bits per key.
+pub struct XorFilterPolicy;
+
+impl FilterPolicy for XorFilterPolicy {
+    fn id(&self) -> u8 {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "xor8"
+    }
+
+    fn build(&self, hashes: &[u64], _bits_per_key: f64) -> Vec<u8> {
+        xor::build(hashes)
+    }
+
+    fn may_contain(&self, filter: &[u8], hash: u64) -> bool {
+        xor::may_contain(filter, hash)
+    }
+}
+
+/// Get the built-in filter policy with `id`.
+pub fn builtin_filter_policy(id: u8) -> Option<Arc<dyn FilterPolicy>> {
+    match id {
+        1 => Some(Arc::new(BloomFilterPolicy)),
+        2 => Some(Arc::new(XorFilterPolicy)),
+        _ => None,
+    }
+}
+
+/// How the memory of the filters is shared between the levels, for the policies that can trade
+/// the size of filters for their false positive rate. Configured through
+/// `LsmStorageOptions::filter_bits_allocation`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilterBitsAllocation {
+    /// Every level gets the same bits per key.
+    #[default]
+    Uniform,
+    /// Every level gets the bits per key minimizing the expected number of SSTs a lookup of an
+    /// absent key reads, for the same total memory, as in "Monkey: Optimal Navigable Key-Value
+    /// Store" (Dayan et al., 2017). Large levels, which hold most of the keys, get fewer bits per
+    /// key, and small levels more.
+    Optimal,
+}
+
+impl FilterBitsAllocation {
+    /// Get the bits per key of the filters of each level, given the number of keys of the level
+    /// and of its sorted runs, which lookups check one by one. On average, keys get `bits_per_key`
+    /// bits. Levels without keys get `bits_per_key`.
+    pub fn allocate(&self, levels: &[(u64, usize)], bits_per_key: f64) -> Vec<f64> {
+        let mut allocation = vec![bits_per_key; levels.len()];
+        if *self == FilterBitsAllocation::Uniform {
+            return allocation;
+        }
+        // A bloom filter with b bits per key has a false positive rate of about
+        // p = exp(-b ln(2)^2). The sum of the false positive rates of the runs is minimal for the
+        // memory if the rate of each level is proportional to the size of its runs, p_i = c s_i.
+        let ln2_squared = LN_2 * LN_2;
+        let mut levels: Vec<(usize, f64, f64)> = levels
+            .iter()
+            .enumerate()
+            .filter(|(_, (keys, runs))| *keys > 0 && *runs > 0)
+            .map(|(idx, &(keys, runs))| (idx, keys as f64, (keys as f64 / runs as f64).ln()))
+            .collect();
+        let budget = levels.iter().map(|(_, keys, _)| keys).sum::<f64>() * bits_per_key;
+        while !levels.is_empty() {
+            let keys: f64 = levels.iter().map(|(_, keys, _)| keys).sum();
+            let weighted: f64 = levels.iter().map(|(_, keys, ln_size)| keys * ln_size).sum();
+            // -ln(c), so that the bits of the levels add up to the budget.
+            let neg_ln_c = (budget * ln2_squared + weighted) / keys;
+            // Levels whose runs are so large that their false positive rate would reach 1 are
+            // better off without filters, the others share their memory.
+            let num_levels = levels.len();
+            levels.retain(|&(idx, _, ln_size)| {
+                let bits = (neg_ln_c - ln_size) / ln2_squared;
+                allocation[idx] = bits.max(0.0);
+                bits > 0.0
+            });
+            if levels.len() == num_levels {
+                break;
+            }
+        }
+        allocation
+    }
+}
+
+/// Hash a key for a filter: FNV-1a, with the bits spread by `mix`.
+pub fn hash(key: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for byte in key {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    mix(hash)
+}
+
+/// The finalizer of SplitMix64, each bit of the input flipping each bit of the output with a
+/// probability of about one half.
+fn mix(mut x: u64) -> u64 {
+    x ^= x >> 30;
+    x = x.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x ^= x >> 27;
+    x = x.wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests;
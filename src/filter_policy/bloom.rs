@@ -0,0 +1,44 @@
+//! Bloom filters:
+//!
+//! | bits | k (u8) |
+//!
+//! Each key sets `k` bits, found by double hashing from the two halves of its 64-bit hash.
+
+/// Build a filter of the keys hashed to `hashes` with about `bits_per_key` bits for each, which
+/// gives a false positive rate of about 1% for 10 bits.
+pub(super) fn build(hashes: &[u64], bits_per_key: f64) -> Vec<u8> {
+    // ln 2 times the bits per key minimizes the false positive rate.
+    let k = ((bits_per_key * 0.69) as u8).clamp(1, 30);
+    let num_bits = ((hashes.len() as f64 * bits_per_key).ceil() as usize).max(64);
+    let mut filter = vec![0u8; num_bits.div_ceil(8)];
+    let num_bits = filter.len() * 8;
+    for &hash in hashes {
+        for bit in bits(hash, k, num_bits) {
+            filter[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+    filter.push(k);
+    filter
+}
+
+/// Check if the key hashed to `hash` may be in `filter`.
+pub(super) fn may_contain(filter: &[u8], hash: u64) -> bool {
+    let Some((&k, filter)) = filter.split_last() else {
+        return true;
+    };
+    if filter.is_empty() {
+        return true;
+    }
+    bits(hash, k, filter.len() * 8).all(|bit| filter[bit / 8] & (1 << (bit % 8)) != 0)
+}
+
+/// Get the `k` bits set by the key hashed to `hash`.
+fn bits(hash: u64, k: u8, num_bits: usize) -> impl Iterator<Item = usize> {
+    let delta = (hash >> 32) as u32;
+    let mut hash = hash as u32;
+    (0..k).map(move |_| {
+        let bit = hash as usize % num_bits;
+        hash = hash.wrapping_add(delta);
+        bit
+    })
+}
@@ -0,0 +1,100 @@
+use super::*;
+
+/// Build a filter of 10000 keys with `policy`, check that it has them all and count the false
+/// positives among 10000 other keys. Returns the size of the filter and the false positives.
+fn check_policy(policy: &dyn FilterPolicy, bits_per_key: f64) -> (usize, usize) {
+    let hashes: Vec<u64> = (0..10000)
+        .map(|i| hash(format!("key_{}", i).as_bytes()))
+        .collect();
+    let filter = policy.build(&hashes, bits_per_key);
+    for &hash in &hashes {
+        assert!(policy.may_contain(&filter, hash));
+    }
+    let false_positives = (0..10000)
+        .filter(|i| policy.may_contain(&filter, hash(format!("other_{}", i).as_bytes())))
+        .count();
+    (filter.len(), false_positives)
+}
+
+#[test]
+fn test_bloom_filter_policy() {
+    let (size, false_positives) = check_policy(&BloomFilterPolicy, 10.0);
+    assert_eq!(size, 10000 * 10 / 8 + 1);
+    assert!(false_positives < 200, "{} false positives", false_positives);
+    let (_, more_false_positives) = check_policy(&BloomFilterPolicy, 4.0);
+    assert!(more_false_positives > false_positives);
+}
+
+#[test]
+fn test_xor_filter_policy() {
+    let (size, false_positives) = check_policy(&XorFilterPolicy, 10.0);
+    assert!(size < 10000 * 10 / 8, "{} bytes", size);
+    assert!(false_positives < 80, "{} false positives", false_positives);
+
+    // Duplicate keys and empty filters.
+    let filter = XorFilterPolicy.build(&[1, 2, 2, 3], 10.0);
+    assert!([1, 2, 3]
+        .iter()
+        .all(|&h| XorFilterPolicy.may_contain(&filter, h)));
+    let filter = XorFilterPolicy.build(&[], 10.0);
+    let false_positives = (0..100)
+        .filter(|&h| XorFilterPolicy.may_contain(&filter, hash(&[h])))
+        .count();
+    assert!(false_positives < 5);
+}
+
+#[test]
+fn test_builtin_filter_policies() {
+    for policy in [
+        Arc::new(BloomFilterPolicy) as Arc<dyn FilterPolicy>,
+        Arc::new(XorFilterPolicy),
+    ] {
+        assert_eq!(
+            builtin_filter_policy(policy.id()).unwrap().name(),
+            policy.name()
+        );
+        // Filters which cannot be read rule nothing out.
+        assert!(policy.may_contain(&[], 42));
+    }
+    assert!(builtin_filter_policy(0).is_none());
+}
+
+/// The expected number of runs a lookup of an absent key reads through bloom filters with the
+/// bits per key of each level.
+fn expected_reads(levels: &[(u64, usize)], allocation: &[f64]) -> f64 {
+    let ln2_squared = std::f64::consts::LN_2.powi(2);
+    levels
+        .iter()
+        .zip(allocation)
+        .map(|(&(_, runs), bits)| runs as f64 * (-bits * ln2_squared).exp().min(1.0))
+        .sum()
+}
+
+#[test]
+fn test_filter_bits_allocation() {
+    // Four L0 SSTs, an empty level and two levels ten times larger than the one before.
+    let levels = [(4000, 4), (0, 0), (10_000, 1), (100_000, 1)];
+    let uniform = FilterBitsAllocation::Uniform.allocate(&levels, 10.0);
+    assert_eq!(uniform, vec![10.0; 4]);
+
+    let optimal = FilterBitsAllocation::Optimal.allocate(&levels, 10.0);
+    assert!(optimal[0] > optimal[2] && optimal[2] > optimal[3]);
+    assert!(optimal[3] < 10.0);
+    assert_eq!(optimal[1], 10.0);
+    // The same memory, fewer reads.
+    let memory = |allocation: &[f64]| -> f64 {
+        levels
+            .iter()
+            .zip(allocation)
+            .map(|(&(keys, _), bits)| keys as f64 * bits)
+            .sum()
+    };
+    assert!((memory(&optimal) - memory(&uniform)).abs() < 1e-6 * memory(&uniform));
+    assert!(expected_reads(&levels, &optimal) < expected_reads(&levels, &uniform));
+
+    // With little memory, the largest level gets no filter at all.
+    let optimal = FilterBitsAllocation::Optimal.allocate(&levels, 0.5);
+    assert_eq!(optimal[3], 0.0);
+    assert!((memory(&optimal) - memory(&[0.5; 4])).abs() < 1e-6 * memory(&[0.5; 4]));
+    assert!(FilterBitsAllocation::Optimal.allocate(&[], 10.0).is_empty());
+}
@@ -0,0 +1,104 @@
+//! Xor filters with 8-bit fingerprints, as described in "Xor Filters: Faster and Smaller Than
+//! Bloom and Cuckoo Filters" (Graf and Lemire, 2020):
+//!
+//! | seed (u64) | block length (u32) | fingerprints (u8), 3 blocks |
+//!
+//! Each key maps to a slot in each of the three blocks, the fingerprints of which xor to the
+//! fingerprint of the key. All fields are little-endian.
+
+use super::mix;
+
+const HEADER_SIZE: usize = 12;
+
+pub(super) fn build(hashes: &[u64]) -> Vec<u8> {
+    // Keys hashed alike are the same key to the filter, and would keep it from being built.
+    let mut keys = hashes.to_vec();
+    keys.sort_unstable();
+    keys.dedup();
+    let block_len = ((keys.len() as f64 * 1.23) as usize + 32) / 3;
+    let num_slots = 3 * block_len;
+    // Mapping keys to slots fails with a small probability, another seed then maps them again.
+    let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+    loop {
+        if let Some(fingerprints) = assign(&keys, seed, block_len, num_slots) {
+            let mut filter = Vec::with_capacity(HEADER_SIZE + num_slots);
+            filter.extend_from_slice(&seed.to_le_bytes());
+            filter.extend_from_slice(&(block_len as u32).to_le_bytes());
+            filter.extend_from_slice(&fingerprints);
+            return filter;
+        }
+        seed = mix(seed);
+    }
+}
+
+/// Find fingerprints for the slots such that those of the slots of each key xor to its
+/// fingerprint, by peeling off the keys that are alone in one of their slots.
+fn assign(keys: &[u64], seed: u64, block_len: usize, num_slots: usize) -> Option<Vec<u8>> {
+    let mut counts = vec![0u32; num_slots];
+    // The xor of the hashes of the keys in each slot, the hash of the key alone in it.
+    let mut xors = vec![0u64; num_slots];
+    for &key in keys {
+        let hash = mix(key.wrapping_add(seed));
+        for slot in slots(hash, block_len) {
+            counts[slot] += 1;
+            xors[slot] ^= hash;
+        }
+    }
+    let mut alone: Vec<usize> = (0..num_slots).filter(|&slot| counts[slot] == 1).collect();
+    let mut peeled = Vec::with_capacity(keys.len());
+    while let Some(slot) = alone.pop() {
+        if counts[slot] != 1 {
+            continue;
+        }
+        let hash = xors[slot];
+        peeled.push((hash, slot));
+        for other in slots(hash, block_len) {
+            counts[other] -= 1;
+            xors[other] ^= hash;
+            if counts[other] == 1 {
+                alone.push(other);
+            }
+        }
+    }
+    if peeled.len() < keys.len() {
+        return None;
+    }
+    // Each key is given the fingerprint of the slot it was alone in, which slots peeled after it
+    // no longer change.
+    let mut fingerprints = vec![0u8; num_slots];
+    for (hash, slot) in peeled.into_iter().rev() {
+        let [a, b, c] = slots(hash, block_len);
+        fingerprints[slot] =
+            fingerprint(hash) ^ fingerprints[a] ^ fingerprints[b] ^ fingerprints[c];
+    }
+    Some(fingerprints)
+}
+
+pub(super) fn may_contain(filter: &[u8], hash: u64) -> bool {
+    if filter.len() < HEADER_SIZE {
+        return true;
+    }
+    let seed = u64::from_le_bytes(filter[..8].try_into().unwrap());
+    let block_len = u32::from_le_bytes(filter[8..HEADER_SIZE].try_into().unwrap()) as usize;
+    let fingerprints = &filter[HEADER_SIZE..];
+    if block_len == 0 || fingerprints.len() != 3 * block_len {
+        return true;
+    }
+    let hash = mix(hash.wrapping_add(seed));
+    let [a, b, c] = slots(hash, block_len);
+    fingerprint(hash) == fingerprints[a] ^ fingerprints[b] ^ fingerprints[c]
+}
+
+fn fingerprint(hash: u64) -> u8 {
+    (hash ^ (hash >> 32)) as u8
+}
+
+/// Get the slot of the key hashed to `hash` in each block.
+fn slots(hash: u64, block_len: usize) -> [usize; 3] {
+    let reduce = |x: u64| ((x as u32 as u64 * block_len as u64) >> 32) as usize;
+    [
+        reduce(hash),
+        block_len + reduce(hash.rotate_left(21)),
+        2 * block_len + reduce(hash.rotate_left(42)),
+    ]
+}
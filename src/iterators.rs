@@ -15,5 +15,86 @@ pub trait StorageIterator {
     fn next(&mut self) -> anyhow::Result<()>;
 }
 
+/// An iterator which can also move backwards and be repositioned.
+pub trait BidirectionalIterator: StorageIterator {
+    /// Move to the previous position.
+    fn prev(&mut self) -> anyhow::Result<()>;
+
+    /// Move to the first position.
+    fn seek_to_first(&mut self) -> anyhow::Result<()>;
+
+    /// Move to the last position.
+    fn seek_to_last(&mut self) -> anyhow::Result<()>;
+
+    /// Move to the first position whose key is at or after `key`.
+    fn seek(&mut self, key: &[u8]) -> anyhow::Result<()>;
+
+    /// Move to the last position whose key is at or before `key`.
+    fn seek_for_prev(&mut self, key: &[u8]) -> anyhow::Result<()>;
+}
+
+/// Turn one of the iterators merged by a merge iterator around, when the merge iterator changes
+/// direction at `key`. If `current`, the iterator holds the current entry and takes a step in the
+/// new direction. Otherwise it is positioned just past the current entry in the old direction, or
+/// ran out of entries: a step back, or a seek to the other end, puts it just before the current
+/// entry in the new direction. With `dedup`, entries with `key` are skipped as well.
+pub(crate) fn turn_around<I: BidirectionalIterator>(
+    iter: &mut I,
+    current: bool,
+    backward: bool,
+    dedup: bool,
+    key: &[u8],
+) -> anyhow::Result<()> {
+    match (current || iter.is_valid(), backward) {
+        (true, true) => iter.prev()?,
+        (true, false) => iter.next()?,
+        (false, true) => iter.seek_to_last()?,
+        (false, false) => iter.seek_to_first()?,
+    }
+    while dedup && iter.is_valid() && iter.key() == key {
+        if backward {
+            iter.prev()?;
+        } else {
+            iter.next()?;
+        }
+    }
+    Ok(())
+}
+
+/// Iterates on a `BidirectionalIterator` backwards: `next` moves to the previous position.
+pub struct RevIterator<I: BidirectionalIterator> {
+    iter: I,
+}
+
+impl<I: BidirectionalIterator> RevIterator<I> {
+    /// Iterate backwards from the current position of `iter`.
+    pub fn new(iter: I) -> Self {
+        Self { iter }
+    }
+
+    /// Get the underlying iterator.
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+}
+
+impl<I: BidirectionalIterator> StorageIterator for RevIterator<I> {
+    fn value(&self) -> &[u8] {
+        self.iter.value()
+    }
+
+    fn key(&self) -> &[u8] {
+        self.iter.key()
+    }
+
+    fn is_valid(&self) -> bool {
+        self.iter.is_valid()
+    }
+
+    fn next(&mut self) -> anyhow::Result<()> {
+        self.iter.prev()
+    }
+}
+
 #[cfg(test)]
 mod tests;
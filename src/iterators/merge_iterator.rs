@@ -5,17 +5,29 @@
 use std::cmp::{self};
 use std::collections::binary_heap::PeekMut;
 use std::collections::BinaryHeap;
-use std::usize;
+use std::sync::Arc;
 
 use anyhow::Result;
 
-use super::StorageIterator;
+use super::{turn_around, BidirectionalIterator, StorageIterator};
+use crate::comparator::{BytewiseComparator, Comparator};
 
-struct HeapWrapper<I: StorageIterator>(pub usize, pub Box<I>);
+/// The order in which the heap yields iterators. Forwards, the smallest key comes first, and
+/// iterators sharing it come by their index. Backwards, the largest key comes first, and iterators
+/// sharing it come by their index reversed, so that entries come in the reverse of the forward
+/// order. When duplicate keys are skipped, the entry kept still comes from the smallest index.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HeapOrder {
+    Forward,
+    Backward,
+    BackwardDedup,
+}
+
+struct HeapWrapper<I: StorageIterator>(pub usize, pub Box<I>, Arc<dyn Comparator>, HeapOrder);
 
 impl<I: StorageIterator> PartialEq for HeapWrapper<I> {
     fn eq(&self, other: &Self) -> bool {
-        self.partial_cmp(other).unwrap() == cmp::Ordering::Equal
+        self.cmp(other) == cmp::Ordering::Equal
     }
 }
 
@@ -23,107 +35,242 @@ impl<I: StorageIterator> Eq for HeapWrapper<I> {}
 
 impl<I: StorageIterator> PartialOrd for HeapWrapper<I> {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        match self.1.key().cmp(other.1.key()) {
-            cmp::Ordering::Greater => Some(cmp::Ordering::Greater),
-            cmp::Ordering::Less => Some(cmp::Ordering::Less),
-            cmp::Ordering::Equal => self.0.partial_cmp(&other.0),
-        }
-        .map(|x| x.reverse())
+        Some(self.cmp(other))
     }
 }
 
 impl<I: StorageIterator> Ord for HeapWrapper<I> {
     fn cmp(&self, other: &Self) -> cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        let by_key = self.2.compare(self.1.key(), other.1.key());
+        let by_idx = self.0.cmp(&other.0);
+        match self.3 {
+            HeapOrder::Forward => by_key.then(by_idx).reverse(),
+            HeapOrder::Backward => by_key.then(by_idx),
+            HeapOrder::BackwardDedup => by_key.then(by_idx.reverse()),
+        }
     }
 }
 
-/// Merge multiple iterators of the same type. If the same key occurs multiple times in some
-/// iterators, perfer the one with smaller index.
+/// Merge multiple iterators of the same type, whose keys are ordered by the same comparator. If the
+/// same key occurs multiple times in some iterators, perfer the one with smaller index.
 pub struct MergeIterator<I: StorageIterator> {
     iters: BinaryHeap<HeapWrapper<I>>,
-    current: HeapWrapper<I>,
+    current: Option<HeapWrapper<I>>,
+    /// Iterators which ran out of entries in the direction of iteration, kept to turn back.
+    exhausted: Vec<HeapWrapper<I>>,
+    /// Whether entries of other iterators sharing the current key are skipped by `next`.
+    dedup: bool,
+    /// Whether the iterator moves backwards, after `prev`, `seek_to_last` or `seek_for_prev`.
+    backward: bool,
 }
 
 impl<I: StorageIterator> MergeIterator<I> {
     pub fn create(iters: Vec<Box<I>>) -> Self {
-        let mut idx: usize = 0 as usize;
-        let mut bh: BinaryHeap<HeapWrapper<I>> = BinaryHeap::new();
+        Self::create_inner(iters, true, Arc::new(BytewiseComparator))
+    }
+
+    /// Merge multiple iterators whose keys are ordered by `comparator`.
+    pub fn create_with_comparator(iters: Vec<Box<I>>, comparator: Arc<dyn Comparator>) -> Self {
+        Self::create_inner(iters, true, comparator)
+    }
+
+    /// Merge multiple iterators without collapsing duplicate keys. Entries with the same key are
+    /// produced one after another, ordered by the index of the iterator they come from.
+    pub fn create_all_versions(iters: Vec<Box<I>>) -> Self {
+        Self::create_inner(iters, false, Arc::new(BytewiseComparator))
+    }
+
+    /// Merge multiple iterators whose keys are ordered by `comparator` without collapsing
+    /// duplicate keys, see `create_all_versions`.
+    pub fn create_all_versions_with_comparator(
+        iters: Vec<Box<I>>,
+        comparator: Arc<dyn Comparator>,
+    ) -> Self {
+        Self::create_inner(iters, false, comparator)
+    }
+
+    fn create_inner(iters: Vec<Box<I>>, dedup: bool, comparator: Arc<dyn Comparator>) -> Self {
+        let mut iter = MergeIterator {
+            iters: BinaryHeap::new(),
+            current: None,
+            exhausted: Vec::new(),
+            dedup,
+            backward: false,
+        };
+        let wrappers = iters
+            .into_iter()
+            .enumerate()
+            .map(|(idx, iter)| HeapWrapper(idx, iter, comparator.clone(), HeapOrder::Forward))
+            .collect();
+        iter.rebuild(wrappers, false);
+        iter
+    }
 
-        for iter in iters {
-            if !iter.is_valid() {
-                continue;
+    /// Take every iterator out of the heap.
+    fn take_all(&mut self) -> Vec<HeapWrapper<I>> {
+        let mut wrappers: Vec<_> = self.current.take().into_iter().collect();
+        wrappers.extend(self.iters.drain());
+        wrappers.append(&mut self.exhausted);
+        wrappers
+    }
+
+    /// Fill the heap with the iterators, ordered for the given direction.
+    fn rebuild(&mut self, wrappers: Vec<HeapWrapper<I>>, backward: bool) {
+        self.backward = backward;
+        let order = match (backward, self.dedup) {
+            (false, _) => HeapOrder::Forward,
+            (true, false) => HeapOrder::Backward,
+            (true, true) => HeapOrder::BackwardDedup,
+        };
+        for mut wrapper in wrappers {
+            wrapper.3 = order;
+            if wrapper.1.is_valid() {
+                self.iters.push(wrapper);
+            } else {
+                self.exhausted.push(wrapper);
             }
-            let hw = HeapWrapper { 0: idx, 1: iter };
-            bh.push(hw);
-            idx += 1;
-        }
-        // println!("heap size: {}", bh.len());
-        if bh.is_empty() {
-            panic!("fail to create");
-        }
-        let curr: HeapWrapper<I> = bh.pop().unwrap();
-        MergeIterator {
-            iters: bh,
-            current: curr,
         }
+        self.current = self.iters.pop();
     }
 }
 
-impl<I: StorageIterator> StorageIterator for MergeIterator<I> {
-    fn key(&self) -> &[u8] {
-        self.current.1.key()
+impl<I: BidirectionalIterator> MergeIterator<I> {
+    /// Move every iterator with `f`, then fill the heap for the given direction.
+    fn reposition(
+        &mut self,
+        backward: bool,
+        mut f: impl FnMut(&mut HeapWrapper<I>) -> Result<()>,
+    ) -> Result<()> {
+        let mut wrappers = self.take_all();
+        let result = wrappers.iter_mut().try_for_each(&mut f);
+        self.rebuild(wrappers, backward);
+        result
     }
 
-    fn value(&self) -> &[u8] {
-        self.current.1.value()
-    }
-
-    fn is_valid(&self) -> bool {
-        self.current.1.is_valid()
+    /// Turn around and take a step in the new direction, see `turn_around`. Past the end, the
+    /// iterators start over from the other end.
+    fn turn(&mut self, backward: bool) -> Result<()> {
+        let current = self
+            .current
+            .as_ref()
+            .filter(|current| current.1.is_valid())
+            .map(|current| (current.0, current.1.key().to_vec()));
+        let dedup = self.dedup;
+        self.reposition(backward, |wrapper| match &current {
+            Some((idx, key)) => {
+                turn_around(&mut *wrapper.1, wrapper.0 == *idx, backward, dedup, key)
+            }
+            None if backward => wrapper.1.seek_to_last(),
+            None => wrapper.1.seek_to_first(),
+        })
     }
 
-    fn next(&mut self) -> Result<()> {
-        // let mut current = &self.current;
+    /// Move to the next entry in the direction of iteration.
+    fn step(&mut self) -> Result<()> {
+        let current = match self.current.as_mut() {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+        let backward = self.backward;
+        let advance = |iter: &mut I| if backward { iter.prev() } else { iter.next() };
         // Pop the item out of the heap if they have the same value.
-        while let Some(mut inner_iter) = self.iters.peek_mut() {
+        while self.dedup {
+            let Some(mut inner_iter) = self.iters.peek_mut() else {
+                break;
+            };
             debug_assert!(
-                inner_iter.1.key() >= self.current.1.key(),
+                {
+                    let ord = current.2.compare(inner_iter.1.key(), current.1.key());
+                    if backward {
+                        ord.is_le()
+                    } else {
+                        ord.is_ge()
+                    }
+                },
                 "heap invariant violated"
             );
-            if inner_iter.1.key() == self.current.1.key() {
+            if inner_iter.1.key() == current.1.key() {
                 // Case 1: an error occurred when calling `next`.
-                if let e @ Err(_) = inner_iter.1.next() {
-                    PeekMut::pop(inner_iter);
+                if let e @ Err(_) = advance(&mut inner_iter.1) {
+                    self.exhausted.push(PeekMut::pop(inner_iter));
                     return e;
                 }
 
                 // Case 2: iter is no longer valid.
                 if !inner_iter.1.is_valid() {
-                    PeekMut::pop(inner_iter);
+                    self.exhausted.push(PeekMut::pop(inner_iter));
                 }
             } else {
                 break;
             }
         }
 
-        self.current.1.next()?;
+        advance(&mut current.1)?;
 
         // If the current iterator is invalid, pop it out of the heap and select the next one.
-        if !self.current.1.is_valid() {
+        if !current.1.is_valid() {
             if let Some(iter) = self.iters.pop() {
-                self.current = iter;
+                self.exhausted.push(std::mem::replace(current, iter));
             }
             return Ok(());
         }
 
         // Otherwise, compare with heap top and swap if necessary.
         if let Some(mut inner_iter) = self.iters.peek_mut() {
-            if self.current < *inner_iter {
-                std::mem::swap(&mut *inner_iter, &mut self.current);
+            if *current < *inner_iter {
+                std::mem::swap(&mut *inner_iter, current);
             }
         }
 
         Ok(())
     }
 }
+
+impl<I: BidirectionalIterator> StorageIterator for MergeIterator<I> {
+    fn key(&self) -> &[u8] {
+        self.current.as_ref().unwrap().1.key()
+    }
+
+    fn value(&self) -> &[u8] {
+        self.current.as_ref().unwrap().1.value()
+    }
+
+    fn is_valid(&self) -> bool {
+        self.current
+            .as_ref()
+            .map(|x| x.1.is_valid())
+            .unwrap_or(false)
+    }
+
+    fn next(&mut self) -> Result<()> {
+        if self.backward {
+            return self.turn(false);
+        }
+        self.step()
+    }
+}
+
+impl<I: BidirectionalIterator> BidirectionalIterator for MergeIterator<I> {
+    fn prev(&mut self) -> Result<()> {
+        if !self.backward {
+            return self.turn(true);
+        }
+        self.step()
+    }
+
+    fn seek_to_first(&mut self) -> Result<()> {
+        self.reposition(false, |wrapper| wrapper.1.seek_to_first())
+    }
+
+    fn seek_to_last(&mut self) -> Result<()> {
+        self.reposition(true, |wrapper| wrapper.1.seek_to_last())
+    }
+
+    fn seek(&mut self, key: &[u8]) -> Result<()> {
+        self.reposition(false, |wrapper| wrapper.1.seek(key))
+    }
+
+    fn seek_for_prev(&mut self, key: &[u8]) -> Result<()> {
+        self.reposition(true, |wrapper| wrapper.1.seek_for_prev(key))
+    }
+}
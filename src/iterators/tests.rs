@@ -1,11 +1,44 @@
 use anyhow::Result;
 use bytes::Bytes;
 
-use super::StorageIterator;
+use super::{BidirectionalIterator, StorageIterator};
 
 pub mod merge_iterator_test;
 pub mod two_merge_iterator_test;
 
+/// Check that `iter` yields `expected` backwards from the last entry, then forwards again, turning
+/// around at every entry.
+pub fn check_bidirectional(mut iter: impl BidirectionalIterator, expected: &[(Bytes, Bytes)]) {
+    let check = |iter: &dyn BidirectionalIterator, idx: usize| {
+        assert!(iter.is_valid(), "expected {:?}", expected[idx]);
+        assert_eq!(iter.key(), &expected[idx].0[..]);
+        assert_eq!(iter.value(), &expected[idx].1[..]);
+    };
+    iter.seek_to_last().unwrap();
+    for idx in (0..expected.len()).rev() {
+        check(&iter, idx);
+        iter.prev().unwrap();
+    }
+    assert!(!iter.is_valid());
+
+    iter.seek_to_first().unwrap();
+    for idx in 0..expected.len() {
+        check(&iter, idx);
+        if idx > 0 {
+            iter.prev().unwrap();
+            check(&iter, idx - 1);
+            iter.next().unwrap();
+            check(&iter, idx);
+        }
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+    iter.prev().unwrap();
+    if let Some(idx) = expected.len().checked_sub(1) {
+        check(&iter, idx);
+    }
+}
+
 #[derive(Clone)]
 pub struct MockIterator {
     pub data: Vec<(Bytes, Bytes)>,
@@ -38,3 +71,37 @@ impl StorageIterator for MockIterator {
         self.index < self.data.len()
     }
 }
+
+impl BidirectionalIterator for MockIterator {
+    fn prev(&mut self) -> Result<()> {
+        // Moving before the first entry invalidates the iterator, like moving past the last one.
+        self.index = match self.index {
+            0 => self.data.len(),
+            index => index - 1,
+        };
+        Ok(())
+    }
+
+    fn seek_to_first(&mut self) -> Result<()> {
+        self.index = 0;
+        Ok(())
+    }
+
+    fn seek_to_last(&mut self) -> Result<()> {
+        self.index = self.data.len().saturating_sub(1);
+        Ok(())
+    }
+
+    fn seek(&mut self, key: &[u8]) -> Result<()> {
+        self.index = self.data.partition_point(|(k, _)| &k[..] < key);
+        Ok(())
+    }
+
+    fn seek_for_prev(&mut self, key: &[u8]) -> Result<()> {
+        self.index = match self.data.partition_point(|(k, _)| &k[..] <= key) {
+            0 => self.data.len(),
+            index => index - 1,
+        };
+        Ok(())
+    }
+}
@@ -135,3 +135,77 @@ fn test_merge_empty() {
     let iter = MergeIterator::<MockIterator>::create(vec![]);
     check_iter_result(iter, vec![]);
 }
+
+#[test]
+fn test_merge_bidirectional() {
+    let i1 = MockIterator::new(vec![
+        (Bytes::from("a"), Bytes::from("1.1")),
+        (Bytes::from("c"), Bytes::from("3.1")),
+    ]);
+    let i2 = MockIterator::new(vec![
+        (Bytes::from("a"), Bytes::from("1.2")),
+        (Bytes::from("b"), Bytes::from("2.2")),
+        (Bytes::from("c"), Bytes::from("3.2")),
+    ]);
+    let i3 = MockIterator::new(vec![
+        (Bytes::from("c"), Bytes::from("3.3")),
+        (Bytes::from("d"), Bytes::from("4.3")),
+    ]);
+    let iters = || -> Vec<Box<MockIterator>> {
+        vec![
+            Box::new(i1.clone()),
+            Box::new(i2.clone()),
+            Box::new(i3.clone()),
+        ]
+    };
+
+    check_bidirectional(
+        MergeIterator::create(iters()),
+        &[
+            (Bytes::from("a"), Bytes::from("1.1")),
+            (Bytes::from("b"), Bytes::from("2.2")),
+            (Bytes::from("c"), Bytes::from("3.1")),
+            (Bytes::from("d"), Bytes::from("4.3")),
+        ],
+    );
+    check_bidirectional(
+        MergeIterator::create_all_versions(iters()),
+        &[
+            (Bytes::from("a"), Bytes::from("1.1")),
+            (Bytes::from("a"), Bytes::from("1.2")),
+            (Bytes::from("b"), Bytes::from("2.2")),
+            (Bytes::from("c"), Bytes::from("3.1")),
+            (Bytes::from("c"), Bytes::from("3.2")),
+            (Bytes::from("c"), Bytes::from("3.3")),
+            (Bytes::from("d"), Bytes::from("4.3")),
+        ],
+    );
+    check_bidirectional(MergeIterator::<MockIterator>::create(vec![]), &[]);
+
+    let mut iter = MergeIterator::create(iters());
+    iter.seek_for_prev(b"bb").unwrap();
+    assert_eq!(iter.key(), b"b");
+    iter.prev().unwrap();
+    assert_eq!(iter.value(), b"1.1");
+    iter.seek_for_prev(b"c").unwrap();
+    assert_eq!(iter.value(), b"3.1");
+    iter.seek_for_prev(b"0").unwrap();
+    assert!(!iter.is_valid());
+
+    iter.seek(b"bb").unwrap();
+    assert_eq!(iter.value(), b"3.1");
+    iter.prev().unwrap();
+    assert_eq!(iter.key(), b"b");
+    iter.seek(b"a").unwrap();
+    assert_eq!(iter.value(), b"1.1");
+    iter.next().unwrap();
+    assert_eq!(iter.key(), b"b");
+    iter.seek(b"e").unwrap();
+    assert!(!iter.is_valid());
+
+    let mut iter = MergeIterator::create_all_versions(iters());
+    iter.seek(b"c").unwrap();
+    assert_eq!(iter.value(), b"3.1");
+    iter.next().unwrap();
+    assert_eq!(iter.value(), b"3.2");
+}
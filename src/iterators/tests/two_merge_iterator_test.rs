@@ -127,3 +127,57 @@ fn test_merge_5() {
     let iter = TwoMergeIterator::create(i1, i2).unwrap();
     check_iter_result(iter, vec![])
 }
+
+#[test]
+fn test_merge_bidirectional() {
+    let i1 = MockIterator::new(vec![
+        (Bytes::from("a"), Bytes::from("1.1")),
+        (Bytes::from("c"), Bytes::from("3.1")),
+    ]);
+    let i2 = MockIterator::new(vec![
+        (Bytes::from("a"), Bytes::from("1.2")),
+        (Bytes::from("b"), Bytes::from("2.2")),
+        (Bytes::from("c"), Bytes::from("3.2")),
+        (Bytes::from("d"), Bytes::from("4.2")),
+    ]);
+
+    check_bidirectional(
+        TwoMergeIterator::create(i1.clone(), i2.clone()).unwrap(),
+        &[
+            (Bytes::from("a"), Bytes::from("1.1")),
+            (Bytes::from("b"), Bytes::from("2.2")),
+            (Bytes::from("c"), Bytes::from("3.1")),
+            (Bytes::from("d"), Bytes::from("4.2")),
+        ],
+    );
+    check_bidirectional(
+        TwoMergeIterator::create_all_versions(i1.clone(), i2.clone()).unwrap(),
+        &[
+            (Bytes::from("a"), Bytes::from("1.1")),
+            (Bytes::from("a"), Bytes::from("1.2")),
+            (Bytes::from("b"), Bytes::from("2.2")),
+            (Bytes::from("c"), Bytes::from("3.1")),
+            (Bytes::from("c"), Bytes::from("3.2")),
+            (Bytes::from("d"), Bytes::from("4.2")),
+        ],
+    );
+
+    let mut iter = TwoMergeIterator::create(i1, i2).unwrap();
+    iter.seek_for_prev(b"c").unwrap();
+    assert_eq!(iter.value(), b"3.1");
+    iter.prev().unwrap();
+    assert_eq!(iter.key(), b"b");
+    iter.prev().unwrap();
+    assert_eq!(iter.value(), b"1.1");
+    iter.prev().unwrap();
+    assert!(!iter.is_valid());
+
+    iter.seek(b"bb").unwrap();
+    assert_eq!(iter.value(), b"3.1");
+    iter.next().unwrap();
+    assert_eq!(iter.key(), b"d");
+    iter.seek(b"a").unwrap();
+    assert_eq!(iter.value(), b"1.1");
+    iter.next().unwrap();
+    assert_eq!(iter.key(), b"b");
+}
@@ -1,63 +1,123 @@
-#![allow(unused_variables)] // TODO(you): remove this lint after implementing this mod
-#![allow(dead_code)] // TODO(you): remove this lint after implementing this mod
-
-use std::f32::consts::E;
+use std::sync::Arc;
 
 use anyhow::Result;
-use bytes::Bytes;
 
-use super::StorageIterator;
+use super::{turn_around, BidirectionalIterator, StorageIterator};
+use crate::comparator::{BytewiseComparator, Comparator};
 
-/// Merges two iterators of different types into one. If the two iterators have the same key, only
-/// produce the key once and prefer the entry from A.
+/// Merges two iterators of different types into one, whose keys are ordered by the same
+/// comparator. If the two iterators have the same key, only produce the key once and prefer the
+/// entry from A. Backwards, entries come in the reverse order.
 pub struct TwoMergeIterator<A: StorageIterator, B: StorageIterator> {
     a: A,
     b: B,
-    // Add fields as need
-    // currentA: HeapWrapper<I>,
-    // currentA: HeapWrapper<I>,
+    /// Whether entries of B sharing a key with A are skipped.
+    dedup: bool,
+    comparator: Arc<dyn Comparator>,
+    /// Whether the iterator moves backwards, after `prev`, `seek_to_last` or `seek_for_prev`.
+    backward: bool,
 }
 
-impl<A: StorageIterator, B: StorageIterator> TwoMergeIterator<A, B> {
+impl<A: BidirectionalIterator, B: BidirectionalIterator> TwoMergeIterator<A, B> {
     pub fn create(a: A, b: B) -> Result<Self> {
-        let mut res = Self { a, b };
-        if res.a.is_valid() && res.b.is_valid() && res.a.key() == res.b.key() {
-            let _ = res.b.next();
+        let mut res = Self {
+            a,
+            b,
+            dedup: true,
+            comparator: Arc::new(BytewiseComparator),
+            backward: false,
+        };
+        res.skip_b()?;
+        Ok(res)
+    }
+
+    /// Merge two iterators without collapsing duplicate keys. When both iterators have the same
+    /// key, the entry from A is produced first.
+    pub fn create_all_versions(a: A, b: B) -> Result<Self> {
+        Self::create_all_versions_with_comparator(a, b, Arc::new(BytewiseComparator))
+    }
+
+    /// Merge two iterators whose keys are ordered by `comparator` without collapsing duplicate
+    /// keys, see `create_all_versions`.
+    pub fn create_all_versions_with_comparator(
+        a: A,
+        b: B,
+        comparator: Arc<dyn Comparator>,
+    ) -> Result<Self> {
+        Ok(Self {
+            a,
+            b,
+            dedup: false,
+            comparator,
+            backward: false,
+        })
+    }
+
+    fn choose_a(&self) -> bool {
+        if !self.a.is_valid() {
+            return false;
+        }
+        if !self.b.is_valid() {
+            return true;
+        }
+        let ord = self.comparator.compare(self.a.key(), self.b.key());
+        match (self.backward, self.dedup) {
+            (false, _) => ord.is_le(),
+            (true, false) => ord.is_gt(),
+            (true, true) => ord.is_ge(),
+        }
+    }
+
+    fn skip_b(&mut self) -> Result<()> {
+        if self.dedup && self.a.is_valid() && self.b.is_valid() && self.a.key() == self.b.key() {
+            if self.backward {
+                self.b.prev()?;
+            } else {
+                self.b.next()?;
+            }
         }
-        return Ok(res);
+        Ok(())
+    }
+
+    /// Turn around and take a step in the new direction, see `turn_around`. Past the end, the
+    /// iterators start over from the other end.
+    fn turn(&mut self, backward: bool) -> Result<()> {
+        if !self.is_valid() {
+            self.backward = backward;
+            if backward {
+                self.a.seek_to_last()?;
+                self.b.seek_to_last()?;
+            } else {
+                self.a.seek_to_first()?;
+                self.b.seek_to_first()?;
+            }
+            return self.skip_b();
+        }
+        let key = self.key().to_vec();
+        let choose_a = self.choose_a();
+        self.backward = backward;
+        turn_around(&mut self.a, choose_a, backward, self.dedup, &key)?;
+        turn_around(&mut self.b, !choose_a, backward, self.dedup, &key)?;
+        self.skip_b()
     }
 }
 
-impl<A: StorageIterator, B: StorageIterator> StorageIterator for TwoMergeIterator<A, B> {
+impl<A: BidirectionalIterator, B: BidirectionalIterator> StorageIterator
+    for TwoMergeIterator<A, B>
+{
     fn key(&self) -> &[u8] {
-        if self.a.is_valid() && !self.b.is_valid() {
+        if self.choose_a() {
             self.a.key()
-        } else if !self.a.is_valid() && self.b.is_valid() {
-            self.b.key()
-        } else if self.a.is_valid() && self.b.is_valid() {
-            if self.a.key() <= self.b.key() {
-                self.a.key()
-            } else {
-                self.b.key()
-            }
         } else {
-            &[0]
+            self.b.key()
         }
     }
 
     fn value(&self) -> &[u8] {
-        if self.a.is_valid() && !self.b.is_valid() {
+        if self.choose_a() {
             self.a.value()
-        } else if !self.a.is_valid() && self.b.is_valid() {
-            self.b.value()
-        } else if self.a.is_valid() && self.b.is_valid() {
-            if self.a.key() <= self.b.key() {
-                self.a.value()
-            } else {
-                self.b.value()
-            }
         } else {
-            &[0]
+            self.b.value()
         }
     }
 
@@ -66,27 +126,58 @@ impl<A: StorageIterator, B: StorageIterator> StorageIterator for TwoMergeIterato
     }
 
     fn next(&mut self) -> Result<()> {
-        if self.a.is_valid() && !self.b.is_valid() {
-            let _ = self.a.next();
-            return Ok(());
-        } else if !self.a.is_valid() && self.b.is_valid() {
-            let _ = self.b.next();
-            return Ok(());
-        } else if self.a.is_valid() && self.b.is_valid() {
-            if self.a.key() < self.b.key() {
-                let _ = self.a.next();
-                if self.a.is_valid() && self.a.key() == self.b.key() {
-                    let _ = self.b.next();
-                }
-                return Ok(());
-            } else {
-                let _ = self.b.next();
-                if self.b.is_valid() && self.a.key() == self.b.key() {
-                    let _ = self.b.next();
-                }
-            }
+        if self.backward {
+            return self.turn(false);
+        }
+        if self.choose_a() {
+            self.a.next()?;
+        } else {
+            self.b.next()?;
+        }
+        self.skip_b()
+    }
+}
+
+impl<A: BidirectionalIterator, B: BidirectionalIterator> BidirectionalIterator
+    for TwoMergeIterator<A, B>
+{
+    fn prev(&mut self) -> Result<()> {
+        if !self.backward {
+            return self.turn(true);
+        }
+        if self.choose_a() {
+            self.a.prev()?;
+        } else {
+            self.b.prev()?;
         }
+        self.skip_b()
+    }
+
+    fn seek_to_first(&mut self) -> Result<()> {
+        self.backward = false;
+        self.a.seek_to_first()?;
+        self.b.seek_to_first()?;
+        self.skip_b()
+    }
+
+    fn seek_to_last(&mut self) -> Result<()> {
+        self.backward = true;
+        self.a.seek_to_last()?;
+        self.b.seek_to_last()?;
+        self.skip_b()
+    }
+
+    fn seek(&mut self, key: &[u8]) -> Result<()> {
+        self.backward = false;
+        self.a.seek(key)?;
+        self.b.seek(key)?;
+        self.skip_b()
+    }
 
-        return Ok(()); // error handling todo
+    fn seek_for_prev(&mut self, key: &[u8]) -> Result<()> {
+        self.backward = true;
+        self.a.seek_for_prev(key)?;
+        self.b.seek_for_prev(key)?;
+        self.skip_b()
     }
 }
@@ -0,0 +1,72 @@
+use std::ops::Bound;
+
+/// The size of a user timestamp appended to keys.
+pub const TS_SIZE: usize = std::mem::size_of::<u64>();
+
+/// Keys written with a user timestamp are stored as the user key with every `0x00` escaped as
+/// `0x00 0xff`, a `0x00 0x01` terminator and the bitwise-inverted timestamp in big endian.
+///
+/// ---------------------------------------------------------
+/// |   escaped user key   | 0x00 0x01 | !timestamp (u64) |
+/// ---------------------------------------------------------
+///
+/// The terminator sorts before any escaped byte, so comparing two encoded keys byte by byte orders
+/// them by user key ascending and then by timestamp descending. Block, SST and merge iterators
+/// therefore seek to the newest version at or below a timestamp with plain bytewise comparisons.
+pub fn key_with_ts(user_key: &[u8], ts: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(user_key.len() + 2 + TS_SIZE);
+    for &b in user_key {
+        key.push(b);
+        if b == 0 {
+            key.push(0xff);
+        }
+    }
+    key.extend_from_slice(&[0x00, 0x01]);
+    key.extend_from_slice(&(!ts).to_be_bytes());
+    key
+}
+
+/// Get the timestamp of a key produced by `key_with_ts`.
+pub fn ts_of(key: &[u8]) -> u64 {
+    !u64::from_be_bytes(key[key.len() - TS_SIZE..].try_into().unwrap())
+}
+
+/// Get the encoded user key of a key produced by `key_with_ts`, including the terminator. All
+/// versions of a user key share the same prefix.
+pub fn user_key_prefix(key: &[u8]) -> &[u8] {
+    &key[..key.len() - TS_SIZE]
+}
+
+/// Decode the user key of a key produced by `key_with_ts`.
+pub fn user_key_of(key: &[u8]) -> Vec<u8> {
+    let prefix = user_key_prefix(key);
+    let escaped = &prefix[..prefix.len() - 2];
+    let mut user_key = Vec::with_capacity(escaped.len());
+    let mut idx = 0;
+    while idx < escaped.len() {
+        user_key.push(escaped[idx]);
+        idx += if escaped[idx] == 0 { 2 } else { 1 };
+    }
+    user_key
+}
+
+/// Map a bound over user keys to a bound over keys with timestamps, covering every version.
+pub fn lower_bound_with_ts(bound: Bound<&[u8]>) -> Bound<Vec<u8>> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key_with_ts(key, u64::MAX)),
+        Bound::Excluded(key) => Bound::Excluded(key_with_ts(key, 0)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// Map a bound over user keys to a bound over keys with timestamps, covering every version.
+pub fn upper_bound_with_ts(bound: Bound<&[u8]>) -> Bound<Vec<u8>> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key_with_ts(key, 0)),
+        Bound::Excluded(key) => Bound::Excluded(key_with_ts(key, u64::MAX)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,30 @@
+use super::*;
+
+#[test]
+fn test_key_with_ts_roundtrip() {
+    for user_key in [&b"a"[..], b"a\x00b", b"\x00", b"\x00\xff\x01"] {
+        for ts in [0, 1, 42, u64::MAX] {
+            let key = key_with_ts(user_key, ts);
+            assert_eq!(user_key_of(&key), user_key);
+            assert_eq!(ts_of(&key), ts);
+        }
+    }
+}
+
+#[test]
+fn test_key_with_ts_order() {
+    // User keys ascending, timestamps descending.
+    let expected = vec![
+        key_with_ts(b"a", 9),
+        key_with_ts(b"a", 3),
+        key_with_ts(b"a\x00", 5),
+        key_with_ts(b"a\x00\x00", 5),
+        key_with_ts(b"a\x01", 5),
+        key_with_ts(b"ab", u64::MAX),
+        key_with_ts(b"ab", 0),
+        key_with_ts(b"b", 7),
+    ];
+    let mut sorted = expected.clone();
+    sorted.sort();
+    assert_eq!(sorted, expected);
+}
@@ -1,9 +1,21 @@
 pub mod block;
+mod compact;
+pub mod compaction_filter;
+pub mod comparator;
+pub mod compression;
+pub mod filter_policy;
 pub mod iterators;
+pub mod key;
 pub mod lsm_iterator;
 pub mod lsm_storage;
+pub mod manifest;
 pub mod mem_table;
+pub mod merge_operator;
+pub mod prefix_extractor;
+pub mod range_tombstone;
 pub mod table;
+pub mod table_properties_collector;
+pub mod value;
 
 #[cfg(test)]
 mod tests;
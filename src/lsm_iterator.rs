@@ -1,32 +1,420 @@
-#![allow(unused_variables)] // TODO(you): remove this lint after implementing this mod
-#![allow(dead_code)] // TODO(you): remove this lint after implementing this mod
+use std::ops::Bound;
+use std::sync::Arc;
 
 use anyhow::Result;
+use bytes::Bytes;
 
-use crate::iterators::StorageIterator;
+use crate::comparator::{BytewiseComparator, Comparator};
+use crate::iterators::merge_iterator::MergeIterator;
+use crate::iterators::two_merge_iterator::TwoMergeIterator;
+use crate::iterators::{BidirectionalIterator, StorageIterator};
+use crate::key;
+use crate::mem_table::MemTableIterator;
+use crate::merge_operator::{MergeOperands, MergeOperator};
+use crate::range_tombstone::RangeTombstoneList;
+use crate::table::SsTableIterator;
+use crate::value::{self, Record, ValueKind};
 
-pub struct LsmIterator {}
+pub(crate) type LsmIteratorInner =
+    TwoMergeIterator<MergeIterator<MemTableIterator>, MergeIterator<SsTableIterator>>;
+
+/// Iterates over the live keys of the storage. The inner iterator yields every version of a key,
+/// newest first. Deleted keys, including expired ones and the ones masked by range tombstones, are
+/// skipped, merge operands are folded onto the value below them and the record header is stripped
+/// from values.
+///
+/// When reading at a user timestamp, the inner iterator yields keys produced by
+/// `key::key_with_ts`. Only the newest version at or below `read_ts` of each user key is visible,
+/// and keys are returned without their timestamp.
+///
+/// Backwards, the inner iterator yields the versions of a key oldest first: they are collected up
+/// to the newest one before the key is resolved.
+pub struct LsmIterator {
+    iter: LsmIteratorInner,
+    start_bound: Bound<Bytes>,
+    end_bound: Bound<Bytes>,
+    /// Orders the keys of the inner iterator.
+    comparator: Arc<dyn Comparator>,
+    is_valid: bool,
+    read_ts: Option<u64>,
+    merge_operator: Option<Arc<dyn MergeOperator>>,
+    range_tombstones: RangeTombstoneList,
+    /// The time records are checked for expiry against, fixed when the iterator is created.
+    now: u64,
+    /// The inner key of the current entry. With user timestamps, only the prefix shared by all
+    /// versions is kept.
+    current: Vec<u8>,
+    /// The decoded user key of the current entry, only used when reading at a timestamp.
+    user_key: Vec<u8>,
+    /// The value of the current entry if it was produced by folding merge operands, or found
+    /// backwards.
+    merged_value: Option<Vec<u8>>,
+    /// Whether the iterator moves backwards. The inner iterator is then positioned before the
+    /// versions of the current entry rather than on them.
+    backward: bool,
+}
+
+impl LsmIterator {
+    pub(crate) fn new(
+        iter: LsmIteratorInner,
+        start_bound: Bound<Bytes>,
+        end_bound: Bound<Bytes>,
+        merge_operator: Option<Arc<dyn MergeOperator>>,
+        range_tombstones: RangeTombstoneList,
+        comparator: Arc<dyn Comparator>,
+    ) -> Result<Self> {
+        let mut iter = Self::create(
+            iter,
+            start_bound,
+            end_bound,
+            None,
+            merge_operator,
+            range_tombstones,
+            comparator,
+        );
+        iter.move_to_visible()?;
+        Ok(iter)
+    }
+
+    /// Create an iterator positioned at the last visible entry, to iterate backwards.
+    pub(crate) fn new_rev(
+        iter: LsmIteratorInner,
+        start_bound: Bound<Bytes>,
+        end_bound: Bound<Bytes>,
+        merge_operator: Option<Arc<dyn MergeOperator>>,
+        range_tombstones: RangeTombstoneList,
+        comparator: Arc<dyn Comparator>,
+    ) -> Result<Self> {
+        let mut iter = Self::create(
+            iter,
+            start_bound,
+            end_bound,
+            None,
+            merge_operator,
+            range_tombstones,
+            comparator,
+        );
+        iter.seek_to_last()?;
+        Ok(iter)
+    }
+
+    pub(crate) fn new_with_ts(
+        iter: LsmIteratorInner,
+        start_bound: Bound<Bytes>,
+        end_bound: Bound<Bytes>,
+        read_ts: u64,
+    ) -> Result<Self> {
+        let mut iter = Self::create(
+            iter,
+            start_bound,
+            end_bound,
+            Some(read_ts),
+            None,
+            RangeTombstoneList::default(),
+            // Keys with timestamps are ordered bytewise.
+            Arc::new(BytewiseComparator),
+        );
+        iter.move_to_visible()?;
+        Ok(iter)
+    }
+
+    fn create(
+        iter: LsmIteratorInner,
+        start_bound: Bound<Bytes>,
+        end_bound: Bound<Bytes>,
+        read_ts: Option<u64>,
+        merge_operator: Option<Arc<dyn MergeOperator>>,
+        range_tombstones: RangeTombstoneList,
+        comparator: Arc<dyn Comparator>,
+    ) -> Self {
+        Self {
+            iter,
+            start_bound,
+            end_bound,
+            comparator,
+            is_valid: false,
+            read_ts,
+            merge_operator,
+            range_tombstones,
+            now: value::unix_millis(),
+            current: Vec::new(),
+            user_key: Vec::new(),
+            merged_value: None,
+            backward: false,
+        }
+    }
+
+    fn inner_valid(&self) -> bool {
+        self.iter.is_valid()
+            && self
+                .comparator
+                .is_below(self.iter.key(), self.end_bound.as_ref().map(Bytes::as_ref))
+    }
+
+    /// Check if the inner iterator is valid and not before the start bound, which ends backward
+    /// iteration.
+    fn inner_valid_backward(&self) -> bool {
+        self.iter.is_valid() && self.is_above_start(self.iter.key())
+    }
+
+    fn is_above_start(&self, key: &[u8]) -> bool {
+        self.comparator
+            .is_above(key, self.start_bound.as_ref().map(Bytes::as_ref))
+    }
+
+    /// Check if an inner key is a version of the current entry.
+    fn is_current(&self, key: &[u8]) -> bool {
+        if self.read_ts.is_some() {
+            key::user_key_prefix(key) == self.current
+        } else {
+            key == self.current
+        }
+    }
+
+    fn current_record(&self) -> Result<Record<'_>> {
+        Ok(self.range_tombstones.mask(
+            self.iter.key(),
+            Record::decode(self.iter.value())?.expire(self.now),
+        ))
+    }
+
+    /// Move past every remaining version of the current entry.
+    fn skip_current(&mut self) -> Result<()> {
+        while self.inner_valid() && self.is_current(self.iter.key()) {
+            self.iter.next()?;
+        }
+        Ok(())
+    }
+
+    /// Check if an inner key is before every version of the current entry.
+    fn is_before_current(&self, key: &[u8]) -> bool {
+        !self.is_current(key) && self.comparator.compare(key, &self.current).is_lt()
+    }
+
+    /// Set the current entry from the inner key of one of its versions.
+    fn set_current(&mut self) {
+        if self.read_ts.is_some() {
+            self.current = key::user_key_prefix(self.iter.key()).to_vec();
+            self.user_key = key::user_key_of(self.iter.key());
+        } else {
+            self.current = self.iter.key().to_vec();
+        }
+    }
+
+    /// Move to the next visible entry, starting from the inner iterator's position.
+    fn move_to_visible(&mut self) -> Result<()> {
+        // After seeking to an excluded start bound, the inner iterator may start on it.
+        while self.iter.is_valid() && !self.is_above_start(self.iter.key()) {
+            self.iter.next()?;
+        }
+        while self.inner_valid() {
+            if let Some(read_ts) = self.read_ts {
+                // Versions are ordered by timestamp descending, skip the ones written after
+                // `read_ts`.
+                if key::ts_of(self.iter.key()) > read_ts {
+                    self.iter.next()?;
+                    continue;
+                }
+            }
+            self.set_current();
+
+            self.merged_value = None;
+            match self.current_record()?.kind {
+                ValueKind::Put => {}
+                ValueKind::Delete => {
+                    self.skip_current()?;
+                    continue;
+                }
+                ValueKind::Merge => {
+                    let mut operands = MergeOperands::default();
+                    while self.inner_valid() && self.is_current(self.iter.key()) {
+                        if !operands.push(&self.current_record()?)? {
+                            break;
+                        }
+                        self.iter.next()?;
+                    }
+                    match operands.finish(self.key(), self.merge_operator.as_deref())? {
+                        Some(value) => self.merged_value = Some(value),
+                        None => {
+                            self.skip_current()?;
+                            continue;
+                        }
+                    }
+                }
+            }
+            self.is_valid = true;
+            return Ok(());
+        }
+        self.is_valid = false;
+        Ok(())
+    }
+
+    /// Move to the previous visible entry, starting from the inner iterator's position, the
+    /// oldest version of an entry.
+    fn move_to_visible_backward(&mut self) -> Result<()> {
+        while self.inner_valid_backward()
+            && !self
+                .comparator
+                .is_below(self.iter.key(), self.end_bound.as_ref().map(Bytes::as_ref))
+        {
+            self.iter.prev()?;
+        }
+        while self.inner_valid_backward() {
+            self.set_current();
+            let mut versions = Vec::new();
+            while self.inner_valid_backward() && self.is_current(self.iter.key()) {
+                versions.push((self.iter.key().to_vec(), self.iter.value().to_vec()));
+                self.iter.prev()?;
+            }
+            if let Some(value) = self.resolve(&versions)? {
+                self.merged_value = Some(value);
+                self.is_valid = true;
+                return Ok(());
+            }
+        }
+        self.is_valid = false;
+        Ok(())
+    }
+
+    /// Get the value of the current entry from its versions, oldest first, `None` if it is not
+    /// visible.
+    fn resolve(&self, versions: &[(Vec<u8>, Vec<u8>)]) -> Result<Option<Vec<u8>>> {
+        let mut operands = MergeOperands::default();
+        for (key, value) in versions.iter().rev() {
+            if self
+                .read_ts
+                .is_some_and(|read_ts| key::ts_of(key) > read_ts)
+            {
+                continue;
+            }
+            let record = self
+                .range_tombstones
+                .mask(key, Record::decode(value)?.expire(self.now));
+            if !operands.push(&record)? {
+                break;
+            }
+        }
+        operands.finish(self.key(), self.merge_operator.as_deref())
+    }
+}
 
 impl StorageIterator for LsmIterator {
     fn is_valid(&self) -> bool {
-        unimplemented!()
+        self.is_valid
     }
 
     fn key(&self) -> &[u8] {
-        unimplemented!()
+        if self.read_ts.is_some() {
+            &self.user_key
+        } else {
+            &self.current
+        }
     }
 
     fn value(&self) -> &[u8] {
-        unimplemented!()
+        match self.merged_value.as_ref() {
+            Some(value) => value,
+            // `move_to_visible` decoded the record of the current entry already.
+            None => Record::try_decode(self.iter.value()).map_or(&[], |record| record.value),
+        }
     }
 
     fn next(&mut self) -> Result<()> {
-        unimplemented!()
+        if self.backward {
+            self.backward = false;
+            if !self.is_valid {
+                return self.seek_to_first();
+            }
+            // Move past the versions of the current entry, which are after the inner iterator.
+            loop {
+                self.iter.next()?;
+                if !self.iter.is_valid()
+                    || !(self.is_current(self.iter.key())
+                        || self.is_before_current(self.iter.key()))
+                {
+                    break;
+                }
+            }
+            return self.move_to_visible();
+        }
+        self.skip_current()?;
+        self.move_to_visible()
+    }
+}
+
+impl BidirectionalIterator for LsmIterator {
+    fn prev(&mut self) -> Result<()> {
+        if !self.backward {
+            self.backward = true;
+            if !self.is_valid {
+                return self.seek_to_last();
+            }
+            // Move before the versions of the current entry. The inner iterator is on one of them,
+            // or past them once merge operands were folded.
+            loop {
+                self.iter.prev()?;
+                if !self.iter.is_valid() || self.is_before_current(self.iter.key()) {
+                    break;
+                }
+            }
+        }
+        self.move_to_visible_backward()
+    }
+
+    /// Move to the first visible entry, seeking the inner iterator to the start bound.
+    fn seek_to_first(&mut self) -> Result<()> {
+        self.backward = false;
+        match self.start_bound.as_ref() {
+            Bound::Included(key) | Bound::Excluded(key) => self.iter.seek(key)?,
+            Bound::Unbounded => self.iter.seek_to_first()?,
+        }
+        self.move_to_visible()
+    }
+
+    fn seek_to_last(&mut self) -> Result<()> {
+        self.backward = true;
+        match self.end_bound.as_ref() {
+            Bound::Included(key) | Bound::Excluded(key) => self.iter.seek_for_prev(key)?,
+            Bound::Unbounded => self.iter.seek_to_last()?,
+        }
+        self.move_to_visible_backward()
+    }
+
+    /// Move to the first visible entry at or after `key`, a user key when reading at a timestamp.
+    /// The blocks already loaded by the inner iterators are reused when they hold `key`.
+    fn seek(&mut self, key: &[u8]) -> Result<()> {
+        let key = match self.read_ts {
+            // The newest version is the first one of the user key.
+            Some(_) => key::key_with_ts(key, u64::MAX),
+            None => key.to_vec(),
+        };
+        if !self.is_above_start(&key) {
+            return self.seek_to_first();
+        }
+        self.backward = false;
+        self.iter.seek(&key)?;
+        self.move_to_visible()
+    }
+
+    /// Move to the last visible entry at or before `key`, a user key when reading at a timestamp.
+    fn seek_for_prev(&mut self, key: &[u8]) -> Result<()> {
+        let key = match self.read_ts {
+            // The oldest version is the last one of the user key.
+            Some(_) => key::key_with_ts(key, 0),
+            None => key.to_vec(),
+        };
+        let end_bound = self.end_bound.as_ref().map(Bytes::as_ref);
+        if !self.comparator.is_below(&key, end_bound) {
+            return self.seek_to_last();
+        }
+        self.backward = true;
+        self.iter.seek_for_prev(&key)?;
+        self.move_to_visible_backward()
     }
 }
 
-/// A wrapper around existing iterator, will prevent users from calling `next` when the iterator is
-/// invalid.
+/// A wrapper around existing iterator, will prevent users from calling `next` or `prev` when the
+/// iterator is invalid.
 pub struct FusedIterator<I: StorageIterator> {
     iter: I,
 }
@@ -39,18 +427,46 @@ impl<I: StorageIterator> FusedIterator<I> {
 
 impl<I: StorageIterator> StorageIterator for FusedIterator<I> {
     fn is_valid(&self) -> bool {
-        unimplemented!()
+        self.iter.is_valid()
     }
 
     fn key(&self) -> &[u8] {
-        unimplemented!()
+        self.iter.key()
     }
 
     fn value(&self) -> &[u8] {
-        unimplemented!()
+        self.iter.value()
     }
 
     fn next(&mut self) -> Result<()> {
-        unimplemented!()
+        if self.iter.is_valid() {
+            self.iter.next()?;
+        }
+        Ok(())
+    }
+}
+
+impl<I: BidirectionalIterator> BidirectionalIterator for FusedIterator<I> {
+    fn prev(&mut self) -> Result<()> {
+        if self.iter.is_valid() {
+            self.iter.prev()?;
+        }
+        Ok(())
+    }
+
+    fn seek_to_first(&mut self) -> Result<()> {
+        self.iter.seek_to_first()
+    }
+
+    fn seek_to_last(&mut self) -> Result<()> {
+        self.iter.seek_to_last()
+    }
+
+    fn seek(&mut self, key: &[u8]) -> Result<()> {
+        self.iter.seek(key)
+    }
+
+    fn seek_for_prev(&mut self, key: &[u8]) -> Result<()> {
+        self.iter.seek_for_prev(key)
     }
 }
@@ -1,75 +1,822 @@
-#![allow(unused_variables)] // TODO(you): remove this lint after implementing this mod
-#![allow(dead_code)] // TODO(you): remove this lint after implementing this mod
-
+use std::cmp;
+use std::fmt;
 use std::ops::Bound;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{bail, ensure, Result};
 use bytes::Bytes;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 
 use crate::block::Block;
-use crate::lsm_iterator::{FusedIterator, LsmIterator};
+use crate::compaction_filter::CompactionFilterFactory;
+use crate::comparator::{BytewiseComparator, Comparator};
+use crate::compression::CompressionCodec;
+use crate::filter_policy::{BloomFilterPolicy, FilterBitsAllocation, FilterPolicy};
+use crate::iterators::merge_iterator::MergeIterator;
+use crate::iterators::two_merge_iterator::TwoMergeIterator;
+use crate::iterators::{RevIterator, StorageIterator};
+use crate::key;
+use crate::lsm_iterator::{FusedIterator, LsmIterator, LsmIteratorInner};
+use crate::manifest::{Manifest, ManifestRecord};
 use crate::mem_table::MemTable;
-use crate::table::SsTable;
+use crate::merge_operator::{MergeOperands, MergeOperator};
+use crate::prefix_extractor::PrefixExtractor;
+use crate::range_tombstone::{RangeTombstone, RangeTombstoneList};
+use crate::table::{SsTable, SsTableBuilder, SsTableError, SsTableIterator};
+use crate::table_properties_collector::{TablePropertiesCollectorFactory, UserProperties};
+use crate::value::{self, Record, ValueKind};
 
+/// Caches the data and index blocks of SSTs, keyed by SST ID and block offset.
 pub type BlockCache = moka::sync::Cache<(usize, usize), Arc<Block>>;
 
 #[derive(Clone)]
 pub struct LsmStorageInner {
     /// The current memtable.
-    memtable: Arc<MemTable>,
+    pub(crate) memtable: Arc<MemTable>,
     /// Immutable memTables, from earliest to latest.
-    imm_memtables: Vec<Arc<MemTable>>,
+    pub(crate) imm_memtables: Vec<Arc<MemTable>>,
     /// L0 SsTables, from earliest to latest.
-    l0_sstables: Vec<Arc<SsTable>>,
+    pub(crate) l0_sstables: Vec<Arc<SsTable>>,
     /// L1 - L6 SsTables, sorted by key range.
-    #[allow(dead_code)]
-    levels: Vec<Vec<Arc<SsTable>>>,
-    /// The next SSTable ID.
-    next_sst_id: usize,
+    pub(crate) levels: Vec<Vec<Arc<SsTable>>>,
 }
 
 impl LsmStorageInner {
-    fn create() -> Self {
+    fn create(comparator: Arc<dyn Comparator>) -> Self {
         Self {
-            memtable: Arc::new(MemTable::create()),
+            memtable: Arc::new(MemTable::create_with_comparator(comparator)),
             imm_memtables: vec![],
             l0_sstables: vec![],
             levels: vec![],
-            next_sst_id: 1,
+        }
+    }
+
+    /// Get the SSTs of each level, starting from L0.
+    fn tables_per_level(&self) -> impl Iterator<Item = &[Arc<SsTable>]> {
+        std::iter::once(&self.l0_sstables[..]).chain(self.levels.iter().map(Vec::as_slice))
+    }
+}
+
+/// The lookup of a key by `LsmStorage::multi_get`.
+#[derive(Default)]
+struct KeyLookup {
+    operands: MergeOperands,
+    /// Whether a value or a deletion was found.
+    done: bool,
+    error: Option<anyhow::Error>,
+}
+
+impl KeyLookup {
+    fn is_done(&self) -> bool {
+        self.done || self.error.is_some()
+    }
+}
+
+/// An error of `LsmStorage::multi_get` shared by several keys. It reads as the error it shares,
+/// whose causes it keeps.
+#[derive(Debug)]
+struct SharedError(Arc<anyhow::Error>);
+
+impl fmt::Display for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self.0, f)
+    }
+}
+
+impl std::error::Error for SharedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// Get the error of one of the keys sharing `err`: the error itself for the last of them, a copy
+/// for SST errors, so that they stay typed, and a `SharedError` otherwise.
+fn unshare_error(err: Arc<anyhow::Error>) -> anyhow::Error {
+    match Arc::try_unwrap(err) {
+        Ok(err) => err,
+        Err(err) => match err.downcast_ref::<SsTableError>() {
+            Some(sst_err) => (*sst_err).into(),
+            None => SharedError(err).into(),
+        },
+    }
+}
+
+/// Statistics of the storage, see `LsmStorage::stats`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LsmStorageStats {
+    /// The statistics of each level, starting from L0.
+    pub levels: Vec<LevelStats>,
+}
+
+/// Statistics of a level, see `LsmStorageStats`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LevelStats {
+    pub num_sstables: usize,
+    /// The number of entries of the SSTs of the level.
+    pub num_entries: u64,
+    /// The bits per key allocated by `LsmStorageOptions::filter_bits_allocation` to the filters
+    /// of the SSTs written to the level from now on. SSTs already in the level keep the bits they
+    /// were written with, see `filter_size`.
+    pub target_filter_bits_per_key: f64,
+    /// The total size of the key and prefix filters of the SSTs of the level, in bytes.
+    pub filter_size: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct LsmStorageOptions {
+    /// Block size in bytes.
+    pub block_size: usize,
+    /// SST size in bytes, compaction splits its output into SSTs of about this size.
+    pub target_sst_size: usize,
+    /// Whether keys carry a timestamp supplied by the application. When enabled, keys are written
+    /// with `put_with_ts` / `delete_with_ts` and read with `get_with_ts` / `scan_with_ts`.
+    pub enable_user_timestamp: bool,
+    /// Folds the operands written by `merge` onto the value of a key.
+    pub merge_operator: Option<Arc<dyn MergeOperator>>,
+    /// Creates the filter each compaction passes its output values through.
+    pub compaction_filter_factory: Option<Arc<dyn CompactionFilterFactory>>,
+    /// The codec compressing the data blocks of each level, starting from L0, `None` for no
+    /// compression. Levels past the end use the last entry.
+    pub compression_per_level: Vec<Option<Arc<dyn CompressionCodec>>>,
+    /// Create the collectors recording user properties in each SST written.
+    pub table_properties_collector_factories: Vec<Arc<dyn TablePropertiesCollectorFactory>>,
+    /// Split the index of each SST into index blocks of about this many bytes, which are read
+    /// through the block cache when needed instead of being kept in memory. `None` keeps whole
+    /// indexes in memory.
+    pub index_partition_size: Option<usize>,
+    /// Give data blocks a hash index, so that point lookups find a key in a block without
+    /// searching it.
+    pub block_hash_index: bool,
+    /// Orders the keys. It is recorded in the manifest when the storage is created, and the
+    /// storage cannot be opened with another one. User timestamps require the bytewise one.
+    pub comparator: Arc<dyn Comparator>,
+    /// The policy building the filters of the SSTs of each level, starting from L0, `None` for no
+    /// filters. Levels past the end use the last entry. The filter of the keys of each SST lets
+    /// lookups skip the SSTs without a key, and the filter of their prefixes lets `scan_prefix`
+    /// skip the SSTs without a prefix.
+    pub filter_policy_per_level: Vec<Option<Arc<dyn FilterPolicy>>>,
+    /// The number of bits of the filters for each key or prefix, for the policies that can trade
+    /// the size of filters for their false positive rate. It is the average over all levels, see
+    /// `filter_bits_allocation`.
+    pub bloom_bits_per_key: f64,
+    /// How the bits of the filters are shared between the levels. `LsmStorage::stats` reports the
+    /// bits per key each level gets.
+    pub filter_bits_allocation: FilterBitsAllocation,
+    /// Extracts the prefixes put in the prefix filter of each SST. `None` builds no prefix
+    /// filters.
+    pub prefix_extractor: Option<Arc<dyn PrefixExtractor>>,
+    /// Give each SST a range filter, which lets scans and lookups skip the SSTs without keys in
+    /// their range even if it is within the key range of the SST. It keeps this many bytes of each
+    /// key after the prefix telling it apart from the other keys, see
+    /// `SsTableBuilder::set_range_filter`. `None` builds no range filters. Only used with the
+    /// bytewise comparator.
+    pub range_filter: Option<usize>,
+}
+
+impl LsmStorageOptions {
+    /// Get the codec compressing the SSTs written to `level`, 0 being L0.
+    pub fn compression_for_level(&self, level: usize) -> Option<Arc<dyn CompressionCodec>> {
+        self.compression_per_level
+            .get(level)
+            .or(self.compression_per_level.last())
+            .cloned()
+            .flatten()
+    }
+
+    /// Get the policy building the filters of the SSTs written to `level`, 0 being L0.
+    pub fn filter_policy_for_level(&self, level: usize) -> Option<Arc<dyn FilterPolicy>> {
+        self.filter_policy_per_level
+            .get(level)
+            .or(self.filter_policy_per_level.last())
+            .cloned()
+            .flatten()
+    }
+}
+
+impl Default for LsmStorageOptions {
+    fn default() -> Self {
+        Self {
+            block_size: 4096,
+            target_sst_size: 2 << 20,
+            enable_user_timestamp: false,
+            merge_operator: None,
+            compaction_filter_factory: None,
+            compression_per_level: Vec::new(),
+            table_properties_collector_factories: Vec::new(),
+            index_partition_size: None,
+            block_hash_index: false,
+            comparator: Arc::new(BytewiseComparator),
+            filter_policy_per_level: vec![Some(Arc::new(BloomFilterPolicy))],
+            bloom_bits_per_key: 10.0,
+            filter_bits_allocation: FilterBitsAllocation::Uniform,
+            prefix_extractor: None,
+            range_filter: None,
         }
     }
 }
 
 /// The storage interface of the LSM tree.
 pub struct LsmStorage {
-    inner: Arc<RwLock<Arc<LsmStorageInner>>>,
+    pub(crate) inner: Arc<RwLock<Arc<LsmStorageInner>>>,
+    /// Serializes writes to the memtable, as merges read the record they build upon.
+    write_lock: Mutex<()>,
+    /// Serializes memtable flushes.
+    flush_lock: Mutex<()>,
+    /// Serializes compactions.
+    pub(crate) compaction_lock: Mutex<()>,
+    /// The sequence number assigned to the next write.
+    next_seq: AtomicU64,
+    /// The next SSTable ID.
+    next_sst_id: AtomicUsize,
+    /// Compaction only keeps the newest version at or below this user timestamp.
+    pub(crate) gc_watermark: AtomicU64,
+    pub(crate) options: LsmStorageOptions,
+    pub(crate) block_cache: Arc<BlockCache>,
+    path: PathBuf,
 }
 
 impl LsmStorage {
     pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with_options(path, LsmStorageOptions::default())
+    }
+
+    pub fn open_with_options(path: impl AsRef<Path>, options: LsmStorageOptions) -> Result<Self> {
+        let comparator = options.comparator.name();
+        ensure!(
+            !options.enable_user_timestamp || comparator == BytewiseComparator.name(),
+            "user timestamps require the bytewise comparator, not {}",
+            comparator
+        );
+        let (mut manifest, records) = Manifest::open(path.as_ref())?;
+        let mut recorded = None;
+        for record in &records {
+            match record {
+                ManifestRecord::Comparator(name) => recorded = Some(name),
+            }
+        }
+        match recorded {
+            Some(name) if name != comparator => bail!(
+                "the storage was created with comparator {}, it cannot be opened with {}",
+                name,
+                comparator
+            ),
+            Some(_) => {}
+            None => manifest.add_record(&ManifestRecord::Comparator(comparator.to_string()))?,
+        }
+
         Ok(Self {
-            inner: Arc::new(RwLock::new(Arc::new(LsmStorageInner::create()))),
+            inner: Arc::new(RwLock::new(Arc::new(LsmStorageInner::create(
+                options.comparator.clone(),
+            )))),
+            write_lock: Mutex::new(()),
+            flush_lock: Mutex::new(()),
+            compaction_lock: Mutex::new(()),
+            next_seq: AtomicU64::new(1),
+            next_sst_id: AtomicUsize::new(1),
+            gc_watermark: AtomicU64::new(0),
+            options,
+            block_cache: Arc::new(BlockCache::new(1 << 20)),
+            path: path.as_ref().to_path_buf(),
         })
     }
 
+    pub(crate) fn snapshot(&self) -> Arc<LsmStorageInner> {
+        let guard = self.inner.read();
+        Arc::clone(&guard)
+    }
+
     /// Get a key from the storage. In day 7, this can be further optimized by using a bloom filter.
     pub fn get(&self, key: &[u8]) -> Result<Option<Bytes>> {
-        unimplemented!()
+        self.ensure_user_timestamp(false)?;
+        let snapshot = self.snapshot();
+
+        let range_tombstones = self.range_tombstones(&snapshot);
+        let now = value::unix_millis();
+
+        // Search on the current memtable, then on immutable memtables from latest to earliest,
+        // collecting merge operands until a value or a deletion is found.
+        let mut operands = MergeOperands::default();
+        let memtables =
+            std::iter::once(&snapshot.memtable).chain(snapshot.imm_memtables.iter().rev());
+        for memtable in memtables {
+            if let Some(raw) = memtable.get(key) {
+                let record = Record::decode(&raw)?.expire(now);
+                if !operands.push(&range_tombstones.mask(key, record))? {
+                    return self.finish_merge(key, operands);
+                }
+            }
+        }
+
+        // An SST holds a single version of each key, look them up from the newest SST.
+        let tables = snapshot
+            .l0_sstables
+            .iter()
+            .rev()
+            .chain(snapshot.levels.iter().flatten())
+            .filter(|table| {
+                table.may_overlap(Bound::Included(key), Bound::Included(key))
+                    && table.may_contain_key(key)
+            });
+        for table in tables {
+            if let Some(raw) = table.get(key)? {
+                let record = Record::decode(&raw)?.expire(now);
+                if !operands.push(&range_tombstones.mask(key, record))? {
+                    break;
+                }
+            }
+        }
+        self.finish_merge(key, operands)
+    }
+
+    /// Check if `key` may exist, without reading any block: false means the key is absent, true
+    /// that it may be present. Only the memtables and the filters of the SSTs are checked, so a
+    /// key deleted in an SST, or ruled out by none of the filters, may exist.
+    pub fn key_may_exist(&self, key: &[u8]) -> Result<bool> {
+        self.ensure_user_timestamp(false)?;
+        let snapshot = self.snapshot();
+        let range_tombstones = self.range_tombstones(&snapshot);
+        let now = value::unix_millis();
+
+        // The newest record in the memtables decides, merge operands making the key exist.
+        let memtables =
+            std::iter::once(&snapshot.memtable).chain(snapshot.imm_memtables.iter().rev());
+        for memtable in memtables {
+            if let Some(raw) = memtable.get(key) {
+                let record = range_tombstones.mask(key, Record::decode(&raw)?.expire(now));
+                return Ok(!record.is_tombstone());
+            }
+        }
+        Ok(snapshot
+            .l0_sstables
+            .iter()
+            .chain(snapshot.levels.iter().flatten())
+            .any(|table| {
+                table.may_overlap(Bound::Included(key), Bound::Included(key))
+                    && table.may_contain_key(key)
+            }))
+    }
+
+    /// Check if `key` exists, like `get(key)` returning a value, without copying the value or
+    /// applying merge operands: a key with merge operands on top of its latest write exists.
+    pub fn contains(&self, key: &[u8]) -> Result<bool> {
+        self.ensure_user_timestamp(false)?;
+        let snapshot = self.snapshot();
+        let range_tombstones = self.range_tombstones(&snapshot);
+        let now = value::unix_millis();
+        let exists = |raw: &[u8]| -> Result<bool> {
+            let record = range_tombstones.mask(key, Record::decode(raw)?.expire(now));
+            Ok(!record.is_tombstone())
+        };
+
+        let memtables =
+            std::iter::once(&snapshot.memtable).chain(snapshot.imm_memtables.iter().rev());
+        for memtable in memtables {
+            if let Some(raw) = memtable.get(key) {
+                return exists(&raw);
+            }
+        }
+        let tables = snapshot
+            .l0_sstables
+            .iter()
+            .rev()
+            .chain(snapshot.levels.iter().flatten())
+            .filter(|table| {
+                table.may_overlap(Bound::Included(key), Bound::Included(key))
+                    && table.may_contain_key(key)
+            });
+        for table in tables {
+            if let Some(exists) = table.get_with(key, exists)? {
+                return exists;
+            }
+        }
+        Ok(false)
+    }
+
+    /// Get several keys from one snapshot of the storage, like `get` for each key. The keys are
+    /// looked up in order, so that each SST is visited once, its filters checked once for each
+    /// key, and each of its blocks read once for all the keys in it.
+    pub fn multi_get(&self, keys: &[&[u8]]) -> Vec<Result<Option<Bytes>>> {
+        let comparator = &self.options.comparator;
+        // Each distinct key is looked up once, in the order of the comparator.
+        let mut sorted = keys.to_vec();
+        sorted.sort_by(|a, b| comparator.compare(a, b));
+        sorted.dedup_by(|a, b| comparator.compare(a, b).is_eq());
+        let distinct: Vec<Result<Option<Bytes>, Arc<anyhow::Error>>> =
+            match self.ensure_user_timestamp(false) {
+                Ok(()) => self
+                    .multi_get_sorted(&sorted)
+                    .into_iter()
+                    .map(|result| result.map_err(Arc::new))
+                    .collect(),
+                Err(err) => {
+                    let err = Arc::new(err);
+                    sorted.iter().map(|_| Err(err.clone())).collect()
+                }
+            };
+
+        // Keys given more than once share the result of their lookup.
+        let results: Vec<_> = keys
+            .iter()
+            .map(|key| {
+                let idx = sorted
+                    .binary_search_by(|probe| comparator.compare(probe, key))
+                    .unwrap();
+                distinct[idx].clone()
+            })
+            .collect();
+        // The last key sharing an error gets it back.
+        drop(distinct);
+        results
+            .into_iter()
+            .map(|result| result.map_err(unshare_error))
+            .collect()
+    }
+
+    /// Look up distinct keys sorted by the comparator, see `multi_get`.
+    fn multi_get_sorted(&self, sorted: &[&[u8]]) -> Vec<Result<Option<Bytes>>> {
+        let snapshot = self.snapshot();
+        let range_tombstones = self.range_tombstones(&snapshot);
+        let now = value::unix_millis();
+        let mut lookups: Vec<KeyLookup> = sorted.iter().map(|_| KeyLookup::default()).collect();
+        let push = |lookup: &mut KeyLookup, key: &[u8], raw: &[u8]| {
+            let pushed = Record::decode(raw).and_then(|record| {
+                let record = range_tombstones.mask(key, record.expire(now));
+                lookup.operands.push(&record)
+            });
+            match pushed {
+                Ok(more) => lookup.done = !more,
+                Err(err) => lookup.error = Some(err),
+            }
+        };
+
+        let memtables =
+            std::iter::once(&snapshot.memtable).chain(snapshot.imm_memtables.iter().rev());
+        for (key, lookup) in sorted.iter().zip(&mut lookups) {
+            for memtable in memtables.clone() {
+                if lookup.is_done() {
+                    break;
+                }
+                if let Some(raw) = memtable.get(key) {
+                    push(lookup, key, &raw);
+                }
+            }
+        }
+
+        // An SST holds a single version of each key, look the keys up from the newest SST.
+        let tables = snapshot
+            .l0_sstables
+            .iter()
+            .rev()
+            .chain(snapshot.levels.iter().flatten());
+        for table in tables {
+            let (idxs, table_keys): (Vec<usize>, Vec<&[u8]>) = sorted
+                .iter()
+                .enumerate()
+                .filter(|&(idx, key)| {
+                    !lookups[idx].is_done()
+                        && table.may_overlap(Bound::Included(key), Bound::Included(key))
+                        && table.may_contain_key(key)
+                })
+                .map(|(idx, key)| (idx, *key))
+                .unzip();
+            if idxs.is_empty() {
+                continue;
+            }
+            let found = table.multi_get(&table_keys);
+            for ((idx, key), found) in idxs.into_iter().zip(table_keys).zip(found) {
+                match found {
+                    Ok(Some(raw)) => push(&mut lookups[idx], key, &raw),
+                    Ok(None) => {}
+                    Err(err) => lookups[idx].error = Some(err),
+                }
+            }
+        }
+
+        sorted
+            .iter()
+            .zip(lookups)
+            .map(|(key, lookup)| match lookup.error {
+                Some(err) => Err(err),
+                None => self.finish_merge(key, lookup.operands),
+            })
+            .collect()
+    }
+
+    fn finish_merge(&self, key: &[u8], operands: MergeOperands) -> Result<Option<Bytes>> {
+        Ok(operands
+            .finish(key, self.options.merge_operator.as_deref())?
+            .map(Bytes::from))
+    }
+
+    /// Get the newest version of a key whose timestamp is at or below `read_ts`.
+    pub fn get_with_ts(&self, key: &[u8], read_ts: u64) -> Result<Option<Bytes>> {
+        self.ensure_user_timestamp(true)?;
+        let snapshot = self.snapshot();
+        let lower = key::key_with_ts(key, read_ts);
+        let upper = key::key_with_ts(key, 0);
+        let iter = self.create_inner_iter(
+            &snapshot,
+            Bound::Included(&lower),
+            Bound::Included(&upper),
+            None,
+        )?;
+        // SSTs do not stop at `upper`, make sure the entry is a version of `key`.
+        if iter.is_valid() && key::user_key_prefix(iter.key()) == key::user_key_prefix(&upper) {
+            return Self::live_value(iter.value(), value::unix_millis());
+        }
+        Ok(None)
+    }
+
+    /// Get every retained version of a key, newest first, up to `limit` versions. Each version is
+    /// returned with the sequence number of the write that produced it, and the value of the key
+    /// right after that write, `None` for deletions. Only the `limit` newest versions are read,
+    /// and below them the merge operands of the oldest one down to the put or deletion they apply
+    /// to.
+    pub fn get_versions(&self, key: &[u8], limit: usize) -> Result<Vec<(u64, Option<Bytes>)>> {
+        self.ensure_user_timestamp(false)?;
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let snapshot = self.snapshot();
+        let range_tombstones = self.range_tombstones(&snapshot);
+        let now = value::unix_millis();
+        let mut iter =
+            self.create_inner_iter(&snapshot, Bound::Included(key), Bound::Included(key), None)?;
+        let mut records = Vec::new();
+        while iter.is_valid() && iter.key() == key {
+            let raw = Bytes::copy_from_slice(iter.value());
+            let record = range_tombstones.mask(key, Record::decode(&raw)?.expire(now));
+            let resolved = record.kind != ValueKind::Merge;
+            records.push(raw);
+            if resolved && records.len() >= limit {
+                break;
+            }
+            iter.next()?;
+        }
+
+        // Merge operands only make sense on top of the older versions, replay from the oldest.
+        let mut versions = Vec::with_capacity(records.len());
+        let mut value: Option<Bytes> = None;
+        for raw in records.iter().rev() {
+            let record = range_tombstones.mask(key, Record::decode(raw)?.expire(now));
+            value = match record.kind {
+                ValueKind::Put => Some(Bytes::copy_from_slice(record.value)),
+                ValueKind::Delete => None,
+                ValueKind::Merge => {
+                    let Some(operator) = self.options.merge_operator.as_ref() else {
+                        bail!("found merge operands but no merge operator is configured");
+                    };
+                    let operands = record.operands()?;
+                    Some(
+                        operator
+                            .full_merge(key, value.as_deref(), &operands)?
+                            .into(),
+                    )
+                }
+            };
+            versions.push((record.seq, value.clone()));
+        }
+        versions.reverse();
+        versions.truncate(limit);
+        Ok(versions)
+    }
+
+    /// Decode a stored value, returning `None` for deletions and records expired at `now`.
+    fn live_value(raw: &[u8], now: u64) -> Result<Option<Bytes>> {
+        let record = Record::decode(raw)?.expire(now);
+        if record.is_tombstone() {
+            Ok(None)
+        } else {
+            Ok(Some(Bytes::copy_from_slice(record.value)))
+        }
     }
 
     /// Put a key-value pair into the storage by writing into the current memtable.
     pub fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
         assert!(!value.is_empty(), "value cannot be empty");
         assert!(!key.is_empty(), "key cannot be empty");
-        unimplemented!()
+        self.ensure_user_timestamp(false)?;
+        self.write(key, ValueKind::Put, None, value)
+    }
+
+    /// Put a key-value pair with an application-supplied timestamp. Versions with different
+    /// timestamps are kept side by side until compaction collects them.
+    pub fn put_with_ts(&self, key: &[u8], ts: u64, value: &[u8]) -> Result<()> {
+        assert!(!value.is_empty(), "value cannot be empty");
+        assert!(!key.is_empty(), "key cannot be empty");
+        self.ensure_user_timestamp(true)?;
+        self.write(&key::key_with_ts(key, ts), ValueKind::Put, None, value)
+    }
+
+    /// Put a key-value pair that reads as deleted once `ttl` has elapsed. Compaction removes it
+    /// after that.
+    pub fn put_with_ttl(&self, key: &[u8], value: &[u8], ttl: Duration) -> Result<()> {
+        assert!(!value.is_empty(), "value cannot be empty");
+        assert!(!key.is_empty(), "key cannot be empty");
+        self.ensure_user_timestamp(false)?;
+        let expire_at = value::unix_millis().saturating_add(ttl.as_millis() as u64);
+        self.write(key, ValueKind::Put, Some(expire_at), value)
+    }
+
+    /// Remove a key from the storage by writing a deletion marker.
+    pub fn delete(&self, key: &[u8]) -> Result<()> {
+        assert!(!key.is_empty(), "key cannot be empty");
+        self.ensure_user_timestamp(false)?;
+        self.write(key, ValueKind::Delete, None, b"")
+    }
+
+    /// Remove a key as of timestamp `ts` by writing a deletion marker.
+    pub fn delete_with_ts(&self, key: &[u8], ts: u64) -> Result<()> {
+        assert!(!key.is_empty(), "key cannot be empty");
+        self.ensure_user_timestamp(true)?;
+        self.write(&key::key_with_ts(key, ts), ValueKind::Delete, None, b"")
+    }
+
+    /// Write a merge operand for a key without reading it. The configured merge operator folds
+    /// the operands onto the value of the key when it is read or compacted.
+    pub fn merge(&self, key: &[u8], operand: &[u8]) -> Result<()> {
+        assert!(!key.is_empty(), "key cannot be empty");
+        self.ensure_user_timestamp(false)?;
+        let Some(operator) = self.options.merge_operator.as_deref() else {
+            bail!("merge requires a merge operator");
+        };
+
+        let _write_lock = self.write_lock.lock();
+        let guard = self.inner.read();
+        let seq = self.next_seq.fetch_add(1, Ordering::SeqCst);
+        // The memtable keeps a single record per key, combine the operand with the existing one.
+        let mut operands = MergeOperands::default();
+        operands.push(&Record {
+            seq,
+            kind: ValueKind::Merge,
+            expire_at: None,
+            value: &Record::encode_operands(&[operand]),
+        })?;
+        if let Some(raw) = guard.memtable.get(key) {
+            let range_tombstones = RangeTombstoneList::new_with_comparator(
+                &guard.memtable.range_tombstones(),
+                self.options.comparator.clone(),
+            );
+            let record = Record::decode(&raw)?.expire(value::unix_millis());
+            operands.push(&range_tombstones.mask(key, record))?;
+        }
+        let record = if operands.is_resolved() {
+            // A put or deletion is below, the operands can be applied right away.
+            let expire_at = operands.expire_at();
+            let value = operands.finish(key, Some(operator))?.unwrap();
+            Record::encode_with_expiry(seq, ValueKind::Put, expire_at, &value)
+        } else {
+            let value = Record::encode_operands(&operands.operands());
+            Record::encode(seq, ValueKind::Merge, &value)
+        };
+        guard.memtable.put(key, &record);
+        Ok(())
+    }
+
+    /// Remove every key in `[start, end)` by writing a single range tombstone. An empty range
+    /// removes nothing, and a range whose end is before its start is an error.
+    pub fn delete_range(&self, start: &[u8], end: &[u8]) -> Result<()> {
+        self.ensure_user_timestamp(false)?;
+        match self.options.comparator.compare(start, end) {
+            cmp::Ordering::Less => {}
+            cmp::Ordering::Equal => return Ok(()),
+            cmp::Ordering::Greater => bail!("range end is before its start"),
+        }
+        let _write_lock = self.write_lock.lock();
+        let guard = self.inner.read();
+        let seq = self.next_seq.fetch_add(1, Ordering::SeqCst);
+        guard.memtable.add_range_tombstone(RangeTombstone {
+            start: Bytes::copy_from_slice(start),
+            end: Bytes::copy_from_slice(end),
+            seq,
+        });
+        Ok(())
+    }
+
+    /// Write a record to the memtable under a new sequence number. `expire_at` is the time, in
+    /// milliseconds since the Unix epoch, after which the record reads as deleted.
+    fn write(
+        &self,
+        key: &[u8],
+        kind: ValueKind,
+        expire_at: Option<u64>,
+        value: &[u8],
+    ) -> Result<()> {
+        let _write_lock = self.write_lock.lock();
+        let guard = self.inner.read();
+        let seq = self.next_seq.fetch_add(1, Ordering::SeqCst);
+        let record = Record::encode_with_expiry(seq, kind, expire_at, value);
+        guard.memtable.put(key, &record);
+        Ok(())
+    }
+
+    /// Let compaction drop versions that are shadowed by a newer version at or below `ts`. Reads
+    /// at timestamps below the watermark may no longer see the versions they used to.
+    pub fn set_gc_watermark(&self, ts: u64) {
+        self.gc_watermark.fetch_max(ts, Ordering::SeqCst);
+    }
+
+    fn ensure_user_timestamp(&self, expected: bool) -> Result<()> {
+        ensure!(
+            self.options.enable_user_timestamp == expected,
+            "user timestamps are {}, use the {} API instead",
+            if expected { "disabled" } else { "enabled" },
+            if expected { "plain" } else { "`*_with_ts`" },
+        );
+        Ok(())
+    }
+
+    pub(crate) fn next_sst_id(&self) -> usize {
+        self.next_sst_id.fetch_add(1, Ordering::SeqCst)
+    }
+
+    /// Create a builder for an SST of `level`, 0 being L0.
+    pub(crate) fn sst_builder(&self, level: usize) -> SsTableBuilder {
+        let mut builder = SsTableBuilder::new(self.options.block_size);
+        builder.set_compression(self.options.compression_for_level(level));
+        builder.set_index_partition_size(self.options.index_partition_size);
+        builder.set_block_hash_index(self.options.block_hash_index);
+        builder.set_comparator(self.options.comparator.clone());
+        let bits_per_key = self
+            .filter_bits_per_level(&self.snapshot())
+            .get(level)
+            .copied()
+            .unwrap_or(self.options.bloom_bits_per_key);
+        // Levels allocated no bits are better off without filters.
+        let filter_policy = self
+            .options
+            .filter_policy_for_level(level)
+            .filter(|_| bits_per_key > 0.0);
+        builder.set_filter_policy(filter_policy, bits_per_key);
+        // Keys with a timestamp are not looked up whole.
+        builder.set_whole_key_filtering(!self.options.enable_user_timestamp);
+        builder.set_prefix_extractor(self.options.prefix_extractor.clone());
+        builder.set_range_filter(self.options.range_filter);
+        builder.set_user_timestamp(self.options.enable_user_timestamp);
+        for factory in &self.options.table_properties_collector_factories {
+            builder.add_properties_collector(factory.create());
+        }
+        builder
     }
 
-    /// Remove a key from the storage by writing an empty value.
-    pub fn delete(&self, _key: &[u8]) -> Result<()> {
-        unimplemented!()
+    pub(crate) fn path_of_sst(&self, id: usize) -> PathBuf {
+        self.path.join(format!("{:05}.sst", id))
+    }
+
+    /// Get the statistics of each level, including the bits per key of the filters allocated to
+    /// it. Data still in memtables is not accounted for.
+    pub fn stats(&self) -> LsmStorageStats {
+        let snapshot = self.snapshot();
+        let bits_per_level = self.filter_bits_per_level(&snapshot);
+        let levels = snapshot
+            .tables_per_level()
+            .zip(bits_per_level)
+            .map(|(tables, target_filter_bits_per_key)| LevelStats {
+                num_sstables: tables.len(),
+                num_entries: tables.iter().map(|t| t.properties().num_entries).sum(),
+                target_filter_bits_per_key,
+                filter_size: tables.iter().map(|t| t.filter_size() as u64).sum(),
+            })
+            .collect();
+        LsmStorageStats { levels }
+    }
+
+    /// Allocate the bits per key of the filters of each level of a snapshot, starting from L0.
+    /// Each L0 SST is a sorted run of its own, the SSTs of the other levels form one.
+    fn filter_bits_per_level(&self, snapshot: &LsmStorageInner) -> Vec<f64> {
+        let levels: Vec<(u64, usize)> = snapshot
+            .tables_per_level()
+            .enumerate()
+            .map(|(level, tables)| {
+                let num_entries = tables.iter().map(|t| t.properties().num_entries).sum();
+                let num_runs = if level == 0 { tables.len() } else { 1 };
+                (num_entries, num_runs)
+            })
+            .collect();
+        self.options
+            .filter_bits_allocation
+            .allocate(&levels, self.options.bloom_bits_per_key)
+    }
+
+    /// Get the user properties of all SSTs, summing the values of each name. Data still in
+    /// memtables is not accounted for.
+    pub fn user_properties(&self) -> UserProperties {
+        let snapshot = self.snapshot();
+        let tables = snapshot
+            .l0_sstables
+            .iter()
+            .chain(snapshot.levels.iter().flatten());
+        let mut properties = UserProperties::new();
+        for table in tables {
+            for (name, value) in &table.properties().user_properties {
+                let sum = properties.entry(name.clone()).or_default();
+                *sum = sum.saturating_add(*value);
+            }
+        }
+        properties
     }
 
     /// Persist data to disk.
@@ -77,15 +824,235 @@ impl LsmStorage {
     /// In day 3: flush the current memtable to disk as L0 SST.
     /// In day 6: call `fsync` on WAL.
     pub fn sync(&self) -> Result<()> {
-        unimplemented!()
+        let _flush_lock = self.flush_lock.lock();
+
+        // Move the mutable memtable to the immutable memtables.
+        let flush_memtable;
+        {
+            let mut guard = self.inner.write();
+            // There is nothing to flush, and no empty SST is needed.
+            if guard.memtable.is_empty() {
+                return Ok(());
+            }
+            let mut snapshot = guard.as_ref().clone();
+            let memtable = MemTable::create_with_comparator(self.options.comparator.clone());
+            let memtable = std::mem::replace(&mut snapshot.memtable, Arc::new(memtable));
+            flush_memtable = memtable.clone();
+            snapshot.imm_memtables.push(memtable);
+            *guard = Arc::new(snapshot);
+        }
+
+        // Build the L0 SST without holding the lock, writes go to the new memtable meanwhile.
+        let mut builder = self.sst_builder(0);
+        flush_memtable.flush(&mut builder)?;
+        let sst_id = self.next_sst_id();
+        let sst = Arc::new(builder.build(
+            sst_id,
+            Some(self.block_cache.clone()),
+            self.path_of_sst(sst_id),
+        )?);
+
+        {
+            let mut guard = self.inner.write();
+            let mut snapshot = guard.as_ref().clone();
+            snapshot.imm_memtables.pop();
+            snapshot.l0_sstables.push(sst);
+            *guard = Arc::new(snapshot);
+        }
+        Ok(())
     }
 
     /// Create an iterator over a range of keys.
     pub fn scan(
         &self,
-        _lower: Bound<&[u8]>,
-        _upper: Bound<&[u8]>,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> Result<FusedIterator<LsmIterator>> {
+        self.ensure_user_timestamp(false)?;
+        let snapshot = self.snapshot();
+        let iter = self.create_inner_iter(&snapshot, lower, upper, None)?;
+        Ok(FusedIterator::new(LsmIterator::new(
+            iter,
+            map_bound(lower),
+            map_bound(upper),
+            self.options.merge_operator.clone(),
+            self.range_tombstones(&snapshot),
+            self.options.comparator.clone(),
+        )?))
+    }
+
+    /// Create an iterator over a range of keys in descending order, starting from the last one.
+    pub fn scan_rev(
+        &self,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> Result<FusedIterator<RevIterator<LsmIterator>>> {
+        self.ensure_user_timestamp(false)?;
+        let snapshot = self.snapshot();
+        let iter = self.create_inner_iter(&snapshot, lower, upper, None)?;
+        Ok(FusedIterator::new(RevIterator::new(LsmIterator::new_rev(
+            iter,
+            map_bound(lower),
+            map_bound(upper),
+            self.options.merge_operator.clone(),
+            self.range_tombstones(&snapshot),
+            self.options.comparator.clone(),
+        )?)))
+    }
+
+    /// Create an iterator over a range of keys, returning the newest version at or below
+    /// `read_ts` of each key.
+    pub fn scan_with_ts(
+        &self,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+        read_ts: u64,
     ) -> Result<FusedIterator<LsmIterator>> {
-        unimplemented!()
+        self.ensure_user_timestamp(true)?;
+        let snapshot = self.snapshot();
+        let lower = key::lower_bound_with_ts(lower);
+        let lower = map_bound(lower.as_ref().map(Vec::as_slice));
+        let upper = key::upper_bound_with_ts(upper);
+        let upper = map_bound(upper.as_ref().map(Vec::as_slice));
+        let iter = self.create_inner_iter(
+            &snapshot,
+            lower.as_ref().map(Bytes::as_ref),
+            upper.as_ref().map(Bytes::as_ref),
+            None,
+        )?;
+        Ok(FusedIterator::new(LsmIterator::new_with_ts(
+            iter, lower, upper, read_ts,
+        )?))
+    }
+
+    /// Create an iterator over the keys starting with `prefix`, which ends as soon as keys leave
+    /// the prefix. When `prefix` is a prefix extracted by `LsmStorageOptions::prefix_extractor`,
+    /// the SSTs whose prefix filter rules it out are not read. Requires the bytewise comparator,
+    /// as other orders may not keep the keys of a prefix together.
+    pub fn scan_prefix(&self, prefix: &[u8]) -> Result<FusedIterator<LsmIterator>> {
+        self.ensure_user_timestamp(false)?;
+        ensure!(
+            self.options.comparator.name() == BytewiseComparator.name(),
+            "prefix scans require the bytewise comparator, the storage uses {}",
+            self.options.comparator.name()
+        );
+        let successor = prefix_successor(prefix);
+        let lower = Bound::Included(prefix);
+        let upper = match &successor {
+            Some(successor) => Bound::Excluded(successor.as_slice()),
+            None => Bound::Unbounded,
+        };
+        let snapshot = self.snapshot();
+        let iter = self.create_inner_iter(&snapshot, lower, upper, Some(prefix))?;
+        Ok(FusedIterator::new(LsmIterator::new(
+            iter,
+            map_bound(lower),
+            map_bound(upper),
+            self.options.merge_operator.clone(),
+            self.range_tombstones(&snapshot),
+            self.options.comparator.clone(),
+        )?))
+    }
+
+    /// Collect the range tombstones of all memtables and SSTs of a snapshot.
+    fn range_tombstones(&self, snapshot: &LsmStorageInner) -> RangeTombstoneList {
+        let mut tombstones = snapshot.memtable.range_tombstones();
+        for memtable in &snapshot.imm_memtables {
+            tombstones.extend(memtable.range_tombstones());
+        }
+        let tables = snapshot
+            .l0_sstables
+            .iter()
+            .chain(snapshot.levels.iter().flatten());
+        for table in tables {
+            tombstones.extend_from_slice(table.range_tombstones());
+        }
+        RangeTombstoneList::new_with_comparator(&tombstones, self.options.comparator.clone())
+    }
+
+    /// Merge the memtables and SSTs of a snapshot, starting from `lower`. Every version of a key is
+    /// produced, newest first. Keys beyond `upper` may still be produced by SSTs. With a `prefix`,
+    /// the SSTs whose prefix filter rules it out are skipped.
+    fn create_inner_iter(
+        &self,
+        snapshot: &LsmStorageInner,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+        prefix: Option<&[u8]>,
+    ) -> Result<LsmIteratorInner> {
+        let mut memtable_iters = Vec::with_capacity(snapshot.imm_memtables.len() + 1);
+        memtable_iters.push(Box::new(snapshot.memtable.scan(lower, upper)));
+        for memtable in snapshot.imm_memtables.iter().rev() {
+            memtable_iters.push(Box::new(memtable.scan(lower, upper)));
+        }
+        let comparator = &self.options.comparator;
+        let memtable_iter =
+            MergeIterator::create_all_versions_with_comparator(memtable_iters, comparator.clone());
+        let table_iter = MergeIterator::create_all_versions_with_comparator(
+            self.table_iters(snapshot, lower, upper, prefix)?,
+            comparator.clone(),
+        );
+        TwoMergeIterator::create_all_versions_with_comparator(
+            memtable_iter,
+            table_iter,
+            comparator.clone(),
+        )
+    }
+
+    /// Create iterators positioned at `lower` over the SSTs of a snapshot whose key range overlaps
+    /// `[lower, upper]`, newest first. SSTs whose range filter rules the range out, or whose prefix
+    /// filter rules out `prefix`, if any, are skipped.
+    #[allow(clippy::vec_box)] // `MergeIterator::create` takes boxed iterators
+    fn table_iters(
+        &self,
+        snapshot: &LsmStorageInner,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+        prefix: Option<&[u8]>,
+    ) -> Result<Vec<Box<SsTableIterator>>> {
+        let tables = snapshot
+            .l0_sstables
+            .iter()
+            .rev()
+            .chain(snapshot.levels.iter().flatten())
+            .filter(|table| table.may_overlap(lower, upper))
+            .filter(|table| match (prefix, &self.options.prefix_extractor) {
+                (Some(prefix), Some(extractor)) => table.may_contain_prefix(&**extractor, prefix),
+                _ => true,
+            });
+        let mut iters = Vec::new();
+        for table in tables {
+            let iter = match lower {
+                Bound::Included(key) => {
+                    SsTableIterator::create_and_seek_to_key(table.clone(), key)?
+                }
+                Bound::Excluded(key) => {
+                    let mut iter = SsTableIterator::create_and_seek_to_key(table.clone(), key)?;
+                    if iter.is_valid() && iter.key() == key {
+                        iter.next()?;
+                    }
+                    iter
+                }
+                Bound::Unbounded => SsTableIterator::create_and_seek_to_first(table.clone())?,
+            };
+            iters.push(Box::new(iter));
+        }
+        Ok(iters)
+    }
+}
+
+/// Get the first key after all the keys starting with `prefix`, `None` if there is none.
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let len = prefix.iter().rposition(|&b| b != 0xff)? + 1;
+    let mut successor = prefix[..len].to_vec();
+    successor[len - 1] += 1;
+    Some(successor)
+}
+
+fn map_bound(bound: Bound<&[u8]>) -> Bound<Bytes> {
+    match bound {
+        Bound::Included(x) => Bound::Included(Bytes::copy_from_slice(x)),
+        Bound::Excluded(x) => Bound::Excluded(Bytes::copy_from_slice(x)),
+        Bound::Unbounded => Bound::Unbounded,
     }
 }
@@ -0,0 +1,100 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use bytes::{Buf, BufMut};
+
+use crate::table::checksum::crc32c;
+
+/// The name of the manifest file in the directory of the storage.
+pub const MANIFEST_FILE: &str = "MANIFEST";
+
+/// A change to the storage recorded in the manifest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ManifestRecord {
+    /// The name of the comparator ordering the keys, see `Comparator::name`. Recorded when the
+    /// storage is created.
+    Comparator(String),
+}
+
+const COMPARATOR_TAG: u8 = 1;
+
+impl ManifestRecord {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            ManifestRecord::Comparator(name) => {
+                buf.put_u8(COMPARATOR_TAG);
+                buf.put_slice(name.as_bytes());
+            }
+        }
+    }
+
+    fn decode(mut buf: &[u8]) -> Result<Self> {
+        if !buf.has_remaining() {
+            bail!("empty manifest record");
+        }
+        match buf.get_u8() {
+            COMPARATOR_TAG => Ok(ManifestRecord::Comparator(
+                String::from_utf8(buf.to_vec()).context("comparator name is not UTF-8")?,
+            )),
+            tag => bail!("unknown manifest record type {}", tag),
+        }
+    }
+}
+
+/// The manifest of a storage, a log of `ManifestRecord`s. Each record is stored as:
+///
+/// | len (u32) | crc32c (u32) | type (u8) | payload |
+///
+/// The length and checksum, little-endian, cover the type and the payload.
+pub struct Manifest {
+    file: File,
+}
+
+impl Manifest {
+    /// Open the manifest in the directory `path`, creating both if needed, and read its records.
+    pub fn open(path: &Path) -> Result<(Self, Vec<ManifestRecord>)> {
+        std::fs::create_dir_all(path)?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path.join(MANIFEST_FILE))?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+
+        let mut buf = &data[..];
+        let mut records = Vec::new();
+        while buf.has_remaining() {
+            if buf.remaining() < 8 {
+                bail!("truncated manifest record");
+            }
+            let len = buf.get_u32_le() as usize;
+            let checksum = buf.get_u32_le();
+            if buf.remaining() < len {
+                bail!("truncated manifest record");
+            }
+            let (record, rest) = buf.split_at(len);
+            if crc32c(record) != checksum {
+                bail!("manifest record {} is corrupted", records.len());
+            }
+            records.push(ManifestRecord::decode(record)?);
+            buf = rest;
+        }
+        Ok((Self { file }, records))
+    }
+
+    /// Append a record and sync it to the disk.
+    pub fn add_record(&mut self, record: &ManifestRecord) -> Result<()> {
+        let mut payload = Vec::new();
+        record.encode(&mut payload);
+        let mut buf = Vec::with_capacity(8 + payload.len());
+        buf.put_u32_le(payload.len() as u32);
+        buf.put_u32_le(crc32c(&payload));
+        buf.put_slice(&payload);
+        self.file.write_all(&buf)?;
+        self.file.sync_all()?;
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
 #![allow(unused_variables)] // TODO(you): remove this lint after implementing this mod
 #![allow(dead_code)] // TODO(you): remove this lint after implementing this mod
 
+use std::borrow::Borrow;
+use std::cmp::Ordering;
 use std::ops::Bound;
 use std::sync::Arc;
 
@@ -9,91 +11,249 @@ use bytes::Bytes;
 use crossbeam_skiplist::map::Entry;
 use crossbeam_skiplist::SkipMap;
 use ouroboros::self_referencing;
+use parking_lot::RwLock;
 
-use crate::iterators::StorageIterator;
+use crate::comparator::{BytewiseComparator, Comparator};
+use crate::iterators::{BidirectionalIterator, StorageIterator};
+use crate::range_tombstone::RangeTombstone;
 use crate::table::SsTableBuilder;
 
+/// A key of the skiplist. Keys are ordered bytewise, or by `comparator` in mem-tables with another
+/// comparator.
+struct MemTableKey {
+    key: Bytes,
+    comparator: Option<Arc<dyn Comparator>>,
+}
+
+/// A key compared with the keys of the skiplist. Stored keys are borrowed as `dyn SkipMapKey`, so
+/// that keys are looked up as `LookupKey`s without copying them.
+trait SkipMapKey {
+    fn key(&self) -> &[u8];
+
+    /// The comparator ordering the key, `None` for bytewise.
+    fn comparator(&self) -> Option<&dyn Comparator>;
+}
+
+impl SkipMapKey for MemTableKey {
+    fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    fn comparator(&self) -> Option<&dyn Comparator> {
+        self.comparator.as_deref()
+    }
+}
+
+/// A key looked up in the skiplist.
+struct LookupKey<'a> {
+    key: &'a [u8],
+    comparator: Option<&'a dyn Comparator>,
+}
+
+impl SkipMapKey for LookupKey<'_> {
+    fn key(&self) -> &[u8] {
+        self.key
+    }
+
+    fn comparator(&self) -> Option<&dyn Comparator> {
+        self.comparator
+    }
+}
+
+impl PartialEq for dyn SkipMapKey + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for dyn SkipMapKey + '_ {}
+
+impl PartialOrd for dyn SkipMapKey + '_ {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for dyn SkipMapKey + '_ {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.comparator() {
+            Some(comparator) => comparator.compare(self.key(), other.key()),
+            None => self.key().cmp(other.key()),
+        }
+    }
+}
+
+impl<'a> Borrow<dyn SkipMapKey + 'a> for MemTableKey {
+    fn borrow(&self) -> &(dyn SkipMapKey + 'a) {
+        self
+    }
+}
+
+impl PartialEq for MemTableKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MemTableKey {}
+
+impl PartialOrd for MemTableKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MemTableKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        <dyn SkipMapKey>::cmp(self, other)
+    }
+}
+
 /// A basic mem-table based on crossbeam-skiplist
 pub struct MemTable {
-    map: Arc<SkipMap<Bytes, Bytes>>,
+    map: Arc<SkipMap<MemTableKey, Bytes>>,
+    range_tombstones: RwLock<Vec<RangeTombstone>>,
+    /// Orders the keys, `None` for bytewise.
+    comparator: Option<Arc<dyn Comparator>>,
 }
 
 impl MemTable {
     /// Create a new mem-table.
     pub fn create() -> Self {
+        Self::create_with_comparator(Arc::new(BytewiseComparator))
+    }
+
+    /// Create a new mem-table ordering its keys by `comparator`.
+    pub fn create_with_comparator(comparator: Arc<dyn Comparator>) -> Self {
+        // Bytewise keys are compared without going through the comparator.
+        let comparator = Some(comparator).filter(|c| c.name() != BytewiseComparator.name());
         MemTable {
             map: Arc::new(SkipMap::new()),
+            range_tombstones: RwLock::new(Vec::new()),
+            comparator,
         }
     }
 
     /// Get a value by key.
     pub fn get(&self, key: &[u8]) -> Option<Bytes> {
-        match self.map.get(key) {
-            Some(value) => Some(value.value().clone()),
-            None => None,
-        }
+        let key = LookupKey {
+            key,
+            comparator: self.comparator.as_deref(),
+        };
+        self.map
+            .get(&key as &dyn SkipMapKey)
+            .map(|value| value.value().clone())
     }
 
     /// Put a key-value pair into the mem-table.
     pub fn put(&self, key: &[u8], value: &[u8]) {
-        self.map
-            .insert(Bytes::from(key.to_vec()), Bytes::from(value.to_vec()));
+        let key = MemTableKey {
+            key: Bytes::copy_from_slice(key),
+            comparator: self.comparator.clone(),
+        };
+        self.map.insert(key, Bytes::from(value.to_vec()));
+    }
+
+    /// Add a range tombstone to the mem-table.
+    pub fn add_range_tombstone(&self, tombstone: RangeTombstone) {
+        self.range_tombstones.write().push(tombstone);
+    }
+
+    /// Get the range tombstones of the mem-table.
+    pub fn range_tombstones(&self) -> Vec<RangeTombstone> {
+        self.range_tombstones.read().clone()
+    }
+
+    /// Check if the mem-table has neither key-value pairs nor range tombstones.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty() && self.range_tombstones.read().is_empty()
     }
 
     /// Get an iterator over a range of keys.
     pub fn scan(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> MemTableIterator {
-        let lower_bound = match lower {
-            Bound::Unbounded => Bound::Unbounded,
-            Bound::Included(key) => Bound::Included(Bytes::from(key.to_vec())),
-            Bound::Excluded(key) => Bound::Excluded(Bytes::from(key.to_vec())),
-        };
-        let upper_bound = match upper {
-            Bound::Unbounded => Bound::Unbounded,
-            Bound::Included(key) => Bound::Included(Bytes::from(key.to_vec())),
-            Bound::Excluded(key) => Bound::Excluded(Bytes::from(key.to_vec())),
-        };
-
-        // Create the MemTableIterator
         let mut it = MemTableIteratorBuilder {
             map: Arc::clone(&self.map),
-            iter_builder: |map: &Arc<SkipMap<Bytes, Bytes>>| map.range((lower_bound, upper_bound)),
-            // You may need to adjust the item field initialization based on your actual use case
+            lower: lower.map(Bytes::copy_from_slice),
+            upper: upper.map(Bytes::copy_from_slice),
+            comparator: self.comparator.clone(),
+            entry_builder: |_| None,
             item: (Bytes::new(), Bytes::new()),
         }
         .build();
-        let entry = it.with_iter_mut(|it| MemTableIterator::entry_to_item(it.next()));
-        it.with_mut(|x| *x.item = entry);
-        return it;
+        it.move_entry(|map, _, lower, _, _| map.lower_bound(lower));
+        it
     }
 
     /// Flush the mem-table to SSTable.
     pub fn flush(&self, builder: &mut SsTableBuilder) -> Result<()> {
         for entry in self.map.iter() {
-            builder.add(entry.key(), entry.value());
+            builder.add(&entry.key().key, entry.value());
+        }
+        for tombstone in self.range_tombstones.read().iter() {
+            builder.add_range_tombstone(tombstone.clone());
         }
         Ok(())
     }
 }
 
-type SkipMapRangeIter<'a> =
-    crossbeam_skiplist::map::Range<'a, Bytes, (Bound<Bytes>, Bound<Bytes>), Bytes, Bytes>;
+type SkipMapEntry<'a> = Entry<'a, MemTableKey, Bytes>;
 
-/// An iterator over a range of `SkipMap`.
+/// An iterator over a range of `SkipMap`, a cursor on one of its entries.
 #[self_referencing]
 pub struct MemTableIterator {
-    map: Arc<SkipMap<Bytes, Bytes>>,
+    map: Arc<SkipMap<MemTableKey, Bytes>>,
+    lower: Bound<Bytes>,
+    upper: Bound<Bytes>,
+    /// Orders the keys, `None` for bytewise.
+    comparator: Option<Arc<dyn Comparator>>,
     #[borrows(map)]
     #[not_covariant]
-    iter: SkipMapRangeIter<'this>,
+    entry: Option<SkipMapEntry<'this>>,
     item: (Bytes, Bytes),
 }
 
 impl MemTableIterator {
-    fn entry_to_item(entry: Option<Entry<'_, Bytes, Bytes>>) -> (Bytes, Bytes) {
+    fn entry_to_item(entry: Option<&SkipMapEntry<'_>>) -> (Bytes, Bytes) {
         entry
-            .map(|x| (x.key().clone(), x.value().clone()))
+            .map(|x| (x.key().key.clone(), x.value().clone()))
             .unwrap_or_else(|| (Bytes::from_static(&[]), Bytes::from_static(&[])))
     }
+
+    /// Move to the entry returned by `f`, given the map, the current entry, the bounds of the
+    /// iterator and its comparator. Entries out of the bounds invalidate the iterator.
+    fn move_entry(
+        &mut self,
+        f: impl for<'a> FnOnce(
+            &'a SkipMap<MemTableKey, Bytes>,
+            Option<&SkipMapEntry<'a>>,
+            Bound<&dyn SkipMapKey>,
+            Bound<&dyn SkipMapKey>,
+            Option<&dyn Comparator>,
+        ) -> Option<SkipMapEntry<'a>>,
+    ) {
+        self.with_mut(|fields| {
+            let comparator = fields.comparator.as_deref();
+            let (lower, upper) = (fields.lower.as_ref(), fields.upper.as_ref());
+            let lower_key = lower.map(|key| LookupKey { key, comparator });
+            let upper_key = upper.map(|key| LookupKey { key, comparator });
+            let entry = f(
+                fields.map,
+                fields.entry.as_ref(),
+                lower_key.as_ref().map(|key| key as &dyn SkipMapKey),
+                upper_key.as_ref().map(|key| key as &dyn SkipMapKey),
+                comparator,
+            )
+            .filter(|entry| {
+                let comparator = comparator.unwrap_or(&BytewiseComparator);
+                let lower = lower.map(Bytes::as_ref);
+                let upper = upper.map(Bytes::as_ref);
+                comparator.is_above(&entry.key().key, lower)
+                    && comparator.is_below(&entry.key().key, upper)
+            });
+            *fields.item = Self::entry_to_item(entry.as_ref());
+            *fields.entry = entry;
+        });
+    }
 }
 
 impl StorageIterator for MemTableIterator {
@@ -110,8 +270,50 @@ impl StorageIterator for MemTableIterator {
     }
 
     fn next(&mut self) -> Result<()> {
-        let entry = self.with_iter_mut(|iter| MemTableIterator::entry_to_item(iter.next()));
-        self.with_item_mut(|x| *x = entry);
+        self.move_entry(|_, entry, _, _, _| entry.and_then(|entry| entry.next()));
+        Ok(())
+    }
+}
+
+impl BidirectionalIterator for MemTableIterator {
+    fn prev(&mut self) -> Result<()> {
+        self.move_entry(|_, entry, _, _, _| entry.and_then(|entry| entry.prev()));
+        Ok(())
+    }
+
+    fn seek_to_first(&mut self) -> Result<()> {
+        self.move_entry(|map, _, lower, _, _| map.lower_bound(lower));
+        Ok(())
+    }
+
+    fn seek_to_last(&mut self) -> Result<()> {
+        self.move_entry(|map, _, _, upper, _| map.upper_bound(upper));
+        Ok(())
+    }
+
+    fn seek(&mut self, key: &[u8]) -> Result<()> {
+        self.move_entry(|map, _, lower, _, comparator| {
+            let key: &dyn SkipMapKey = &LookupKey { key, comparator };
+            match lower {
+                Bound::Included(bound) | Bound::Excluded(bound) if *bound >= *key => {
+                    map.lower_bound(lower)
+                }
+                _ => map.lower_bound(Bound::Included(key)),
+            }
+        });
+        Ok(())
+    }
+
+    fn seek_for_prev(&mut self, key: &[u8]) -> Result<()> {
+        self.move_entry(|map, _, _, upper, comparator| {
+            let key: &dyn SkipMapKey = &LookupKey { key, comparator };
+            match upper {
+                Bound::Included(bound) | Bound::Excluded(bound) if *bound <= *key => {
+                    map.upper_bound(upper)
+                }
+                _ => map.upper_bound(Bound::Included(key)),
+            }
+        });
         Ok(())
     }
 }
@@ -1,7 +1,8 @@
 use tempfile::tempdir;
 
 use super::MemTable;
-use crate::iterators::StorageIterator;
+use crate::comparator::ReverseBytewiseComparator;
+use crate::iterators::{BidirectionalIterator, StorageIterator};
 use crate::table::{SsTableBuilder, SsTableIterator};
 
 #[test]
@@ -93,3 +94,72 @@ fn test_memtable_iter() {
         assert!(!iter.is_valid());
     }
 }
+
+#[test]
+fn test_memtable_reverse_iter_and_seek() {
+    use std::ops::Bound;
+    let memtable = MemTable::create();
+    for i in 1..=5 {
+        memtable.put(
+            format!("key{}", i).as_bytes(),
+            format!("value{}", i).as_bytes(),
+        );
+    }
+
+    let mut iter = memtable.scan(Bound::Excluded(b"key1"), Bound::Included(b"key4"));
+    iter.seek_to_last().unwrap();
+    for i in (2..=4).rev() {
+        assert_eq!(iter.key(), format!("key{}", i).as_bytes());
+        assert_eq!(iter.value(), format!("value{}", i).as_bytes());
+        iter.prev().unwrap();
+    }
+    assert!(!iter.is_valid());
+
+    iter.seek_for_prev(b"key3a").unwrap();
+    assert_eq!(iter.key(), b"key3");
+    iter.next().unwrap();
+    assert_eq!(iter.key(), b"key4");
+    iter.seek_for_prev(b"key9").unwrap();
+    assert_eq!(iter.key(), b"key4");
+    iter.seek_for_prev(b"key1").unwrap();
+    assert!(!iter.is_valid());
+    iter.seek_to_first().unwrap();
+    assert_eq!(iter.key(), b"key2");
+
+    iter.seek(b"key3a").unwrap();
+    assert_eq!(iter.key(), b"key4");
+    iter.seek(b"key0").unwrap();
+    assert_eq!(iter.key(), b"key2");
+    iter.seek(b"key5").unwrap();
+    assert!(!iter.is_valid());
+}
+
+#[test]
+fn test_memtable_comparator() {
+    use std::ops::Bound;
+    use std::sync::Arc;
+
+    let memtable = MemTable::create_with_comparator(Arc::new(ReverseBytewiseComparator));
+    for i in 1..=5 {
+        memtable.put(
+            format!("key{}", i).as_bytes(),
+            format!("value{}", i).as_bytes(),
+        );
+    }
+    assert_eq!(&memtable.get(b"key3").unwrap()[..], b"value3");
+    assert!(memtable.get(b"key6").is_none());
+
+    // Keys come in the order of the comparator, so do the bounds.
+    let mut iter = memtable.scan(Bound::Included(b"key4"), Bound::Excluded(b"key1"));
+    for i in (2..=4).rev() {
+        assert_eq!(iter.key(), format!("key{}", i).as_bytes());
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+    iter.seek(b"key3a").unwrap();
+    assert_eq!(iter.key(), b"key3");
+    iter.seek_for_prev(b"key2a").unwrap();
+    assert_eq!(iter.key(), b"key3");
+    iter.seek_for_prev(b"key0").unwrap();
+    assert_eq!(iter.key(), b"key2");
+}
@@ -0,0 +1,184 @@
+use std::fmt;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::value::{Record, ValueKind};
+
+/// Combines merge operands written by `LsmStorage::merge` with the value of a key. Operators are
+/// registered through `LsmStorageOptions::merge_operator` and applied lazily by `get`, `scan` and
+/// compaction, so they must be deterministic.
+pub trait MergeOperator: Send + Sync {
+    /// The name of the operator.
+    fn name(&self) -> &str;
+
+    /// Apply `operands`, oldest first, on top of `existing`, the value of the key before the first
+    /// operand was written, or `None` if the key did not exist.
+    fn full_merge(
+        &self,
+        key: &[u8],
+        existing: Option<&[u8]>,
+        operands: &[&[u8]],
+    ) -> Result<Vec<u8>>;
+}
+
+impl fmt::Debug for dyn MergeOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn decode_u64(value: &[u8]) -> Result<u64> {
+    Ok(u64::from_be_bytes(value.try_into().map_err(|_| {
+        anyhow!("expect a 8-byte u64 operand, got {} bytes", value.len())
+    })?))
+}
+
+/// Adds big-endian u64 operands to a big-endian u64 counter, wrapping on overflow. A missing
+/// counter starts from 0.
+pub struct U64AddOperator;
+
+impl MergeOperator for U64AddOperator {
+    fn name(&self) -> &str {
+        "u64_add"
+    }
+
+    fn full_merge(
+        &self,
+        _key: &[u8],
+        existing: Option<&[u8]>,
+        operands: &[&[u8]],
+    ) -> Result<Vec<u8>> {
+        let mut sum = existing.map(decode_u64).transpose()?.unwrap_or(0);
+        for operand in operands {
+            sum = sum.wrapping_add(decode_u64(operand)?);
+        }
+        Ok(sum.to_be_bytes().to_vec())
+    }
+}
+
+/// Keeps the largest of the big-endian u64 value and operands.
+pub struct U64MaxOperator;
+
+impl MergeOperator for U64MaxOperator {
+    fn name(&self) -> &str {
+        "u64_max"
+    }
+
+    fn full_merge(
+        &self,
+        _key: &[u8],
+        existing: Option<&[u8]>,
+        operands: &[&[u8]],
+    ) -> Result<Vec<u8>> {
+        let mut max = existing.map(decode_u64).transpose()?.unwrap_or(0);
+        for operand in operands {
+            max = max.max(decode_u64(operand)?);
+        }
+        Ok(max.to_be_bytes().to_vec())
+    }
+}
+
+/// Appends operands to the value, putting `separator` between consecutive pieces.
+#[derive(Default)]
+pub struct BytesAppendOperator {
+    pub separator: Vec<u8>,
+}
+
+impl MergeOperator for BytesAppendOperator {
+    fn name(&self) -> &str {
+        "bytes_append"
+    }
+
+    fn full_merge(
+        &self,
+        _key: &[u8],
+        existing: Option<&[u8]>,
+        operands: &[&[u8]],
+    ) -> Result<Vec<u8>> {
+        let mut pieces = existing.into_iter().chain(operands.iter().copied());
+        let mut value = pieces.next().unwrap_or_default().to_vec();
+        for piece in pieces {
+            value.extend_from_slice(&self.separator);
+            value.extend_from_slice(piece);
+        }
+        Ok(value)
+    }
+}
+
+/// Collects the records of a key, newest first, until the value the merge operands apply to is
+/// found.
+#[derive(Default)]
+pub(crate) struct MergeOperands {
+    /// The operands of each merge record seen so far, newest record first.
+    operands: Vec<Vec<Vec<u8>>>,
+    /// The value found below the operands, `None` if the key was deleted or never written.
+    base: Option<Vec<u8>>,
+    /// The expiry of the value found below the operands.
+    expire_at: Option<u64>,
+    resolved: bool,
+}
+
+impl MergeOperands {
+    /// Add the next older record of the key. Returns false once older records no longer matter.
+    pub fn push(&mut self, record: &Record) -> Result<bool> {
+        debug_assert!(!self.resolved, "record pushed after the key is resolved");
+        match record.kind {
+            ValueKind::Put => {
+                self.base = Some(record.value.to_vec());
+                self.expire_at = record.expire_at;
+                self.resolved = true;
+            }
+            ValueKind::Delete => self.resolved = true,
+            ValueKind::Merge => self.operands.push(
+                record
+                    .operands()?
+                    .into_iter()
+                    .map(|operand| operand.to_vec())
+                    .collect(),
+            ),
+        }
+        Ok(!self.resolved)
+    }
+
+    /// Check if a put or a deletion has been found below the operands.
+    pub fn is_resolved(&self) -> bool {
+        self.resolved
+    }
+
+    /// Get the expiry of the value found below the operands, which the value they fold into
+    /// keeps.
+    pub fn expire_at(&self) -> Option<u64> {
+        self.expire_at
+    }
+
+    /// Get the operands, oldest first.
+    pub fn operands(&self) -> Vec<&[u8]> {
+        self.operands
+            .iter()
+            .rev()
+            .flatten()
+            .map(|operand| operand.as_slice())
+            .collect()
+    }
+
+    /// Apply the operands on top of the value found, returning `None` if the key does not exist.
+    /// Records not seen are treated as absent.
+    pub fn finish(
+        self,
+        key: &[u8],
+        operator: Option<&dyn MergeOperator>,
+    ) -> Result<Option<Vec<u8>>> {
+        if self.operands.is_empty() {
+            return Ok(self.base);
+        }
+        let Some(operator) = operator else {
+            bail!("found merge operands but no merge operator is configured");
+        };
+        operator
+            .full_merge(key, self.base.as_deref(), &self.operands())
+            .map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests;
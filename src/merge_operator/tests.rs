@@ -0,0 +1,84 @@
+use super::*;
+
+#[test]
+fn test_builtin_operators() {
+    let one = 1u64.to_be_bytes();
+    let five = 5u64.to_be_bytes();
+    assert_eq!(
+        U64AddOperator
+            .full_merge(b"k", Some(&five), &[&one, &one])
+            .unwrap(),
+        7u64.to_be_bytes()
+    );
+    assert_eq!(
+        U64AddOperator.full_merge(b"k", None, &[&one]).unwrap(),
+        1u64.to_be_bytes()
+    );
+    assert!(U64AddOperator
+        .full_merge(b"k", Some(b"x"), &[&one])
+        .is_err());
+    assert_eq!(
+        U64MaxOperator
+            .full_merge(b"k", Some(&one), &[&five, &one])
+            .unwrap(),
+        5u64.to_be_bytes()
+    );
+    let append = BytesAppendOperator {
+        separator: b",".to_vec(),
+    };
+    assert_eq!(
+        append.full_merge(b"k", Some(b"a"), &[b"b", b"c"]).unwrap(),
+        b"a,b,c"
+    );
+    assert_eq!(append.full_merge(b"k", None, &[b"b"]).unwrap(), b"b");
+}
+
+#[test]
+fn test_merge_operands() {
+    let newer = Record::encode_operands(&[b"c", b"d"]);
+    let older = Record::encode_operands(&[b"b"]);
+    let mut operands = MergeOperands::default();
+    assert!(operands
+        .push(&Record {
+            seq: 3,
+            kind: ValueKind::Merge,
+            expire_at: None,
+            value: &newer,
+        })
+        .unwrap());
+    assert!(operands
+        .push(&Record {
+            seq: 2,
+            kind: ValueKind::Merge,
+            expire_at: None,
+            value: &older,
+        })
+        .unwrap());
+    assert!(!operands.is_resolved());
+    assert!(!operands
+        .push(&Record {
+            seq: 1,
+            kind: ValueKind::Put,
+            expire_at: Some(42),
+            value: b"a",
+        })
+        .unwrap());
+    assert!(operands.is_resolved());
+    assert_eq!(operands.expire_at(), Some(42));
+    assert_eq!(operands.operands(), vec![&b"b"[..], b"c", b"d"]);
+    let value = operands
+        .finish(b"k", Some(&BytesAppendOperator::default()))
+        .unwrap();
+    assert_eq!(value.unwrap(), b"abcd");
+
+    let mut operands = MergeOperands::default();
+    operands
+        .push(&Record {
+            seq: 1,
+            kind: ValueKind::Merge,
+            expire_at: None,
+            value: &older,
+        })
+        .unwrap();
+    assert!(operands.finish(b"k", None).is_err());
+}
@@ -0,0 +1,83 @@
+use std::fmt;
+
+/// Extracts the prefix of keys, such as the entity a key belongs to. Configured through
+/// `LsmStorageOptions::prefix_extractor`, each SST then gets a filter of the prefixes of its
+/// keys, which `LsmStorage::scan_prefix` checks to skip the SSTs without the prefix.
+///
+/// The filter of an SST is only used if it was built with an extractor of the same name.
+pub trait PrefixExtractor: Send + Sync {
+    /// The name identifying the extraction. Extractors returning different prefixes must have
+    /// different names.
+    fn name(&self) -> &str;
+
+    /// Get the prefix of `key`, `None` if the key has none. It must be a prefix of the key.
+    fn prefix<'a>(&self, key: &'a [u8]) -> Option<&'a [u8]>;
+
+    /// Check if `prefix` is a whole prefix, that is the prefix of the keys starting with it.
+    fn is_prefix(&self, prefix: &[u8]) -> bool {
+        self.prefix(prefix) == Some(prefix)
+    }
+}
+
+impl fmt::Debug for dyn PrefixExtractor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Takes the first `len` bytes of keys. Shorter keys have no prefix.
+#[derive(Clone, Debug)]
+pub struct FixedPrefixExtractor {
+    len: usize,
+    name: String,
+}
+
+impl FixedPrefixExtractor {
+    pub fn new(len: usize) -> Self {
+        Self {
+            len,
+            name: format!("minilsm.FixedPrefix.{}", len),
+        }
+    }
+}
+
+impl PrefixExtractor for FixedPrefixExtractor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn prefix<'a>(&self, key: &'a [u8]) -> Option<&'a [u8]> {
+        key.get(..self.len)
+    }
+}
+
+/// Takes the bytes of keys up to and including the first `delimiter`, as in `user42:` for
+/// `user42:orders`. Keys without the delimiter have no prefix.
+#[derive(Clone, Debug)]
+pub struct DelimiterPrefixExtractor {
+    delimiter: u8,
+    name: String,
+}
+
+impl DelimiterPrefixExtractor {
+    pub fn new(delimiter: u8) -> Self {
+        Self {
+            delimiter,
+            name: format!("minilsm.DelimiterPrefix.{}", delimiter),
+        }
+    }
+}
+
+impl PrefixExtractor for DelimiterPrefixExtractor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn prefix<'a>(&self, key: &'a [u8]) -> Option<&'a [u8]> {
+        let end = key.iter().position(|byte| *byte == self.delimiter)?;
+        Some(&key[..=end])
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,17 @@
+use super::*;
+
+#[test]
+fn test_prefix_extractors() {
+    let fixed = FixedPrefixExtractor::new(4);
+    assert_eq!(fixed.prefix(b"user42"), Some(&b"user"[..]));
+    assert_eq!(fixed.prefix(b"use"), None);
+    assert!(fixed.is_prefix(b"user"));
+    assert!(!fixed.is_prefix(b"user4"));
+
+    let delimited = DelimiterPrefixExtractor::new(b':');
+    assert_eq!(delimited.prefix(b"user42:orders:1"), Some(&b"user42:"[..]));
+    assert_eq!(delimited.prefix(b"user42"), None);
+    assert!(delimited.is_prefix(b"user42:"));
+    assert!(!delimited.is_prefix(b"user42:orders:"));
+    assert_ne!(fixed.name(), FixedPrefixExtractor::new(5).name());
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use bytes::{Buf, BufMut, Bytes};
+
+use crate::comparator::{BytewiseComparator, Comparator};
+use crate::value::{Record, ValueKind};
+
+/// Deletes every key in `[start, end)` written before it, that is with a smaller sequence number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RangeTombstone {
+    pub start: Bytes,
+    pub end: Bytes,
+    pub seq: u64,
+}
+
+impl RangeTombstone {
+    /// Check if `key` is in the deleted range.
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.contains_by(key, &BytewiseComparator)
+    }
+
+    /// Check if `key` is in the deleted range, in the order of `comparator`.
+    pub fn contains_by(&self, key: &[u8], comparator: &dyn Comparator) -> bool {
+        comparator.compare(&self.start, key).is_le() && comparator.compare(key, &self.end).is_lt()
+    }
+
+    /// Encode range tombstones to a buffer. Each one is stored as its sequence number followed by
+    /// the length-prefixed (u32) start and end keys.
+    pub fn encode_range_tombstones(tombstones: &[RangeTombstone], buf: &mut Vec<u8>) {
+        for tombstone in tombstones {
+            buf.put_u64(tombstone.seq);
+            buf.put_u32(tombstone.start.len() as u32);
+            buf.put_slice(&tombstone.start);
+            buf.put_u32(tombstone.end.len() as u32);
+            buf.put_slice(&tombstone.end);
+        }
+    }
+
+    /// Decode range tombstones from a buffer.
+    pub fn decode_range_tombstones(mut buf: impl Buf) -> Vec<RangeTombstone> {
+        let mut tombstones = Vec::new();
+        while buf.has_remaining() {
+            let seq = buf.get_u64();
+            let start_len = buf.get_u32() as usize;
+            let start = buf.copy_to_bytes(start_len);
+            let end_len = buf.get_u32() as usize;
+            let end = buf.copy_to_bytes(end_len);
+            tombstones.push(RangeTombstone { start, end, seq });
+        }
+        tombstones
+    }
+}
+
+/// The range tombstones visible to a read, split into sorted, non-overlapping fragments. Each
+/// fragment carries the largest sequence number of the tombstones covering it, so checking a key
+/// is a binary search.
+#[derive(Clone, Debug)]
+pub struct RangeTombstoneList {
+    fragments: Vec<RangeTombstone>,
+    comparator: Arc<dyn Comparator>,
+}
+
+impl Default for RangeTombstoneList {
+    fn default() -> Self {
+        Self::new([])
+    }
+}
+
+impl RangeTombstoneList {
+    pub fn new<'a>(tombstones: impl IntoIterator<Item = &'a RangeTombstone>) -> Self {
+        Self::new_with_comparator(tombstones, Arc::new(BytewiseComparator))
+    }
+
+    /// Fragment tombstones over keys ordered by `comparator`.
+    pub fn new_with_comparator<'a>(
+        tombstones: impl IntoIterator<Item = &'a RangeTombstone>,
+        comparator: Arc<dyn Comparator>,
+    ) -> Self {
+        // Sweep over the boundaries of all tombstones, keeping the sequence numbers of the ones
+        // covering the current position.
+        let mut events: Vec<(Bytes, bool, u64)> = Vec::new();
+        for tombstone in tombstones {
+            if comparator.compare(&tombstone.start, &tombstone.end).is_lt() {
+                events.push((tombstone.start.clone(), true, tombstone.seq));
+                events.push((tombstone.end.clone(), false, tombstone.seq));
+            }
+        }
+        events.sort_by(|a, b| comparator.compare(&a.0, &b.0));
+
+        let mut fragments = Vec::new();
+        let mut active: BTreeMap<u64, usize> = BTreeMap::new();
+        let mut idx = 0;
+        while idx < events.len() {
+            let position = events[idx].0.clone();
+            while idx < events.len() && events[idx].0 == position {
+                let (_, is_start, seq) = &events[idx];
+                if *is_start {
+                    *active.entry(*seq).or_default() += 1;
+                } else if let Some(count) = active.get_mut(seq) {
+                    *count -= 1;
+                    if *count == 0 {
+                        active.remove(seq);
+                    }
+                }
+                idx += 1;
+            }
+            if let (Some((&seq, _)), Some(next)) = (active.last_key_value(), events.get(idx)) {
+                fragments.push(RangeTombstone {
+                    start: position,
+                    end: next.0.clone(),
+                    seq,
+                });
+            }
+        }
+        Self {
+            fragments,
+            comparator,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fragments.is_empty()
+    }
+
+    /// Get the largest sequence number of the tombstones covering `key`.
+    pub fn max_seq_covering(&self, key: &[u8]) -> Option<u64> {
+        let idx = self
+            .fragments
+            .partition_point(|fragment| self.comparator.compare(&fragment.start, key).is_le());
+        let fragment = self.fragments.get(idx.checked_sub(1)?)?;
+        if fragment.contains_by(key, &*self.comparator) {
+            Some(fragment.seq)
+        } else {
+            None
+        }
+    }
+
+    /// Check if a record of `key` written with `seq` is deleted by a tombstone.
+    pub fn is_deleted(&self, key: &[u8], seq: u64) -> bool {
+        self.max_seq_covering(key)
+            .is_some_and(|tombstone_seq| seq < tombstone_seq)
+    }
+
+    /// Turn a record into a deletion if it is covered by a newer tombstone.
+    pub fn mask<'a>(&self, key: &[u8], record: Record<'a>) -> Record<'a> {
+        if self.is_deleted(key, record.seq) {
+            Record {
+                seq: record.seq,
+                kind: ValueKind::Delete,
+                expire_at: None,
+                value: &[],
+            }
+        } else {
+            record
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
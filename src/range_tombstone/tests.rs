@@ -0,0 +1,42 @@
+use super::*;
+
+fn tombstone(start: &'static [u8], end: &'static [u8], seq: u64) -> RangeTombstone {
+    RangeTombstone {
+        start: Bytes::from_static(start),
+        end: Bytes::from_static(end),
+        seq,
+    }
+}
+
+#[test]
+fn test_range_tombstone_encode() {
+    let tombstones = vec![tombstone(b"a", b"c", 1), tombstone(b"", b"zz", 7)];
+    let mut buf = Vec::new();
+    RangeTombstone::encode_range_tombstones(&tombstones, &mut buf);
+    assert_eq!(
+        RangeTombstone::decode_range_tombstones(&buf[..]),
+        tombstones
+    );
+}
+
+#[test]
+fn test_range_tombstone_fragments() {
+    let tombstones = [
+        tombstone(b"b", b"f", 5),
+        tombstone(b"d", b"h", 3),
+        tombstone(b"e", b"g", 9),
+        tombstone(b"x", b"x", 100),
+    ];
+    let list = RangeTombstoneList::new(&tombstones);
+    assert_eq!(list.max_seq_covering(b"a"), None);
+    assert_eq!(list.max_seq_covering(b"b"), Some(5));
+    assert_eq!(list.max_seq_covering(b"d"), Some(5));
+    assert_eq!(list.max_seq_covering(b"e"), Some(9));
+    assert_eq!(list.max_seq_covering(b"f"), Some(9));
+    assert_eq!(list.max_seq_covering(b"g"), Some(3));
+    assert_eq!(list.max_seq_covering(b"h"), None);
+    assert_eq!(list.max_seq_covering(b"x"), None);
+    assert!(list.is_deleted(b"c", 4));
+    assert!(!list.is_deleted(b"c", 5));
+    assert!(!list.is_deleted(b"c", 6));
+}
@@ -2,80 +2,147 @@
 #![allow(dead_code)] // TODO(you): remove this lint after implementing this mod
 
 mod builder;
+pub mod checksum;
+mod footer;
+mod index;
 mod iterator;
+mod legacy_bloom;
+mod properties;
+mod range_filter;
 
 // use core::slice::SlicePattern;
+use std::fmt;
+use std::ops::Bound;
 use std::path::Path;
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 pub use builder::SsTableBuilder;
-use bytes::{Buf, Bytes};
+use bytes::{Buf, BufMut, Bytes};
 pub use iterator::SsTableIterator;
-
+pub use properties::TableProperties;
+
+use self::checksum::crc32c;
+use self::footer::Footer;
+pub use self::footer::{SST_FORMAT_VERSION, SST_MAGIC};
+use self::index::{Index, IndexPartition};
+use self::legacy_bloom::LegacyBloom;
+use self::range_filter::RangeFilter;
 use crate::block::Block;
+use crate::comparator::{BytewiseComparator, Comparator};
+use crate::compression::{self, CompressionCodec, NO_COMPRESSION};
+use crate::filter_policy::{self, FilterPolicy};
 use crate::lsm_storage::BlockCache;
+use crate::prefix_extractor::PrefixExtractor;
+use crate::range_tombstone::RangeTombstone;
+
+/// The size of a checksum (u32).
+const CHECKSUM_SIZE: usize = 4;
+
+/// The size of the compression type (u8) and checksum following every data block.
+const BLOCK_TRAILER_SIZE: usize = 1 + CHECKSUM_SIZE;
+
+/// An SST that cannot be read: bytes whose checksum does not match, or a format version this build
+/// does not know. Returned by `SsTable::open`, `read_block` and everything reading blocks as an
+/// `anyhow::Error`, use `downcast_ref` to get it back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SsTableError {
+    /// A data block is corrupted.
+    Corruption { sst_id: usize, block_idx: usize },
+    /// The footer or the meta section, block metas and range tombstones, is corrupted.
+    MetaCorruption { sst_id: usize },
+    /// The SST was written in a newer format version.
+    UnsupportedVersion { sst_id: usize, version: u32 },
+}
+
+impl fmt::Display for SsTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SsTableError::Corruption { sst_id, block_idx } => {
+                write!(f, "block {} of SST {} is corrupted", block_idx, sst_id)
+            }
+            SsTableError::MetaCorruption { sst_id } => {
+                write!(f, "meta section of SST {} is corrupted", sst_id)
+            }
+            SsTableError::UnsupportedVersion { sst_id, version } => write!(
+                f,
+                "SST {} has format version {}, only versions 1 to {} are supported",
+                sst_id, version, SST_FORMAT_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SsTableError {}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct BlockMeta {
     /// Offset of this data block.
     pub offset: usize,
-    /// The first key of the data block, mainly used for index purpose.
-    pub first_key: Bytes,
+    /// The shortest key at or after the last key of the data block and before the first key of
+    /// the next one. The block that may contain a key is the first one whose separator is at or
+    /// after it.
+    pub separator: Bytes,
 }
 
 impl BlockMeta {
-    /// Encode block meta to a buffer.
-    /// You may add extra fields to the buffer,
-    /// in order to help keep track of `first_key` when decoding from the same buffer in the future.
-    pub fn encode_block_meta(
-        block_meta: &[BlockMeta],
-        #[allow(clippy::ptr_arg)] // remove this allow after you finish
-        buf: &mut Vec<u8>,
-    ) {
-        let meta_offset = buf.len();
+    /// Encode block meta to a buffer, in the current format: the offset (u64), then the length
+    /// (u32) and bytes of the separator, all little-endian.
+    pub fn encode_block_meta(block_meta: &[BlockMeta], buf: &mut Vec<u8>) {
         for meta in block_meta {
-            buf.append(&mut meta.offset.to_be_bytes().to_vec());
-            buf.append(&mut meta.first_key.len().to_be_bytes().to_vec());
-            buf.append(&mut meta.first_key.to_vec());
+            buf.put_u64_le(meta.offset as u64);
+            buf.put_u32_le(meta.separator.len() as u32);
+            buf.put_slice(&meta.separator);
         }
-        buf.append(&mut meta_offset.to_be_bytes().to_vec());
     }
 
-    /// Decode block meta from a buffer.
-    pub fn decode_block_meta(mut buf: impl Buf) -> Vec<BlockMeta> {
-        let usize_size = std::mem::size_of::<usize>();
-        let total_len: usize = buf.remaining();
-
-        let mut block_metas = Vec::new();
-
-        let bytes = buf.copy_to_bytes(total_len).to_vec();
-
-        let mut curr_idx = 0;
-
-        while curr_idx < total_len {
-            let offset_bytes: [u8; 8] = bytes[curr_idx..curr_idx + usize_size]
-                .try_into()
-                .expect("len not match");
-            let offset = usize::from_be_bytes(offset_bytes);
-            curr_idx += usize_size;
-
-            let keylen_bytes: [u8; 8] = bytes[curr_idx..curr_idx + usize_size]
-                .try_into()
-                .expect("len not match");
-            let keylen = usize::from_be_bytes(keylen_bytes);
-            curr_idx += usize_size;
-
-            let key_bytes = bytes[curr_idx..curr_idx + keylen].to_vec();
-            curr_idx += keylen;
-
-            block_metas.push(BlockMeta {
-                offset: offset,
-                first_key: Bytes::from(key_bytes),
+    /// Decode block meta written by SST format `version`.
+    pub fn decode_block_meta(buf: impl Buf, version: u32) -> Result<Vec<BlockMeta>> {
+        let entries = Self::decode_entries(buf, version)?;
+        Ok(entries
+            .into_iter()
+            .map(|(offset, mut keys)| BlockMeta {
+                offset,
+                // The last key of a block separates it from the next one.
+                separator: keys.pop().unwrap(),
             })
-        }
+            .collect())
+    }
 
-        block_metas
+    /// Decode the offset and keys of each block meta. Versions 1 and 2 store the first and the
+    /// last key of each block instead of a separator, and version 1 stores the offset and key
+    /// lengths as big-endian u64.
+    fn decode_entries(mut buf: impl Buf, version: u32) -> Result<Vec<(usize, Vec<Bytes>)>> {
+        let len_size = if version == 1 { 8 } else { 4 };
+        let num_keys = if version < 3 { 2 } else { 1 };
+        let mut entries = Vec::new();
+        while buf.has_remaining() {
+            if buf.remaining() < 8 + len_size {
+                bail!("truncated block meta");
+            }
+            let offset = if version == 1 {
+                buf.get_u64()
+            } else {
+                buf.get_u64_le()
+            };
+            let mut keys = Vec::with_capacity(num_keys);
+            for _ in 0..num_keys {
+                if buf.remaining() < len_size {
+                    bail!("truncated block meta");
+                }
+                let len = if version == 1 {
+                    buf.get_u64() as usize
+                } else {
+                    buf.get_u32_le() as usize
+                };
+                if buf.remaining() < len {
+                    bail!("truncated block meta");
+                }
+                keys.push(buf.copy_to_bytes(len));
+            }
+            entries.push((offset as usize, keys));
+        }
+        Ok(entries)
     }
 }
 
@@ -84,7 +151,15 @@ pub struct FileObject(Bytes);
 
 impl FileObject {
     pub fn read(&self, offset: u64, len: u64) -> Result<Vec<u8>> {
-        Ok(self.0[offset as usize..(offset + len) as usize].to_vec())
+        match offset.checked_add(len) {
+            Some(end) if end <= self.size() => Ok(self.0[offset as usize..end as usize].to_vec()),
+            _ => bail!(
+                "read of {} bytes at {} is out of the file of {} bytes",
+                len,
+                offset,
+                self.size()
+            ),
+        }
     }
 
     pub fn size(&self) -> u64 {
@@ -101,18 +176,52 @@ impl FileObject {
     }
 }
 
-/// -------------------------------------------------------------------------------------------------------
-/// |              Data Block             |             Meta Block              |          Extra          |
-/// -------------------------------------------------------------------------------------------------------
-/// | Data Block #1 | ... | Data Block #N | Meta Block #1 | ... | Meta Block #N | Meta Block Offset (u32) |
-/// -------------------------------------------------------------------------------------------------------
+/// ---------------------------------------------------------------------------------------------------------------------------------
+/// |       Data Block       |   Index Block   |       Meta Block       |   Range Tombstones   |  Filter  |  Properties  |   Footer   |
+/// ---------------------------------------------------------------------------------------------------------------------------------
+/// | Data Block #1 | ... #N | #1 | ... | #P  | Meta Block #1 | ... #N | Tombstone #1 | ... #M |          |              |            |
+/// ---------------------------------------------------------------------------------------------------------------------------------
+///
+/// Each data block is stored as compressed by the codec of its compression type (u8, see
+/// `CompressionCodec::id`), followed by the type and the CRC32C (u32) of the stored bytes and the
+/// type. The footer locates the other sections and checks them, see `Footer`. The properties
+/// section is laid out as described by `TableProperties`. The filter section holds the filters of
+/// the SST, each as its kind (u8), then the length (u32, little-endian) and bytes of the filter:
+/// the filter of the keys (`KEY_FILTER`) and the filter of their prefixes (`PREFIX_FILTER`, see
+/// `TableProperties::prefix_extractor`), both laid out as described by `PolicyFilter`, and the
+/// range filter (`RANGE_FILTER`, see `RangeFilter`). SSTs of format version 4 have no key filter,
+/// and their prefix filter is laid out as described by `LegacyBloom`. In SSTs of format version 3,
+/// the filter section is that prefix filter alone, or empty, and in SSTs of format version 1
+/// there is no filter nor properties section.
+///
+/// SSTs with a partitioned index, see `TableProperties::num_index_partitions`, store their block
+/// metas in index blocks and the partitions locating them in place of the meta blocks. Other
+/// SSTs have no index blocks.
 pub struct SsTable {
+    id: usize,
     /// The actual storage unit of SsTable, the format is as above.
     file: FileObject,
-    /// The meta blocks that hold info for data blocks.
-    block_metas: Vec<BlockMeta>,
-    /// The offset that indicates the start point of meta blocks in `file`.
-    block_meta_offset: usize,
+    /// Locates the data blocks.
+    index: Index,
+    /// The end of the data blocks in `file`.
+    data_end: usize,
+    /// The format version the SSTable was written in.
+    format_version: u32,
+    /// The range tombstones written along with the data blocks.
+    range_tombstones: Vec<RangeTombstone>,
+    properties: TableProperties,
+    /// The codecs besides the built-in ones that data blocks may be compressed with: the one the
+    /// SSTable was built with, or the ones it was opened with.
+    codecs: Vec<Arc<dyn CompressionCodec>>,
+    block_cache: Option<Arc<BlockCache>>,
+    /// Orders the keys of the SSTable.
+    comparator: Arc<dyn Comparator>,
+    /// The filter of the keys.
+    key_filter: Option<PolicyFilter>,
+    /// The filter of the prefixes of the keys.
+    prefix_filter: Option<PrefixFilter>,
+    /// Tells whether keys of the SSTable may be in a range.
+    range_filter: Option<RangeFilter>,
 }
 
 impl SsTable {
@@ -121,76 +230,572 @@ impl SsTable {
         Self::open(0, None, file)
     }
 
+    #[cfg(test)]
+    pub(crate) fn file(&self) -> &FileObject {
+        &self.file
+    }
+
     /// Open SSTable from a file.
     pub fn open(id: usize, block_cache: Option<Arc<BlockCache>>, file: FileObject) -> Result<Self> {
-        let usize_size = std::mem::size_of::<usize>();
-        let total_size: u64 = file.size();
-
-        let bmo_vec = file
-            .read(total_size - usize_size as u64, usize_size as u64)
-            .expect("cant read bmo vec from file");
-        let block_meta_offset = Bytes::from(bmo_vec).get_u64();
-
-        let block_metas_bytes = file
-            .read(
-                block_meta_offset,
-                total_size - block_meta_offset - (usize_size as u64),
+        Self::open_with_comparator(id, block_cache, file, Arc::new(BytewiseComparator))
+    }
+
+    /// Open SSTable from a file whose keys are ordered by `comparator`. Fails if the SSTable was
+    /// written with another comparator.
+    pub fn open_with_comparator(
+        id: usize,
+        block_cache: Option<Arc<BlockCache>>,
+        file: FileObject,
+        comparator: Arc<dyn Comparator>,
+    ) -> Result<Self> {
+        Self::open_with_codecs(id, block_cache, file, comparator, Vec::new())
+    }
+
+    /// Open SSTable from a file like `open_with_comparator`, reading the data blocks compressed
+    /// by one of `codecs` in addition to the ones compressed by a built-in codec.
+    pub fn open_with_codecs(
+        id: usize,
+        block_cache: Option<Arc<BlockCache>>,
+        file: FileObject,
+        comparator: Arc<dyn Comparator>,
+        codecs: Vec<Arc<dyn CompressionCodec>>,
+    ) -> Result<Self> {
+        let corruption = SsTableError::MetaCorruption { sst_id: id };
+        let footer = Footer::decode(id, &file)?;
+        let meta = file.read(
+            footer.block_meta_offset,
+            footer.offset - footer.block_meta_offset,
+        )?;
+        if crc32c(&meta) != footer.meta_checksum {
+            return Err(corruption.into());
+        }
+        let section = |start: u64, end: u64| {
+            &meta[(start - footer.block_meta_offset) as usize
+                ..(end - footer.block_meta_offset) as usize]
+        };
+        let index_section = section(footer.block_meta_offset, footer.range_tombstone_offset);
+        let range_tombstones = RangeTombstone::decode_range_tombstones(section(
+            footer.range_tombstone_offset,
+            footer.filter_offset,
+        ));
+        let mut properties = if footer.version == 1 {
+            TableProperties::default()
+        } else {
+            TableProperties::decode(section(footer.properties_offset, footer.offset))
+                .map_err(|_| corruption)?
+        };
+
+        let (index, data_end) = if properties.num_index_partitions > 0 {
+            let partitions = IndexPartition::decode_partitions(index_section, footer.version)
+                .map_err(|_| corruption)?;
+            let Some(first) = partitions.first() else {
+                return Err(corruption.into());
+            };
+            let data_end = first.offset;
+            let num_blocks = properties.num_data_blocks as usize;
+            (
+                Index::Partitioned {
+                    partitions,
+                    num_blocks,
+                },
+                data_end,
             )
-            .expect("cant read block_metas_bytes from file");
-        let block_metas = BlockMeta::decode_block_meta(Bytes::from(block_metas_bytes));
+        } else {
+            let entries =
+                BlockMeta::decode_entries(index_section, footer.version).map_err(|_| corruption)?;
+            // Even tables without key-value pairs have a block.
+            if entries.is_empty() {
+                return Err(corruption.into());
+            }
+            if footer.version == 1 {
+                // Only the key range and the number of blocks are known without scanning the
+                // table.
+                properties = TableProperties {
+                    num_range_tombstones: range_tombstones.len() as u64,
+                    num_data_blocks: entries.len() as u64,
+                    first_key: entries[0].1[0].clone(),
+                    last_key: entries[entries.len() - 1].1[1].clone(),
+                    ..Default::default()
+                };
+            }
+            let block_metas = entries
+                .into_iter()
+                .map(|(offset, mut keys)| BlockMeta {
+                    offset,
+                    separator: keys.pop().unwrap(),
+                })
+                .collect();
+            (Index::Full(block_metas), footer.block_meta_offset)
+        };
+        let mut key_filter = None;
+        let mut prefix_filter = None;
+        let mut range_filter = None;
+        let filter_section = section(footer.filter_offset, footer.properties_offset);
+        let filters = match footer.version {
+            1 | 2 => Vec::new(),
+            3 if filter_section.is_empty() => Vec::new(),
+            3 => vec![(PREFIX_FILTER, filter_section)],
+            _ => decode_filters(filter_section).map_err(|_| corruption)?,
+        };
+        for (kind, filter) in filters {
+            match kind {
+                KEY_FILTER if footer.version >= 5 => {
+                    key_filter = PolicyFilter::decode(filter).map_err(|_| corruption)?;
+                }
+                PREFIX_FILTER if properties.prefix_extractor.is_empty() => {}
+                PREFIX_FILTER if footer.version < 5 => {
+                    let filter = LegacyBloom::decode(filter).map_err(|_| corruption)?;
+                    prefix_filter = Some(PrefixFilter::Legacy(filter));
+                }
+                PREFIX_FILTER => {
+                    prefix_filter = PolicyFilter::decode(filter)
+                        .map_err(|_| corruption)?
+                        .map(PrefixFilter::Policy);
+                }
+                RANGE_FILTER => {
+                    range_filter = Some(RangeFilter::decode(filter).map_err(|_| corruption)?);
+                }
+                // Filters of other kinds, like those of policies which are not built-in, are only
+                // an optimization, they can be left out.
+                _ => {}
+            }
+        }
+        // SSTs which did not record their comparator were written with the bytewise one.
+        let written_with = match properties.comparator.as_str() {
+            "" => BytewiseComparator.name(),
+            name => name,
+        };
+        if written_with != comparator.name() {
+            bail!(
+                "SST {} was written with comparator {}, not {}",
+                id,
+                written_with,
+                comparator.name()
+            );
+        }
         Ok(Self {
+            id,
             file,
-            block_metas,
-            block_meta_offset: block_meta_offset as usize,
+            index,
+            data_end: data_end as usize,
+            format_version: footer.version,
+            range_tombstones,
+            properties,
+            codecs,
+            block_cache,
+            comparator,
+            key_filter,
+            prefix_filter,
+            range_filter,
         })
     }
 
-    /// Read a block from the disk.
+    /// Read a block from the disk, checking it against its checksum and decompressing it.
     pub fn read_block(&self, block_idx: usize) -> Result<Arc<Block>> {
-        let start_offset: u64 = self.block_metas[block_idx].offset as u64;
+        let (start_offset, end_offset) = self.block_offsets(block_idx)?;
+        self.read_block_at(block_idx, start_offset as u64, end_offset as u64)
+    }
 
-        let mut end_offset: u64 = 0u64;
-        if block_idx >= self.block_metas.len() - 1 {
-            end_offset = self.block_meta_offset as u64;
-        } else {
-            end_offset = self.block_metas[block_idx + 1].offset as u64;
-        }
-        let block: Block = Block::decode(
-            self.file
-                .read(start_offset, end_offset - start_offset)
-                .expect("cant read a block from file")
-                .as_slice(),
-        );
+    /// Read the data block `block_idx`, stored from `start_offset` to `end_offset`.
+    fn read_block_at(
+        &self,
+        block_idx: usize,
+        start_offset: u64,
+        end_offset: u64,
+    ) -> Result<Arc<Block>> {
+        let corruption = SsTableError::Corruption {
+            sst_id: self.id,
+            block_idx,
+        };
+        let len = end_offset.checked_sub(start_offset).ok_or(corruption)?;
+        let (data, kind) = self.read_stored_block(start_offset, len, corruption)?;
+        if kind == NO_COMPRESSION {
+            return Ok(Arc::new(Block::decode(&data).map_err(|_| corruption)?));
+        }
+        let codec = self
+            .codecs
+            .iter()
+            .find(|codec| codec.id() == kind)
+            .cloned()
+            .or_else(|| compression::builtin_codec(kind))
+            .ok_or_else(|| {
+                anyhow!(
+                    "unknown compression type {} in block {} of SST {}",
+                    kind,
+                    block_idx,
+                    self.id
+                )
+            })?;
+        let block = Block::decode(&codec.decompress(&data)?).map_err(|_| corruption)?;
         Ok(Arc::new(block))
     }
 
-    /// Read a block from disk, with block cache. (Day 4)
+    /// Read the block stored at `offset` with its trailer, returning its stored bytes and
+    /// compression type.
+    fn read_stored_block(
+        &self,
+        offset: u64,
+        len: u64,
+        corruption: SsTableError,
+    ) -> Result<(Vec<u8>, u8)> {
+        if len < BLOCK_TRAILER_SIZE as u64 {
+            return Err(corruption.into());
+        }
+        let mut data = self.file.read(offset, len)?;
+        let (stored, mut checksum) = data.split_at(data.len() - CHECKSUM_SIZE);
+        if crc32c(stored) != checksum.get_u32() {
+            return Err(corruption.into());
+        }
+        data.truncate(data.len() - CHECKSUM_SIZE);
+        let kind = data.pop().unwrap();
+        Ok((data, kind))
+    }
+
+    /// Read a block from disk, with block cache.
     pub fn read_block_cached(&self, block_idx: usize) -> Result<Arc<Block>> {
-        unimplemented!()
+        let Some(block_cache) = &self.block_cache else {
+            return self.read_block(block_idx);
+        };
+        let (start_offset, end_offset) = self.block_offsets(block_idx)?;
+        Self::cached(block_cache, (self.id, start_offset), || {
+            self.read_block_at(block_idx, start_offset as u64, end_offset as u64)
+        })
     }
 
-    /// Find the block that may contain `key`.
-    /// Note: You may want to make use of the `first_key` stored in `BlockMeta`.
-    /// You may also assume the key-value pairs stored in each consecutive block are sorted.
-    pub fn find_block_idx(&self, key: &[u8]) -> usize {
-        for idx in 0..self.block_metas.len() {
-            let curr_key = &self.block_metas[idx].first_key;
-            if self.compare_bytes(curr_key, key) {
-                return idx;
+    /// Get a block from `block_cache`, reading it with `read` on a miss. Blocks are cached by the
+    /// ID of their SST and their offset.
+    fn cached(
+        block_cache: &BlockCache,
+        key: (usize, usize),
+        read: impl FnOnce() -> Result<Arc<Block>>,
+    ) -> Result<Arc<Block>> {
+        if let Some(block) = block_cache.get(&key) {
+            return Ok(block);
+        }
+        let block = read()?;
+        block_cache.insert(key, block.clone());
+        Ok(block)
+    }
+
+    /// Read the index block of partition `idx`, through the block cache.
+    fn read_index_block(&self, partitions: &[IndexPartition], idx: usize) -> Result<Arc<Block>> {
+        let partition = &partitions[idx];
+        let read = || {
+            let corruption = SsTableError::MetaCorruption { sst_id: self.id };
+            let (data, _) = self.read_stored_block(partition.offset, partition.len, corruption)?;
+            Ok(Arc::new(Block::decode(&data).map_err(|_| corruption)?))
+        };
+        match &self.block_cache {
+            Some(block_cache) => {
+                Self::cached(block_cache, (self.id, partition.offset as usize), read)
+            }
+            None => read(),
+        }
+    }
+
+    /// Get the meta of the data block `block_idx`.
+    pub fn block_meta(&self, block_idx: usize) -> Result<BlockMeta> {
+        match &self.index {
+            Index::Full(block_metas) => Ok(block_metas[block_idx].clone()),
+            Index::Partitioned { partitions, .. } => {
+                let idx = partitions
+                    .partition_point(|p| p.first_block <= block_idx)
+                    .saturating_sub(1);
+                let block = self.read_index_block(partitions, idx)?;
+                index::block_meta_at(block, block_idx - partitions[idx].first_block)
+                    .ok_or_else(|| SsTableError::MetaCorruption { sst_id: self.id }.into())
             }
         }
-        usize::MAX
+    }
+
+    /// Get the offsets the data block `block_idx` starts and ends at, its trailer included. With a
+    /// partitioned index, both are read from the same index block unless the next data block
+    /// starts the next partition.
+    fn block_offsets(&self, block_idx: usize) -> Result<(usize, usize)> {
+        let next = |offset: Option<usize>| -> Result<usize> {
+            match offset {
+                Some(offset) => Ok(offset),
+                None if block_idx + 1 < self.num_of_blocks() => {
+                    Ok(self.block_meta(block_idx + 1)?.offset)
+                }
+                None => Ok(self.data_end),
+            }
+        };
+        match &self.index {
+            Index::Full(block_metas) => Ok((
+                block_metas[block_idx].offset,
+                next(block_metas.get(block_idx + 1).map(|meta| meta.offset))?,
+            )),
+            Index::Partitioned { partitions, .. } => {
+                let idx = partitions
+                    .partition_point(|p| p.first_block <= block_idx)
+                    .saturating_sub(1);
+                let block = self.read_index_block(partitions, idx)?;
+                let (offset, next_offset) =
+                    index::block_offsets_at(block, block_idx - partitions[idx].first_block)
+                        .ok_or(SsTableError::MetaCorruption { sst_id: self.id })?;
+                Ok((offset, next(next_offset)?))
+            }
+        }
+    }
+
+    /// Get the value stored for `key`, `None` if the SSTable does not hold it. Only the block that
+    /// may hold the key is read, and looked up through its hash index if it has one.
+    pub fn get(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        self.get_with(key, Bytes::copy_from_slice)
+    }
+
+    /// Look up `key` like `get`, passing the value stored for it to `f` in place, without copying
+    /// it out of the block.
+    pub fn get_with<T>(&self, key: &[u8], f: impl FnOnce(&[u8]) -> T) -> Result<Option<T>> {
+        let block_idx = self.find_block_idx(key)?;
+        if block_idx == self.num_of_blocks() {
+            return Ok(None);
+        }
+        let block = self.read_block_cached(block_idx)?;
+        Ok(block.get_by(key, &*self.comparator).map(f))
+    }
+
+    /// Get the values stored for `keys`, which must be in the order of the comparator, like
+    /// `get`. Keys in the same block read it once. A key whose block cannot be read gets the error,
+    /// the other keys are still looked up.
+    pub fn multi_get(&self, keys: &[&[u8]]) -> Vec<Result<Option<Bytes>>> {
+        let mut current: Option<(usize, Arc<Block>)> = None;
+        let mut get = |key: &[u8]| -> Result<Option<Bytes>> {
+            let block_idx = self.find_block_idx(key)?;
+            if block_idx == self.num_of_blocks() {
+                return Ok(None);
+            }
+            let block = match &current {
+                Some((idx, block)) if *idx == block_idx => block,
+                _ => {
+                    &current
+                        .insert((block_idx, self.read_block_cached(block_idx)?))
+                        .1
+                }
+            };
+            Ok(block
+                .get_by(key, &*self.comparator)
+                .map(Bytes::copy_from_slice))
+        };
+        keys.iter().map(|key| get(key)).collect()
+    }
+
+    /// Find the block that may contain `key`: the first block whose separator is at or after
+    /// `key`, `num_of_blocks()` if there is none.
+    pub fn find_block_idx(&self, key: &[u8]) -> Result<usize> {
+        match &self.index {
+            Index::Full(block_metas) => Ok(block_metas
+                .partition_point(|meta| self.comparator.compare(&meta.separator, key).is_lt())),
+            Index::Partitioned {
+                partitions,
+                num_blocks,
+            } => {
+                let idx = partitions
+                    .partition_point(|p| self.comparator.compare(&p.separator, key).is_lt());
+                if idx == partitions.len() {
+                    return Ok(*num_blocks);
+                }
+                let block = self.read_index_block(partitions, idx)?;
+                let pos = index::seek_in_block(block, key, &*self.comparator);
+                Ok(partitions[idx].first_block + pos)
+            }
+        }
+    }
+
+    pub fn sst_id(&self) -> usize {
+        self.id
+    }
+
+    /// Get the first key of the SSTable, empty if it has no key-value pairs.
+    pub fn first_key(&self) -> &Bytes {
+        &self.properties.first_key
+    }
+
+    /// Get the last key of the SSTable, empty if it has no key-value pairs.
+    pub fn last_key(&self) -> &Bytes {
+        &self.properties.last_key
+    }
+
+    /// Get the properties of the SSTable, see `TableProperties`. SSTs of format version 1 did not
+    /// record them, only their key range and number of blocks and range tombstones are set.
+    pub fn properties(&self) -> &TableProperties {
+        &self.properties
+    }
+
+    /// Get the range tombstones stored in the SSTable.
+    pub fn range_tombstones(&self) -> &[RangeTombstone] {
+        &self.range_tombstones
+    }
+
+    /// Get the comparator ordering the keys of the SSTable.
+    pub fn comparator(&self) -> &Arc<dyn Comparator> {
+        &self.comparator
+    }
+
+    /// Check whether the SSTable may hold keys starting with `prefix`. Returns false only if the
+    /// prefix filter of the SSTable was built by `extractor` and rules the prefix out, which
+    /// requires `prefix` to be a prefix `extractor` extracts.
+    pub fn may_contain_prefix(&self, extractor: &dyn PrefixExtractor, prefix: &[u8]) -> bool {
+        match &self.prefix_filter {
+            Some(filter)
+                if self.properties.prefix_extractor == extractor.name()
+                    && extractor.is_prefix(prefix) =>
+            {
+                filter.may_contain(prefix)
+            }
+            _ => true,
+        }
+    }
+
+    /// Get the total size of the key and prefix filters of the SSTable, in bytes.
+    pub fn filter_size(&self) -> usize {
+        let key_filter_size = self
+            .key_filter
+            .as_ref()
+            .map_or(0, |filter| filter.filter.len());
+        let prefix_filter_size = match &self.prefix_filter {
+            Some(PrefixFilter::Policy(filter)) => filter.filter.len(),
+            Some(PrefixFilter::Legacy(filter)) => filter.size(),
+            None => 0,
+        };
+        key_filter_size + prefix_filter_size
+    }
+
+    /// Check whether the SSTable may hold `key`. Returns false only if the key filter of the
+    /// SSTable rules it out.
+    pub fn may_contain_key(&self, key: &[u8]) -> bool {
+        match &self.key_filter {
+            Some(filter) => filter.may_contain(key),
+            None => true,
+        }
+    }
+
+    /// Check whether the SSTable may hold keys in the range. Returns false only if the range filter
+    /// of the SSTable rules the range out, or the range is outside of its key range.
+    pub fn may_overlap(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> bool {
+        if !self.properties.overlaps(lower, upper, &*self.comparator) {
+            return false;
+        }
+        match &self.range_filter {
+            Some(filter) => filter.may_overlap(lower, upper),
+            None => true,
+        }
+    }
+
+    /// Get the format version the SSTable was written in.
+    pub fn format_version(&self) -> u32 {
+        self.format_version
     }
 
     /// Get number of data blocks.
     pub fn num_of_blocks(&self) -> usize {
-        self.block_metas.len()
+        match &self.index {
+            Index::Full(block_metas) => block_metas.len(),
+            Index::Partitioned { num_blocks, .. } => *num_blocks,
+        }
+    }
+}
+
+/// The kind of the filter of the prefixes of the keys in the filter section.
+const PREFIX_FILTER: u8 = 1;
+
+/// The kind of the range filter in the filter section.
+const RANGE_FILTER: u8 = 2;
+
+/// The kind of the filter of the keys in the filter section.
+const KEY_FILTER: u8 = 3;
+
+/// The filter of the prefixes of the keys of an SSTable.
+#[derive(Clone, Debug)]
+enum PrefixFilter {
+    /// Built by the filter policy of the SSTable.
+    Policy(PolicyFilter),
+    /// Written by format versions 3 and 4.
+    Legacy(LegacyBloom),
+}
+
+impl PrefixFilter {
+    fn may_contain(&self, prefix: &[u8]) -> bool {
+        match self {
+            PrefixFilter::Policy(filter) => filter.may_contain(prefix),
+            PrefixFilter::Legacy(filter) => filter.may_contain(prefix),
+        }
     }
+}
+
+/// A filter built by a filter policy, stored as the id (u8) of the policy followed by the filter.
+#[derive(Clone, Debug)]
+struct PolicyFilter {
+    policy: Arc<dyn FilterPolicy>,
+    filter: Bytes,
+}
 
-    fn compare_bytes(&self, left: &[u8], right: &[u8]) -> bool {
-        return String::from_utf8(left.to_vec()).unwrap()
-            >= String::from_utf8(right.to_vec()).unwrap();
+impl PolicyFilter {
+    /// Build a filter of the keys hashed to `hashes` with `policy`.
+    fn build(policy: Arc<dyn FilterPolicy>, hashes: &[u64], bits_per_key: f64) -> Self {
+        let filter = policy.build(hashes, bits_per_key).into();
+        Self { policy, filter }
     }
+
+    fn may_contain(&self, key: &[u8]) -> bool {
+        self.policy
+            .may_contain(&self.filter, filter_policy::hash(key))
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.put_u8(self.policy.id());
+        buf.put_slice(&self.filter);
+    }
+
+    /// Decode a filter, `None` if its policy is not built-in.
+    fn decode(buf: &[u8]) -> Result<Option<Self>> {
+        let Some((&id, filter)) = buf.split_first() else {
+            bail!("empty filter");
+        };
+        Ok(filter_policy::builtin_filter_policy(id).map(|policy| Self {
+            policy,
+            filter: Bytes::copy_from_slice(filter),
+        }))
+    }
+}
+
+/// Append a filter of `kind` encoded by `encode` to the filter section in `buf`.
+fn append_filter(buf: &mut Vec<u8>, kind: u8, encode: impl FnOnce(&mut Vec<u8>)) {
+    buf.put_u8(kind);
+    let len_offset = buf.len();
+    buf.put_u32_le(0);
+    encode(buf);
+    let len = (buf.len() - len_offset - 4) as u32;
+    buf[len_offset..len_offset + 4].copy_from_slice(&len.to_le_bytes());
+}
+
+/// Decode the kind and bytes of each filter of the filter section.
+fn decode_filters(mut buf: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    let mut filters = Vec::new();
+    while buf.has_remaining() {
+        if buf.remaining() < 5 {
+            bail!("truncated filter section");
+        }
+        let kind = buf.get_u8();
+        let len = buf.get_u32_le() as usize;
+        if buf.remaining() < len {
+            bail!("truncated filter section");
+        }
+        filters.push((kind, &buf[..len]));
+        buf.advance(len);
+    }
+    Ok(filters)
+}
+
+/// Append a block stored as `data` with compression type `kind` to `buf`, followed by its type
+/// and checksum.
+fn append_block(buf: &mut Vec<u8>, data: &[u8], kind: u8) {
+    let offset = buf.len();
+    buf.extend_from_slice(data);
+    buf.put_u8(kind);
+    let checksum = crc32c(&buf[offset..]);
+    buf.put_u32(checksum);
 }
 
 #[cfg(test)]
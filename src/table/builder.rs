@@ -1,14 +1,28 @@
 #![allow(unused_variables)] // TODO(you): remove this lint after implementing this mod
 #![allow(dead_code)] // TODO(you): remove this lint after implementing this mod
 
+use std::borrow::Cow;
 use std::sync::Arc;
 use std::{mem, path::Path};
 
 use anyhow::Result;
 use bytes::Bytes;
 
-use super::{BlockMeta, SsTable};
-use crate::block::BlockIterator;
+use super::footer::Footer;
+use super::index::{Index, IndexPartition};
+use super::range_filter::RangeFilterBuilder;
+use super::{
+    append_block, append_filter, BlockMeta, PolicyFilter, PrefixFilter, SsTable, TableProperties,
+    KEY_FILTER, PREFIX_FILTER, RANGE_FILTER, SST_FORMAT_VERSION,
+};
+use crate::comparator::{BytewiseComparator, Comparator};
+use crate::compression::{self, CompressionCodec};
+use crate::filter_policy::{self, FilterPolicy};
+use crate::key;
+use crate::prefix_extractor::PrefixExtractor;
+use crate::range_tombstone::RangeTombstone;
+use crate::table_properties_collector::TablePropertiesCollector;
+use crate::value::{self, Record};
 use crate::{block::BlockBuilder, lsm_storage::BlockCache};
 
 /// Builds an SSTable from key-value pairs.
@@ -16,8 +30,37 @@ pub struct SsTableBuilder {
     pub(super) meta: Vec<BlockMeta>,
     // Add other fields you need.
     block_builder: BlockBuilder,
+    /// The last key added.
+    last_key: Vec<u8>,
+    /// The total size of the last keys of the finished blocks.
+    last_keys_size: usize,
+    range_tombstones: Vec<RangeTombstone>,
+    properties: TableProperties,
+    collectors: Vec<Box<dyn TablePropertiesCollector>>,
+    /// Whether keys carry a user timestamp, which collectors are not given.
+    user_timestamp: bool,
+    compression: Option<Arc<dyn CompressionCodec>>,
+    /// The target size of index blocks, `None` for an index that is not partitioned.
+    index_partition_size: Option<usize>,
+    /// Whether data blocks get a hash index.
+    block_hash_index: bool,
+    /// Orders the keys added, and shortens the separators of the index.
+    comparator: Arc<dyn Comparator>,
+    /// Builds the key and prefix filters, `None` for neither.
+    filter_policy: Option<Arc<dyn FilterPolicy>>,
+    /// The number of bits of the key and prefix filters for each key or prefix.
+    bits_per_key: f64,
+    /// Whether the key filter is built.
+    whole_key_filtering: bool,
+    /// Extracts the prefixes put in the prefix filter, `None` for no prefix filter.
+    prefix_extractor: Option<Arc<dyn PrefixExtractor>>,
+    /// The hashes of the keys added.
+    key_hashes: Vec<u64>,
+    /// The hashes of the prefixes of the keys added.
+    prefix_hashes: Vec<u64>,
+    /// Builds the range filter, `None` for no range filter.
+    range_filter: Option<RangeFilterBuilder>,
     block_size: usize,
-    total_size: usize,
     bytes: Vec<u8>,
 }
 
@@ -27,8 +70,24 @@ impl SsTableBuilder {
         Self {
             meta: Vec::new(),
             block_builder: BlockBuilder::new(block_size),
-            block_size: block_size,
-            total_size: 0,
+            last_key: Vec::new(),
+            last_keys_size: 0,
+            range_tombstones: Vec::new(),
+            properties: TableProperties::default(),
+            collectors: Vec::new(),
+            user_timestamp: false,
+            compression: None,
+            index_partition_size: None,
+            block_hash_index: false,
+            comparator: Arc::new(BytewiseComparator),
+            filter_policy: None,
+            bits_per_key: 10.0,
+            whole_key_filtering: true,
+            prefix_extractor: None,
+            key_hashes: Vec::new(),
+            prefix_hashes: Vec::new(),
+            range_filter: None,
+            block_size,
             bytes: Vec::new(),
         }
     }
@@ -36,30 +95,167 @@ impl SsTableBuilder {
     /// Adds a key-value pair to SSTable.
     /// Note: You should split a new block when the current block is full.(`std::mem::replace` may be of help here)
     pub fn add(&mut self, key: &[u8], value: &[u8]) {
+        let mut starts_block = self.block_builder.is_empty();
         if !self.block_builder.add(key, value) {
-            let old_builder: BlockBuilder =
-                mem::replace(&mut self.block_builder, BlockBuilder::new(self.block_size));
-            let built_block = old_builder.build();
-            let bytes = built_block.encode();
-            self.bytes.append(&mut bytes.to_vec());
+            self.finish_block();
+            // An empty block takes any key-value pair.
+            assert!(self.block_builder.add(key, value));
+            starts_block = true;
+        }
+        if starts_block {
+            if let Some(meta) = self.meta.last_mut() {
+                // The previous block is followed by `key`, its separator can be shortened.
+                meta.separator = self.comparator.shortest_separator(&self.last_key, key);
+            }
+        }
+        if self.filter_policy.is_some() {
+            self.add_to_filters(key);
+        }
+        if let Some(range_filter) = &mut self.range_filter {
+            range_filter.add(key);
+        }
+        if self.properties.first_key.is_empty() {
+            self.properties.first_key = Bytes::copy_from_slice(key);
+        }
+        self.last_key.clear();
+        self.last_key.extend_from_slice(key);
+        self.properties.add(key, value);
+        if !self.collectors.is_empty() {
+            self.add_to_collectors(key, value);
+        }
+        // A key-value pair larger than a block gets a block of its own.
+        if self.block_builder.estimated_size() > self.block_size {
+            self.finish_block();
+        }
+    }
+
+    /// Compress data blocks with `codec`, `None` to store them uncompressed.
+    pub fn set_compression(&mut self, codec: Option<Arc<dyn CompressionCodec>>) {
+        self.compression = codec;
+    }
+
+    /// Split the index into index blocks of about `partition_size` bytes, read on demand once the
+    /// SSTable is opened. `None` keeps the whole index in memory.
+    pub fn set_index_partition_size(&mut self, partition_size: Option<usize>) {
+        self.index_partition_size = partition_size;
+    }
+
+    /// Set the comparator the keys are added in the order of, bytewise by default.
+    pub fn set_comparator(&mut self, comparator: Arc<dyn Comparator>) {
+        self.comparator = comparator;
+    }
+
+    /// Build the filters of the keys and of their prefixes with `policy`, with about
+    /// `bits_per_key` bits for each key or prefix, see `SsTable::may_contain_key` and
+    /// `SsTable::may_contain_prefix`. `None` builds neither. Must be called before keys are added.
+    pub fn set_filter_policy(&mut self, policy: Option<Arc<dyn FilterPolicy>>, bits_per_key: f64) {
+        self.filter_policy = policy;
+        self.bits_per_key = bits_per_key;
+    }
+
+    /// Set whether the filter of the keys is built, true by default. Keys which are never looked up
+    /// whole, like those carrying a timestamp, do not need one.
+    pub fn set_whole_key_filtering(&mut self, enabled: bool) {
+        self.whole_key_filtering = enabled;
+    }
+
+    /// Build a filter of the prefixes of the keys extracted by `extractor`, with the filter policy.
+    /// `None` builds no prefix filter. Must be called before keys are added.
+    pub fn set_prefix_extractor(&mut self, extractor: Option<Arc<dyn PrefixExtractor>>) {
+        self.prefix_extractor = extractor;
+    }
+
+    /// Hash `key` and its prefix for the key and prefix filters. Must be called before `last_key`
+    /// is updated.
+    fn add_to_filters(&mut self, key: &[u8]) {
+        // Versions of a key, and keys sharing a prefix, usually follow each other, they are only
+        // added once.
+        let first = self.properties.num_entries == 0;
+        if self.whole_key_filtering && (first || self.last_key != key) {
+            self.key_hashes.push(filter_policy::hash(key));
+        }
+        if let Some(extractor) = &self.prefix_extractor {
+            if let Some(prefix) = extractor.prefix(key) {
+                if first || extractor.prefix(&self.last_key) != Some(prefix) {
+                    self.prefix_hashes.push(filter_policy::hash(prefix));
+                }
+            }
+        }
+    }
+
+    /// Build a range filter of the keys, see `SsTable::may_overlap`, keeping `suffix_len` bytes of
+    /// each key after the prefix telling it apart from the others. More bytes rule out more
+    /// ranges in a larger filter. `None` builds no filter. Only SSTs whose keys are ordered
+    /// bytewise get one. Must be called before keys are added.
+    pub fn set_range_filter(&mut self, suffix_len: Option<usize>) {
+        self.range_filter = suffix_len.map(RangeFilterBuilder::new);
+    }
 
-            let block_iter = BlockIterator::create_and_seek_to_first(Arc::new(built_block));
+    /// Give data blocks a hash index speeding up point lookups, see `Block::get`. Applies to the
+    /// blocks started after the call.
+    pub fn set_block_hash_index(&mut self, enabled: bool) {
+        self.block_hash_index = enabled;
+        if enabled && self.block_builder.is_empty() {
+            self.block_builder.enable_hash_index();
+        }
+    }
 
-            self.meta.push(BlockMeta {
-                offset: self.total_size,
-                first_key: Bytes::copy_from_slice(block_iter.key()),
-            });
+    /// Record the properties computed by `collector` in the SSTable.
+    pub fn add_properties_collector(&mut self, collector: Box<dyn TablePropertiesCollector>) {
+        self.collectors.push(collector);
+    }
 
-            self.total_size += bytes.len();
-            let res = self.block_builder.add(key, value);
-            assert!(res); // Hopefully a single kv pair won't exceed size
+    /// Set whether the keys added carry a user timestamp, false by default. Collectors are given
+    /// the user key without it.
+    pub fn set_user_timestamp(&mut self, enabled: bool) {
+        self.user_timestamp = enabled;
+    }
+
+    /// Pass the user key, kind and user value of an entry to the collectors. Values which are not
+    /// records are not passed.
+    fn add_to_collectors(&mut self, key: &[u8], value: &[u8]) {
+        let Some(record) = Record::try_decode(value) else {
+            return;
+        };
+        let user_key = if self.user_timestamp {
+            Cow::Owned(key::user_key_of(key))
+        } else {
+            Cow::Borrowed(key)
+        };
+        for collector in &mut self.collectors {
+            collector.add(&user_key, record.kind, record.value);
         }
     }
 
+    /// Adds a range tombstone to the range tombstone section of the SSTable.
+    pub fn add_range_tombstone(&mut self, tombstone: RangeTombstone) {
+        self.properties.add_range_tombstone(&tombstone);
+        self.range_tombstones.push(tombstone);
+    }
+
+    fn finish_block(&mut self) {
+        let mut new_builder = BlockBuilder::new(self.block_size);
+        if self.block_hash_index {
+            new_builder.enable_hash_index();
+        }
+        let old_builder: BlockBuilder = mem::replace(&mut self.block_builder, new_builder);
+        let block = old_builder.build().encode();
+        let (kind, bytes) = compression::compress_block(self.compression.as_deref(), &block);
+        let block_offset = self.bytes.len();
+        append_block(&mut self.bytes, &bytes, kind);
+
+        // The separator is shortened once the first key of the next block is known.
+        self.last_keys_size += self.last_key.len();
+        self.meta.push(BlockMeta {
+            offset: block_offset,
+            separator: Bytes::copy_from_slice(&self.last_key),
+        });
+    }
+
     /// Get the estimated size of the SSTable.
     /// Since the data blocks contain much more data than meta blocks, just return the size of data blocks here.
     pub fn estimated_size(&self) -> usize {
-        self.total_size
+        self.bytes.len()
     }
 
     /// Builds the SSTable and writes it to the given path. No need to actually write to disk until
@@ -70,27 +266,108 @@ impl SsTableBuilder {
         block_cache: Option<Arc<BlockCache>>,
         path: impl AsRef<Path>,
     ) -> Result<SsTable> {
-        let built_block = self.block_builder.build();
-        let bytes = built_block.encode();
-        self.bytes.append(&mut bytes.to_vec());
-
-        let block_iter = BlockIterator::create_and_seek_to_first(Arc::new(built_block));
+        // The last block may have been finished by an oversized key-value pair.
+        if !self.block_builder.is_empty() || self.meta.is_empty() {
+            self.finish_block();
+        }
+        let last_meta = self.meta.last_mut().unwrap();
+        last_meta.separator = self.comparator.shortest_successor(&self.last_key);
 
-        self.meta.push(BlockMeta {
-            offset: self.total_size,
-            first_key: Bytes::copy_from_slice(block_iter.key()),
-        });
+        let mut properties = self.properties;
+        properties.num_data_blocks = self.meta.len() as u64;
+        properties.data_size = self.bytes.len() as u64;
+        properties.last_key = self.last_key.into();
+        let separators_size: usize = self.meta.iter().map(|meta| meta.separator.len()).sum();
+        properties.index_size_saved = self.last_keys_size.saturating_sub(separators_size) as u64;
+        properties.creation_time = value::unix_millis();
+        properties.compression = match &self.compression {
+            Some(codec) => codec.name().to_string(),
+            None => "none".to_string(),
+        };
+        properties.comparator = self.comparator.name().to_string();
+        let mut key_filter = None;
+        let mut prefix_filter = None;
+        if let Some(policy) = &self.filter_policy {
+            properties.filter_policy = policy.name().to_string();
+            if self.whole_key_filtering {
+                let filter =
+                    PolicyFilter::build(policy.clone(), &self.key_hashes, self.bits_per_key);
+                key_filter = Some(filter);
+            }
+            if let Some(extractor) = &self.prefix_extractor {
+                properties.prefix_extractor = extractor.name().to_string();
+                let filter =
+                    PolicyFilter::build(policy.clone(), &self.prefix_hashes, self.bits_per_key);
+                prefix_filter = Some(filter);
+            }
+        }
+        let range_filter = self
+            .range_filter
+            .filter(|_| self.comparator.name() == BytewiseComparator.name())
+            .map(RangeFilterBuilder::build);
+        for collector in &mut self.collectors {
+            properties.user_properties.extend(collector.finish());
+        }
 
         let mut buf = self.bytes;
-
-        self.total_size = buf.len();
-        BlockMeta::encode_block_meta(&self.meta, &mut buf);
+        let data_end = buf.len();
+        // Tables without key-value pairs have an empty block, whose keys cannot be indexed.
+        let index = match self.index_partition_size {
+            Some(partition_size) if properties.num_entries > 0 => {
+                let partitions = IndexPartition::build(&self.meta, partition_size, &mut buf);
+                properties.num_index_partitions = partitions.len() as u64;
+                Index::Partitioned {
+                    partitions,
+                    num_blocks: self.meta.len(),
+                }
+            }
+            _ => Index::Full(self.meta),
+        };
+        let block_meta_offset = buf.len();
+        match &index {
+            Index::Full(block_metas) => BlockMeta::encode_block_meta(block_metas, &mut buf),
+            Index::Partitioned { partitions, .. } => {
+                IndexPartition::encode_partitions(partitions, &mut buf)
+            }
+        }
+        let range_tombstone_offset = buf.len();
+        properties.index_size = (range_tombstone_offset - data_end) as u64;
+        RangeTombstone::encode_range_tombstones(&self.range_tombstones, &mut buf);
+        let filter_offset = buf.len();
+        if let Some(filter) = &key_filter {
+            append_filter(&mut buf, KEY_FILTER, |buf| filter.encode(buf));
+        }
+        if let Some(filter) = &prefix_filter {
+            append_filter(&mut buf, PREFIX_FILTER, |buf| filter.encode(buf));
+        }
+        if let Some(filter) = &range_filter {
+            append_filter(&mut buf, RANGE_FILTER, |buf| filter.encode(buf));
+        }
+        let properties_offset = buf.len();
+        properties.encode(&mut buf);
+        Footer::encode(
+            &mut buf,
+            block_meta_offset,
+            range_tombstone_offset,
+            filter_offset,
+            properties_offset,
+        );
         let fo = super::FileObject::create(path.as_ref(), buf);
 
         Ok(SsTable {
+            id,
             file: fo.unwrap(),
-            block_metas: self.meta,
-            block_meta_offset: self.total_size,
+            index,
+            data_end,
+            format_version: SST_FORMAT_VERSION,
+            range_tombstones: self.range_tombstones,
+            properties,
+            codecs: self.compression.into_iter().collect(),
+            block_cache,
+            comparator: self.comparator,
+            key_filter,
+            prefix_filter: prefix_filter.map(PrefixFilter::Policy),
+            range_filter,
         })
     }
 
@@ -0,0 +1,139 @@
+use bytes::{Buf, BufMut};
+
+use super::checksum::crc32c;
+use super::{FileObject, SsTableError, CHECKSUM_SIZE};
+
+/// The last bytes of every SST since format version 2, "MINILSM!" on disk.
+pub const SST_MAGIC: u64 = u64::from_le_bytes(*b"MINILSM!");
+
+/// The format version of SSTs written by `SsTableBuilder`.
+pub const SST_FORMAT_VERSION: u32 = 5;
+
+/// The size of the version 1 footer.
+const FOOTER_V1_SIZE: usize = 24;
+
+/// The size of the current footer.
+const FOOTER_SIZE: usize = 52;
+
+/// Locates the sections of an SST. Every section ends where the next one starts, and the last one
+/// where the footer starts.
+///
+/// Versions 2 to 5 (current), all fields little-endian:
+///
+/// ----------------------------------------------------------------------------------------------
+/// | Meta Offset (u64) | Range Tombstone Offset (u64) | Filter Offset (u64) | Properties Offset  |
+/// ----------------------------------------------------------------------------------------------
+/// | (u64) | Meta Checksum (u32) | Version (u32) | Footer Checksum (u32) | Magic (u64)           |
+/// ----------------------------------------------------------------------------------------------
+///
+/// The meta checksum covers every section after the data blocks, the footer checksum the footer
+/// fields before it.
+///
+/// Version 1, big-endian, without filter or properties sections:
+///
+/// ------------------------------------------------------------------------------------------------
+/// | Range Tombstone Offset (u64) | Meta Offset (u64) | Meta Checksum (u32) | Footer Checksum (u32) |
+/// ------------------------------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Footer {
+    pub version: u32,
+    pub block_meta_offset: u64,
+    pub range_tombstone_offset: u64,
+    pub filter_offset: u64,
+    pub properties_offset: u64,
+    pub meta_checksum: u32,
+    /// Where the footer starts, the end of the last section.
+    pub offset: u64,
+}
+
+impl Footer {
+    /// Append a footer of the current version to `buf`, which ends with the properties section.
+    pub fn encode(
+        buf: &mut Vec<u8>,
+        block_meta_offset: usize,
+        range_tombstone_offset: usize,
+        filter_offset: usize,
+        properties_offset: usize,
+    ) {
+        let meta_checksum = crc32c(&buf[block_meta_offset..]);
+        let footer_offset = buf.len();
+        buf.put_u64_le(block_meta_offset as u64);
+        buf.put_u64_le(range_tombstone_offset as u64);
+        buf.put_u64_le(filter_offset as u64);
+        buf.put_u64_le(properties_offset as u64);
+        buf.put_u32_le(meta_checksum);
+        buf.put_u32_le(SST_FORMAT_VERSION);
+        let footer_checksum = crc32c(&buf[footer_offset..]);
+        buf.put_u32_le(footer_checksum);
+        buf.put_u64_le(SST_MAGIC);
+    }
+
+    /// Read the footer of `file`, in any supported version.
+    pub fn decode(sst_id: usize, file: &FileObject) -> anyhow::Result<Self> {
+        let corruption = SsTableError::MetaCorruption { sst_id };
+        let size = file.size();
+        if size >= FOOTER_SIZE as u64 {
+            let mut magic = &file.read(size - 8, 8)?[..];
+            if magic.get_u64_le() == SST_MAGIC {
+                return Self::decode_v2(sst_id, file);
+            }
+        }
+        // Version 1 SSTs have no magic number.
+        let offset = size.checked_sub(FOOTER_V1_SIZE as u64).ok_or(corruption)?;
+        let footer = file.read(offset, FOOTER_V1_SIZE as u64)?;
+        let (mut fields, mut checksum) = footer.split_at(FOOTER_V1_SIZE - CHECKSUM_SIZE);
+        if crc32c(fields) != checksum.get_u32() {
+            return Err(corruption.into());
+        }
+        let range_tombstone_offset = fields.get_u64();
+        let footer = Footer {
+            version: 1,
+            block_meta_offset: fields.get_u64(),
+            range_tombstone_offset,
+            filter_offset: offset,
+            properties_offset: offset,
+            meta_checksum: fields.get_u32(),
+            offset,
+        };
+        footer.check(sst_id)
+    }
+
+    fn decode_v2(sst_id: usize, file: &FileObject) -> anyhow::Result<Self> {
+        let offset = file.size() - FOOTER_SIZE as u64;
+        let footer = file.read(offset, FOOTER_SIZE as u64)?;
+        let (mut fields, mut checksum) = footer.split_at(40);
+        if crc32c(fields) != checksum.get_u32_le() {
+            return Err(SsTableError::MetaCorruption { sst_id }.into());
+        }
+        // Read the version once checked, so that a corrupted one is not taken for a newer version.
+        let version = (&fields[36..]).get_u32_le();
+        if !(2..=SST_FORMAT_VERSION).contains(&version) {
+            return Err(SsTableError::UnsupportedVersion { sst_id, version }.into());
+        }
+        let footer = Footer {
+            version,
+            block_meta_offset: fields.get_u64_le(),
+            range_tombstone_offset: fields.get_u64_le(),
+            filter_offset: fields.get_u64_le(),
+            properties_offset: fields.get_u64_le(),
+            meta_checksum: fields.get_u32_le(),
+            offset,
+        };
+        footer.check(sst_id)
+    }
+
+    /// Check that the sections are in order.
+    fn check(self, sst_id: usize) -> anyhow::Result<Self> {
+        let offsets = [
+            self.block_meta_offset,
+            self.range_tombstone_offset,
+            self.filter_offset,
+            self.properties_offset,
+            self.offset,
+        ];
+        if offsets.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(SsTableError::MetaCorruption { sst_id }.into());
+        }
+        Ok(self)
+    }
+}
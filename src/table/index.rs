@@ -0,0 +1,158 @@
+use std::mem;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use bytes::{Buf, BufMut, Bytes};
+
+use super::{append_block, BlockMeta};
+use crate::block::{Block, BlockBuilder, BlockIterator};
+use crate::comparator::Comparator;
+use crate::compression::NO_COMPRESSION;
+
+/// How an SSTable locates its data blocks.
+pub(crate) enum Index {
+    /// Every block meta, decoded when the SSTable is opened.
+    Full(Vec<BlockMeta>),
+    /// The block metas are split into index blocks stored after the data blocks, only the
+    /// partitions pointing at them are kept in memory. Index blocks are read on demand through
+    /// the block cache.
+    Partitioned {
+        partitions: Vec<IndexPartition>,
+        num_blocks: usize,
+    },
+}
+
+/// Locates an index block, which holds the metas of consecutive data blocks.
+///
+/// Index blocks are blocks whose entries are keyed by the separator of a data block, and whose
+/// values are its offset (u64, little-endian). Format version 2 keyed them by the last key of the
+/// block, and followed the offset with its first key. They are stored like uncompressed data
+/// blocks, followed by their type and checksum.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct IndexPartition {
+    /// The offset of the index block.
+    pub offset: u64,
+    /// The size of the index block, with its type and checksum.
+    pub len: u64,
+    /// The index of the first data block the index block holds the meta of.
+    pub first_block: usize,
+    /// The separator of its last data block.
+    pub separator: Bytes,
+}
+
+impl IndexPartition {
+    /// Encode the partitions of an index, in the meta section:
+    ///
+    /// | offset (u64) | len (u64) | first_block (u64) | separator_len (u32) | separator |
+    ///
+    /// for each partition, little-endian. Format version 2 stored the first and last key of the
+    /// data blocks, each with its length, in place of the separator.
+    pub fn encode_partitions(partitions: &[IndexPartition], buf: &mut Vec<u8>) {
+        for partition in partitions {
+            buf.put_u64_le(partition.offset);
+            buf.put_u64_le(partition.len);
+            buf.put_u64_le(partition.first_block as u64);
+            buf.put_u32_le(partition.separator.len() as u32);
+            buf.put_slice(&partition.separator);
+        }
+    }
+
+    /// Decode the partitions of an index written by SST format `version`.
+    pub fn decode_partitions(mut buf: &[u8], version: u32) -> Result<Vec<IndexPartition>> {
+        let mut partitions = Vec::new();
+        while buf.has_remaining() {
+            if buf.remaining() < 24 {
+                bail!("truncated index partition");
+            }
+            let offset = buf.get_u64_le();
+            let len = buf.get_u64_le();
+            let first_block = buf.get_u64_le() as usize;
+            if version < 3 {
+                // The first key.
+                get_key(&mut buf)?;
+            }
+            let separator = get_key(&mut buf)?;
+            partitions.push(IndexPartition {
+                offset,
+                len,
+                first_block,
+                separator,
+            });
+        }
+        Ok(partitions)
+    }
+
+    /// Append the index blocks of `metas` to `buf`, each of about `partition_size` bytes, and
+    /// return the partitions locating them.
+    pub fn build(metas: &[BlockMeta], partition_size: usize, buf: &mut Vec<u8>) -> Vec<Self> {
+        let mut partitions = Vec::new();
+        let mut finish = |builder: BlockBuilder, first_block: usize, end_block: usize| {
+            let offset = buf.len();
+            append_block(buf, &builder.build().encode(), NO_COMPRESSION);
+            partitions.push(IndexPartition {
+                offset: offset as u64,
+                len: (buf.len() - offset) as u64,
+                first_block,
+                separator: metas[end_block - 1].separator.clone(),
+            });
+        };
+        let mut builder = BlockBuilder::new(partition_size);
+        let mut first_block = 0;
+        for (idx, meta) in metas.iter().enumerate() {
+            let value = (meta.offset as u64).to_le_bytes();
+            if !builder.add(&meta.separator, &value) {
+                let full = mem::replace(&mut builder, BlockBuilder::new(partition_size));
+                finish(full, first_block, idx);
+                first_block = idx;
+                assert!(builder.add(&meta.separator, &value));
+            }
+        }
+        finish(builder, first_block, metas.len());
+        partitions
+    }
+}
+
+/// Get the meta of the data block at `pos` in an index block.
+pub(crate) fn block_meta_at(block: Arc<Block>, pos: usize) -> Option<BlockMeta> {
+    let iter = BlockIterator::create_and_seek_to_position(block, pos);
+    iter.is_valid().then(|| meta_of_entry(&iter))
+}
+
+/// Get the offset of the data block at `pos` in an index block, and the offset of the next data
+/// block if the index block holds its meta too.
+pub(crate) fn block_offsets_at(block: Arc<Block>, pos: usize) -> Option<(usize, Option<usize>)> {
+    let mut iter = BlockIterator::create_and_seek_to_position(block, pos);
+    if !iter.is_valid() {
+        return None;
+    }
+    let offset = meta_of_entry(&iter).offset;
+    iter.next();
+    Some((offset, iter.is_valid().then(|| meta_of_entry(&iter).offset)))
+}
+
+/// Find the position in an index block of the first data block whose separator is at or after
+/// `key` in the order of `comparator`, the number of entries if there is none.
+pub(crate) fn seek_in_block(block: Arc<Block>, key: &[u8], comparator: &dyn Comparator) -> usize {
+    let mut iter = BlockIterator::create_and_seek_to_position(block, 0);
+    iter.seek_to_key_with_position_by(key, comparator)
+}
+
+fn meta_of_entry(iter: &BlockIterator) -> BlockMeta {
+    // Format version 2 follows the offset with the first key of the block.
+    let mut value = iter.value();
+    BlockMeta {
+        offset: value.get_u64_le() as usize,
+        separator: Bytes::copy_from_slice(iter.key()),
+    }
+}
+
+fn get_key(buf: &mut &[u8]) -> Result<Bytes> {
+    if buf.remaining() < 4 {
+        bail!("truncated index partition");
+    }
+    let len = buf.get_u32_le() as usize;
+    if buf.remaining() < len {
+        bail!("truncated index partition");
+    }
+    Ok(buf.copy_to_bytes(len))
+}
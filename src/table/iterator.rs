@@ -1,14 +1,14 @@
 #![allow(unused_variables)] // TODO(you): remove this lint after implementing this mod
 #![allow(dead_code)] // TODO(you): remove this lint after implementing this mod
 
-use std::{io::Bytes, sync::Arc};
+use std::sync::Arc;
 
-use anyhow::{Error, Ok, Result};
+use anyhow::{Ok, Result};
 
 use super::SsTable;
 use crate::{
-    block::{Block, BlockIterator},
-    iterators::StorageIterator,
+    block::BlockIterator,
+    iterators::{BidirectionalIterator, StorageIterator},
 };
 
 /// An iterator over the contents of an SSTable.
@@ -22,7 +22,7 @@ pub struct SsTableIterator {
 impl SsTableIterator {
     /// Create a new iterator and seek to the first key-value pair in the first data block.
     pub fn create_and_seek_to_first(table: Arc<SsTable>) -> Result<Self> {
-        let block = table.read_block(0).expect("Cannot read block");
+        let block = table.read_block_cached(0)?;
         Ok(Self {
             table,
             // block,
@@ -33,70 +33,51 @@ impl SsTableIterator {
 
     /// Seek to the first key-value pair in the first data block.
     pub fn seek_to_first(&mut self) -> Result<()> {
-        self.idx = 0;
-        let block = self.table.read_block(0).expect("Cannot read block");
-        self.block_it = BlockIterator::create_and_seek_to_first(block);
+        self.load_block(0)?;
+        self.block_it.seek_to_first();
         Ok(())
     }
 
     /// Create a new iterator and seek to the first key-value pair which >= `key`.
     pub fn create_and_seek_to_key(table: Arc<SsTable>, key: &[u8]) -> Result<Self> {
-        let block = table.read_block(0).expect("Cannot read block");
-        let mut si = SsTableIterator {
+        let idx = table.find_block_idx(key)?.min(table.num_of_blocks() - 1);
+        let block = table.read_block_cached(idx)?;
+        let mut iter = Self {
             table,
-            // block,
             block_it: BlockIterator::create_and_seek_to_first(block),
-            idx: 0,
+            idx,
         };
-        let _ = si.seek_to_key(key);
-        Ok(si)
+        iter.seek_to_key(key)?;
+        Ok(iter)
     }
 
-    /// Seek to the first key-value pair which >= `key`.
-    /// Note: You probably want to review the handout for detailed explanation when implementing this function.
+    /// Seek to the first key-value pair which >= `key`, only reading the block that may hold it
+    /// unless it is the current one.
     pub fn seek_to_key(&mut self, key: &[u8]) -> Result<()> {
-        let mut left: usize = 0;
-        let mut right: usize = self.table.num_of_blocks() - 1;
-        let mut mid = (left + right) / 2;
-        while left < right {
-            let curr_key = &self.table.block_metas[mid].first_key;
-            if self.compare_bytes(curr_key, key) {
-                right = mid;
-                mid = (left + right) / 2;
-            } else {
-                // If there exists next block
-                if mid < self.table.num_of_blocks() - 1 {
-                    let next_key = &self.table.block_metas[mid + 1].first_key;
-                    // If first key in next block is larger than query key, then this block is the one
-                    if self.compare_bytes(next_key, key) {
-                        break;
-                    } else {
-                        // Next block is also smaller, we need to continue searching
-                        left = mid + 1;
-                        mid = (left + right) / 2;
-                    }
-                } else {
-                    // If not exist, meaning curr block is last block, has to be it.=
-                    break;
-                }
-            }
-        }
-        let block = self.table.read_block(mid).expect("Cannot read block");
-        self.block_it = BlockIterator::create_and_seek_to_first(block);
-        self.block_it.seek_to_key(key);
-        if !self.block_it.is_valid() && mid < self.table.num_of_blocks() - 1 {
-            mid += 1;
-            let block = self.table.read_block(mid).expect("Cannot read block");
-            self.block_it = BlockIterator::create_and_seek_to_first(block);
-            self.block_it.seek_to_key(key);
+        // Past the last key, the iterator is left invalid at the end of the last block.
+        let idx = self
+            .table
+            .find_block_idx(key)?
+            .min(self.table.num_of_blocks() - 1);
+        self.load_block(idx)?;
+        self.block_it.seek_to_key_by(key, &*self.table.comparator);
+        // A separator may be past the last key of its block: keys between the two start the
+        // next block.
+        if !self.block_it.is_valid() && idx + 1 < self.table.num_of_blocks() {
+            self.load_block(idx + 1)?;
+            self.block_it.seek_to_first();
         }
-        self.idx = mid;
         Ok(())
     }
 
-    fn compare_bytes(&self, left: &[u8], right: &[u8]) -> bool {
-        return String::from_utf8(left.to_vec()).unwrap()
-            > String::from_utf8(right.to_vec()).unwrap();
+    /// Make block `idx` the current one, keeping the current block if it is the same.
+    fn load_block(&mut self, idx: usize) -> Result<()> {
+        if idx != self.idx {
+            let block = self.table.read_block_cached(idx)?;
+            self.block_it = BlockIterator::create_and_seek_to_first(block);
+            self.idx = idx;
+        }
+        Ok(())
     }
 }
 
@@ -121,9 +102,49 @@ impl StorageIterator for SsTableIterator {
     fn next(&mut self) -> Result<()> {
         self.block_it.next();
         if !self.block_it.is_valid() && self.idx < self.table.num_of_blocks() - 1 {
-            self.idx += 1;
-            let block: Arc<Block> = self.table.read_block(self.idx).expect("Cannot read block");
-            self.block_it = BlockIterator::create_and_seek_to_first(block);
+            self.load_block(self.idx + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl BidirectionalIterator for SsTableIterator {
+    /// Move to the previous `key`, in the previous block once the current one is exhausted.
+    fn prev(&mut self) -> Result<()> {
+        self.block_it.prev();
+        if !self.block_it.is_valid() && self.idx > 0 {
+            self.load_block(self.idx - 1)?;
+            self.block_it.seek_to_last();
+        }
+        Ok(())
+    }
+
+    fn seek_to_first(&mut self) -> Result<()> {
+        SsTableIterator::seek_to_first(self)
+    }
+
+    fn seek_to_last(&mut self) -> Result<()> {
+        self.load_block(self.table.num_of_blocks() - 1)?;
+        self.block_it.seek_to_last();
+        Ok(())
+    }
+
+    fn seek(&mut self, key: &[u8]) -> Result<()> {
+        self.seek_to_key(key)
+    }
+
+    /// Seek to the last key-value pair which <= `key`. It is in the block `key` would be in, or
+    /// is the last one of the block before.
+    fn seek_for_prev(&mut self, key: &[u8]) -> Result<()> {
+        let idx = self
+            .table
+            .find_block_idx(key)?
+            .min(self.table.num_of_blocks() - 1);
+        self.load_block(idx)?;
+        self.block_it.seek_for_prev_by(key, &*self.table.comparator);
+        if !self.block_it.is_valid() && idx > 0 {
+            self.load_block(idx - 1)?;
+            self.block_it.seek_to_last();
         }
         Ok(())
     }
@@ -0,0 +1,99 @@
+use anyhow::{bail, Result};
+use bytes::Bytes;
+
+/// The bloom filter of the prefixes of the keys written by SST format versions 3 and 4, read to
+/// keep ruling prefixes out in older SSTs. Newer SSTs store filters built by a `FilterPolicy`.
+///
+/// | bits | k (u8) |
+///
+/// Each prefix sets `k` bits, found by double hashing from its 32-bit hash, see `hash`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct LegacyBloom {
+    bits: Bytes,
+    k: u8,
+}
+
+impl LegacyBloom {
+    /// Build a filter of `keys` with about `bits_per_key` bits for each, as format versions 3 and 4
+    /// did.
+    #[cfg(test)]
+    pub fn build(keys: &[&[u8]], bits_per_key: usize) -> Self {
+        let k = ((bits_per_key as f64 * 0.69) as u8).clamp(1, 30);
+        let num_bits = (keys.len() * bits_per_key).max(64);
+        let mut bits = vec![0u8; num_bits.div_ceil(8)];
+        let num_bits = bits.len() * 8;
+        for key in keys {
+            for bit in Self::bits(hash(key), k, num_bits) {
+                bits[bit / 8] |= 1 << (bit % 8);
+            }
+        }
+        Self {
+            bits: bits.into(),
+            k,
+        }
+    }
+
+    /// Check if `key` may be in the filter.
+    pub fn may_contain(&self, key: &[u8]) -> bool {
+        Self::bits(hash(key), self.k, self.bits.len() * 8)
+            .all(|bit| self.bits[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    /// Get the size of the filter, in bytes.
+    pub fn size(&self) -> usize {
+        self.bits.len() + 1
+    }
+
+    #[cfg(test)]
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.bits);
+        buf.push(self.k);
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self> {
+        let Some((&k, bits)) = buf.split_last() else {
+            bail!("empty bloom filter");
+        };
+        if bits.is_empty() {
+            bail!("bloom filter without bits");
+        }
+        Ok(Self {
+            bits: Bytes::copy_from_slice(bits),
+            k,
+        })
+    }
+
+    /// Get the `k` bits set by the key hashed to `hash`.
+    fn bits(hash: u32, k: u8, num_bits: usize) -> impl Iterator<Item = usize> {
+        let delta = hash.rotate_left(15);
+        let mut hash = hash;
+        (0..k).map(move |_| {
+            let bit = hash as usize % num_bits;
+            hash = hash.wrapping_add(delta);
+            bit
+        })
+    }
+}
+
+/// Hash a key with the 32-bit hash of LevelDB.
+fn hash(key: &[u8]) -> u32 {
+    const SEED: u32 = 0xbc9f1d34;
+    const M: u32 = 0xc6a4a793;
+    let mut hash = SEED ^ (key.len() as u32).wrapping_mul(M);
+    let mut chunks = key.chunks_exact(4);
+    for chunk in &mut chunks {
+        hash = hash
+            .wrapping_add(u32::from_le_bytes(chunk.try_into().unwrap()))
+            .wrapping_mul(M);
+        hash ^= hash >> 16;
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        for (idx, byte) in rest.iter().enumerate() {
+            hash = hash.wrapping_add((*byte as u32) << (8 * idx));
+        }
+        hash = hash.wrapping_mul(M);
+        hash ^= hash >> 24;
+    }
+    hash
+}
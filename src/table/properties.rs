@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use anyhow::{bail, Result};
+use bytes::{Buf, BufMut, Bytes};
+
+use crate::comparator::Comparator;
+use crate::range_tombstone::RangeTombstone;
+use crate::table_properties_collector::UserProperties;
+use crate::value::Record;
+
+/// What an SSTable holds, collected by `SsTableBuilder` while it is built so that readers can
+/// tell without scanning it.
+///
+/// Properties are stored in the properties section as entries sorted by name, built-in ones
+/// being named `minilsm.*` and user properties `user.*`. Unknown names are ignored when reading.
+///
+/// Entry: | name_len (u32) | name | value_len (u32) | value |
+///
+/// Lengths and numbers are little-endian, numbers are u64.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TableProperties {
+    /// The number of key-value pairs.
+    pub num_entries: u64,
+    /// The number of point deletions among them.
+    pub num_tombstones: u64,
+    /// The number of entries written with a TTL.
+    pub num_expiring_entries: u64,
+    /// The number of range tombstones.
+    pub num_range_tombstones: u64,
+    /// The total size of the keys.
+    pub raw_key_size: u64,
+    /// The total size of the values, as stored by the memtables.
+    pub raw_value_size: u64,
+    pub num_data_blocks: u64,
+    /// The number of index blocks, 0 if the block metas are all kept in the meta section.
+    pub num_index_partitions: u64,
+    /// The size of the index: the block metas, or the index blocks and their partitions.
+    pub index_size: u64,
+    /// How much smaller the index is thanks to separators, compared to keying the blocks by
+    /// their last key.
+    pub index_size_saved: u64,
+    /// The size of the data blocks on disk, after compression.
+    pub data_size: u64,
+    /// The first key, empty if the table has no key-value pairs.
+    pub first_key: Bytes,
+    /// The last key, empty if the table has no key-value pairs.
+    pub last_key: Bytes,
+    /// The smallest sequence number of the entries and range tombstones, 0 if there are none.
+    pub min_seq: u64,
+    /// The largest sequence number of the entries and range tombstones, 0 if there are none.
+    pub max_seq: u64,
+    /// When the table was built, in Unix milliseconds.
+    pub creation_time: u64,
+    /// The name of the codec compressing the data blocks, "none" for no compression.
+    pub compression: String,
+    /// The name of the comparator ordering the keys, empty if the table did not record it: it
+    /// was written with the bytewise comparator.
+    pub comparator: String,
+    /// The name of the policy the key and prefix filters were built with, empty if the table has
+    /// neither.
+    pub filter_policy: String,
+    /// The name of the prefix extractor the prefix filter was built with, empty if the table has
+    /// no prefix filter.
+    pub prefix_extractor: String,
+    /// The properties recorded by the `TablePropertiesCollector`s the table was built with.
+    pub user_properties: UserProperties,
+}
+
+const NUM_ENTRIES: &str = "minilsm.num_entries";
+const NUM_TOMBSTONES: &str = "minilsm.num_tombstones";
+const NUM_EXPIRING_ENTRIES: &str = "minilsm.num_expiring_entries";
+const NUM_RANGE_TOMBSTONES: &str = "minilsm.num_range_tombstones";
+const RAW_KEY_SIZE: &str = "minilsm.raw_key_size";
+const RAW_VALUE_SIZE: &str = "minilsm.raw_value_size";
+const NUM_DATA_BLOCKS: &str = "minilsm.num_data_blocks";
+const DATA_SIZE: &str = "minilsm.data_size";
+const NUM_INDEX_PARTITIONS: &str = "minilsm.num_index_partitions";
+const INDEX_SIZE: &str = "minilsm.index_size";
+const INDEX_SIZE_SAVED: &str = "minilsm.index_size_saved";
+const FIRST_KEY: &str = "minilsm.first_key";
+const LAST_KEY: &str = "minilsm.last_key";
+const MIN_SEQ: &str = "minilsm.min_seq";
+const MAX_SEQ: &str = "minilsm.max_seq";
+const CREATION_TIME: &str = "minilsm.creation_time";
+const COMPRESSION: &str = "minilsm.compression";
+const COMPARATOR: &str = "minilsm.comparator";
+const FILTER_POLICY: &str = "minilsm.filter_policy";
+const PREFIX_EXTRACTOR: &str = "minilsm.prefix_extractor";
+const USER_PREFIX: &str = "user.";
+
+impl TableProperties {
+    /// Account for a key-value pair added to the table. Values which are not records only count
+    /// towards the entries and their sizes.
+    pub(crate) fn add(&mut self, key: &[u8], value: &[u8]) {
+        self.num_entries += 1;
+        self.raw_key_size += key.len() as u64;
+        self.raw_value_size += value.len() as u64;
+        if let Some(record) = Record::try_decode(value) {
+            if record.is_tombstone() {
+                self.num_tombstones += 1;
+            }
+            if record.expire_at.is_some() {
+                self.num_expiring_entries += 1;
+            }
+            self.add_seq(record.seq);
+        }
+    }
+
+    /// Account for a range tombstone added to the table.
+    pub(crate) fn add_range_tombstone(&mut self, tombstone: &RangeTombstone) {
+        self.num_range_tombstones += 1;
+        self.add_seq(tombstone.seq);
+    }
+
+    fn add_seq(&mut self, seq: u64) {
+        if self.min_seq == 0 || seq < self.min_seq {
+            self.min_seq = seq;
+        }
+        self.max_seq = self.max_seq.max(seq);
+    }
+
+    /// Check if the key range of the table overlaps the range between `lower` and `upper`, in the
+    /// order of `comparator`.
+    pub fn overlaps(
+        &self,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+        comparator: &dyn Comparator,
+    ) -> bool {
+        !self.first_key.is_empty()
+            && comparator.is_above(&self.last_key, lower)
+            && comparator.is_below(&self.first_key, upper)
+    }
+
+    pub(crate) fn encode(&self, buf: &mut Vec<u8>) {
+        let mut entries: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        for (name, value) in [
+            (NUM_ENTRIES, self.num_entries),
+            (NUM_TOMBSTONES, self.num_tombstones),
+            (NUM_EXPIRING_ENTRIES, self.num_expiring_entries),
+            (NUM_RANGE_TOMBSTONES, self.num_range_tombstones),
+            (RAW_KEY_SIZE, self.raw_key_size),
+            (RAW_VALUE_SIZE, self.raw_value_size),
+            (NUM_DATA_BLOCKS, self.num_data_blocks),
+            (DATA_SIZE, self.data_size),
+            (NUM_INDEX_PARTITIONS, self.num_index_partitions),
+            (INDEX_SIZE, self.index_size),
+            (INDEX_SIZE_SAVED, self.index_size_saved),
+            (MIN_SEQ, self.min_seq),
+            (MAX_SEQ, self.max_seq),
+            (CREATION_TIME, self.creation_time),
+        ] {
+            entries.insert(name.to_string(), value.to_le_bytes().to_vec());
+        }
+        entries.insert(FIRST_KEY.to_string(), self.first_key.to_vec());
+        entries.insert(LAST_KEY.to_string(), self.last_key.to_vec());
+        entries.insert(
+            COMPRESSION.to_string(),
+            self.compression.as_bytes().to_vec(),
+        );
+        entries.insert(COMPARATOR.to_string(), self.comparator.as_bytes().to_vec());
+        entries.insert(
+            FILTER_POLICY.to_string(),
+            self.filter_policy.as_bytes().to_vec(),
+        );
+        entries.insert(
+            PREFIX_EXTRACTOR.to_string(),
+            self.prefix_extractor.as_bytes().to_vec(),
+        );
+        for (name, value) in &self.user_properties {
+            entries.insert(USER_PREFIX.to_string() + name, value.to_le_bytes().to_vec());
+        }
+        for (name, value) in entries {
+            buf.put_u32_le(name.len() as u32);
+            buf.put_slice(name.as_bytes());
+            buf.put_u32_le(value.len() as u32);
+            buf.put_slice(&value);
+        }
+    }
+
+    pub(crate) fn decode(mut buf: &[u8]) -> Result<Self> {
+        let mut properties = TableProperties::default();
+        while buf.has_remaining() {
+            let name = get_slice(&mut buf)?;
+            let value = get_slice(&mut buf)?;
+            let number = || -> Result<u64> {
+                match <[u8; 8]>::try_from(value) {
+                    Ok(bytes) => Ok(u64::from_le_bytes(bytes)),
+                    Err(_) => bail!("property {} is not a number", String::from_utf8_lossy(name)),
+                }
+            };
+            let name = std::str::from_utf8(name).unwrap_or_default();
+            if let Some(name) = name.strip_prefix(USER_PREFIX) {
+                properties
+                    .user_properties
+                    .insert(name.to_string(), number()?);
+                continue;
+            }
+            match name {
+                NUM_ENTRIES => properties.num_entries = number()?,
+                NUM_TOMBSTONES => properties.num_tombstones = number()?,
+                NUM_EXPIRING_ENTRIES => properties.num_expiring_entries = number()?,
+                NUM_RANGE_TOMBSTONES => properties.num_range_tombstones = number()?,
+                RAW_KEY_SIZE => properties.raw_key_size = number()?,
+                RAW_VALUE_SIZE => properties.raw_value_size = number()?,
+                NUM_DATA_BLOCKS => properties.num_data_blocks = number()?,
+                DATA_SIZE => properties.data_size = number()?,
+                NUM_INDEX_PARTITIONS => properties.num_index_partitions = number()?,
+                INDEX_SIZE => properties.index_size = number()?,
+                INDEX_SIZE_SAVED => properties.index_size_saved = number()?,
+                MIN_SEQ => properties.min_seq = number()?,
+                MAX_SEQ => properties.max_seq = number()?,
+                CREATION_TIME => properties.creation_time = number()?,
+                FIRST_KEY => properties.first_key = Bytes::copy_from_slice(value),
+                LAST_KEY => properties.last_key = Bytes::copy_from_slice(value),
+                COMPRESSION => properties.compression = String::from_utf8_lossy(value).into(),
+                COMPARATOR => properties.comparator = String::from_utf8_lossy(value).into(),
+                FILTER_POLICY => properties.filter_policy = String::from_utf8_lossy(value).into(),
+                PREFIX_EXTRACTOR => {
+                    properties.prefix_extractor = String::from_utf8_lossy(value).into()
+                }
+                _ => {}
+            }
+        }
+        Ok(properties)
+    }
+}
+
+/// Read a length-prefixed slice off `buf`.
+fn get_slice<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8]> {
+    if buf.remaining() < 4 {
+        bail!("truncated property length");
+    }
+    let len = buf.get_u32_le() as usize;
+    if buf.remaining() < len {
+        bail!("truncated property");
+    }
+    let (slice, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(slice)
+}
@@ -0,0 +1,163 @@
+use std::ops::Bound;
+
+use anyhow::{bail, Result};
+use bytes::{Buf, BufMut, Bytes};
+
+use crate::block::varint::{put_varint, try_get_varint};
+
+/// A range filter, telling that no key of an SST is in a range or that some may be.
+///
+/// Like the trie of SuRF, it keeps the shortest prefix of each key that tells it apart from the
+/// keys around it, followed by a few more bytes of the key to rule out more ranges. A key whose
+/// prefix is the whole key is known exactly, any other key is only known to start with its
+/// prefix. The prefixes are sorted, and the keys each may stand for do not overlap, so a range is
+/// looked up with a binary search. Requires keys ordered bytewise.
+///
+/// Each prefix is stored as the length it shares with the previous one, then the length and bytes
+/// of the rest, and whether it is the whole key (u8). Lengths are varints, see `put_varint`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RangeFilter {
+    prefixes: Vec<KeyPrefix>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct KeyPrefix {
+    prefix: Bytes,
+    /// Whether the prefix is the whole key.
+    complete: bool,
+}
+
+impl KeyPrefix {
+    /// Check whether the key may be after `lower`.
+    fn may_be_after(&self, lower: Bound<&[u8]>) -> bool {
+        match lower {
+            Bound::Included(lower) if self.complete => self.prefix >= lower,
+            Bound::Excluded(lower) if self.complete => self.prefix > lower,
+            // Keys longer than `lower` and starting with it come after it.
+            Bound::Included(lower) | Bound::Excluded(lower) => {
+                &self.prefix[..] >= lower || lower.starts_with(&self.prefix)
+            }
+            Bound::Unbounded => true,
+        }
+    }
+
+    /// Check whether the key may be before `upper`.
+    fn may_be_before(&self, upper: Bound<&[u8]>) -> bool {
+        match upper {
+            Bound::Included(upper) if self.complete => self.prefix <= upper,
+            // An incomplete key is longer than its prefix, and so after it.
+            Bound::Included(upper) | Bound::Excluded(upper) => self.prefix < upper,
+            Bound::Unbounded => true,
+        }
+    }
+}
+
+impl RangeFilter {
+    /// Check whether a key in the range may be in the filter.
+    pub fn may_overlap(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> bool {
+        let idx = self
+            .prefixes
+            .partition_point(|prefix| !prefix.may_be_after(lower));
+        self.prefixes
+            .get(idx)
+            .is_some_and(|prefix| prefix.may_be_before(upper))
+    }
+
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        let mut last: &[u8] = &[];
+        for prefix in &self.prefixes {
+            let shared = common_prefix_len(last, &prefix.prefix);
+            let rest = &prefix.prefix[shared..];
+            put_varint(buf, shared as u64);
+            put_varint(buf, rest.len() as u64);
+            buf.put_slice(rest);
+            buf.put_u8(prefix.complete as u8);
+            last = &prefix.prefix;
+        }
+    }
+
+    pub fn decode(mut buf: &[u8]) -> Result<Self> {
+        let mut prefixes: Vec<KeyPrefix> = Vec::new();
+        while buf.has_remaining() {
+            let (Some(shared), Some(rest_len)) =
+                (try_get_varint(&mut buf), try_get_varint(&mut buf))
+            else {
+                bail!("truncated range filter");
+            };
+            let (shared, rest_len) = (shared as usize, rest_len as usize);
+            let last = prefixes.last().map_or(&[][..], |last| &last.prefix[..]);
+            if shared > last.len() || buf.remaining() <= rest_len {
+                bail!("truncated range filter");
+            }
+            let mut prefix = last[..shared].to_vec();
+            prefix.extend_from_slice(&buf[..rest_len]);
+            buf.advance(rest_len);
+            prefixes.push(KeyPrefix {
+                prefix: prefix.into(),
+                complete: buf.get_u8() != 0,
+            });
+        }
+        Ok(Self { prefixes })
+    }
+}
+
+/// Builds a `RangeFilter` from keys added in bytewise order.
+pub(crate) struct RangeFilterBuilder {
+    /// The number of bytes kept after the prefix telling each key apart.
+    suffix_len: usize,
+    prefixes: Vec<KeyPrefix>,
+    last_key: Vec<u8>,
+    /// The length of the prefix the last key shares with the one before it.
+    last_shared: usize,
+}
+
+impl RangeFilterBuilder {
+    pub fn new(suffix_len: usize) -> Self {
+        Self {
+            suffix_len,
+            prefixes: Vec::new(),
+            last_key: Vec::new(),
+            last_shared: 0,
+        }
+    }
+
+    pub fn add(&mut self, key: &[u8]) {
+        if self.last_key.is_empty() {
+            self.last_key.extend_from_slice(key);
+            return;
+        }
+        if self.last_key == key {
+            return;
+        }
+        let shared = common_prefix_len(&self.last_key, key);
+        self.push_last(shared);
+        self.last_shared = shared;
+        self.last_key.clear();
+        self.last_key.extend_from_slice(key);
+    }
+
+    pub fn build(mut self) -> RangeFilter {
+        if !self.last_key.is_empty() {
+            self.push_last(0);
+        }
+        RangeFilter {
+            prefixes: self.prefixes,
+        }
+    }
+
+    /// Keep the last key up to the first byte telling it apart from the keys around it, which
+    /// share `shared_with_next` bytes with the next one, and `suffix_len` bytes more.
+    fn push_last(&mut self, shared_with_next: usize) {
+        let len = (self.last_shared.max(shared_with_next) + 1).saturating_add(self.suffix_len);
+        let complete = len >= self.last_key.len();
+        let len = len.min(self.last_key.len());
+        self.prefixes.push(KeyPrefix {
+            prefix: Bytes::copy_from_slice(&self.last_key[..len]),
+            complete,
+        });
+    }
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
@@ -1,11 +1,18 @@
+use std::ops::Bound;
 use std::sync::Arc;
 
-use bytes::Bytes;
+use bytes::{BufMut, Bytes};
 use tempfile::{tempdir, TempDir};
 
 use super::*;
-use crate::iterators::StorageIterator;
+use crate::block::BlockIterator;
+use crate::comparator::{BytewiseComparator, ReverseBytewiseComparator};
+use crate::compression::{CompressionCodec, Lz4Codec};
+use crate::filter_policy::{BloomFilterPolicy, FilterPolicy, XorFilterPolicy};
+use crate::iterators::{BidirectionalIterator, StorageIterator};
+use crate::prefix_extractor::FixedPrefixExtractor;
 use crate::table::SsTableBuilder;
+use crate::value::{Record, ValueKind};
 // #[ignore]
 #[test]
 fn test_sst_build_single_key() {
@@ -61,9 +68,15 @@ fn test_sst_build_all() {
 #[test]
 fn test_sst_decode() {
     let (_dir, sst) = generate_sst();
-    let meta = sst.block_metas.clone();
+    let meta = block_metas(&sst);
     let new_sst = SsTable::open_for_test(sst.file).unwrap();
-    assert_eq!(new_sst.block_metas, meta);
+    assert_eq!(block_metas(&new_sst), meta);
+}
+
+fn block_metas(sst: &SsTable) -> Vec<BlockMeta> {
+    (0..sst.num_of_blocks())
+        .map(|idx| sst.block_meta(idx).unwrap())
+        .collect()
 }
 
 fn as_bytes(x: &[u8]) -> Bytes {
@@ -130,3 +143,796 @@ fn test_sst_seek_key() {
         iter.seek_to_key(b"k").unwrap();
     }
 }
+
+#[test]
+fn test_sst_large_values() {
+    let mut builder = SsTableBuilder::new(4096);
+    let large = vec![b'x'; 3 << 20];
+    builder.add(b"a", b"small");
+    builder.add(b"b", &large);
+    builder.add(b"c", b"small");
+    builder.add(b"d", &large[..70000]);
+    // The large values are in blocks of their own, finished as soon as they are added.
+    assert_eq!(builder.meta.len(), 4);
+    let dir = tempdir().unwrap();
+    let sst = Arc::new(builder.build_for_test(dir.path().join("1.sst")).unwrap());
+    assert_eq!(sst.num_of_blocks(), 4);
+
+    let mut iter = SsTableIterator::create_and_seek_to_first(sst.clone()).unwrap();
+    for (key, value) in [
+        (&b"a"[..], &b"small"[..]),
+        (b"b", &large),
+        (b"c", b"small"),
+        (b"d", &large[..70000]),
+    ] {
+        assert_eq!(iter.key(), key);
+        assert_eq!(iter.value(), value);
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+    let iter = SsTableIterator::create_and_seek_to_key(sst, b"c").unwrap();
+    assert_eq!(iter.value(), b"small");
+}
+
+#[test]
+fn test_crc32c() {
+    assert_eq!(checksum::crc32c(b""), 0);
+    assert_eq!(checksum::crc32c(b"123456789"), 0xe306_9283);
+}
+
+fn corruption_of(err: anyhow::Error) -> SsTableError {
+    *err.downcast_ref::<SsTableError>().unwrap()
+}
+
+#[test]
+fn test_sst_corruption() {
+    let (_dir, sst) = generate_sst();
+    let data = sst.file.0.to_vec();
+    let corrupt = |pos: usize| {
+        let mut data = data.clone();
+        data[pos] ^= 1;
+        FileObject(data.into())
+    };
+
+    // Flip a byte in the second block, it only fails once the block is read.
+    let pos = sst.block_meta(1).unwrap().offset + 3;
+    let sst = Arc::new(SsTable::open(7, None, corrupt(pos)).unwrap());
+    assert!(sst.read_block(0).is_ok());
+    assert_eq!(
+        corruption_of(sst.read_block(1).err().unwrap()),
+        SsTableError::Corruption {
+            sst_id: 7,
+            block_idx: 1
+        }
+    );
+    let mut iter = SsTableIterator::create_and_seek_to_first(sst.clone()).unwrap();
+    let err = loop {
+        if let Err(err) = iter.next() {
+            break err;
+        }
+    };
+    assert_eq!(
+        corruption_of(err),
+        SsTableError::Corruption {
+            sst_id: 7,
+            block_idx: 1
+        }
+    );
+    let key = sst.block_meta(1).unwrap().separator;
+    assert!(SsTableIterator::create_and_seek_to_key(sst, &key).is_err());
+
+    // Corrupted metas and footers are caught when opening.
+    for pos in [
+        sst_meta_offset(&data),
+        data.len() - 30,
+        data.len() - 10,
+        data.len() - 1,
+    ] {
+        assert_eq!(
+            corruption_of(SsTable::open(7, None, corrupt(pos)).err().unwrap()),
+            SsTableError::MetaCorruption { sst_id: 7 }
+        );
+    }
+}
+
+#[test]
+fn test_sst_multi_get() {
+    let (_dir, sst) = generate_sst();
+    let data = sst.file.0.to_vec();
+    let keys: Vec<Vec<u8>> = (0..num_of_keys())
+        .flat_map(|idx| [key_of(idx), format!("key_{:03}", idx * 5 + 1).into_bytes()])
+        .collect();
+    let keys: Vec<&[u8]> = keys.iter().map(|key| &key[..]).collect();
+    let values = sst.multi_get(&keys);
+    assert_eq!(values.len(), keys.len());
+    for (idx, value) in values.into_iter().enumerate() {
+        match idx % 2 {
+            0 => assert_eq!(value.unwrap().unwrap(), value_of(idx / 2)),
+            _ => assert!(value.unwrap().is_none()),
+        }
+    }
+
+    // A corrupted block only fails the keys in it.
+    let mut data = data;
+    data[sst.block_meta(1).unwrap().offset + 3] ^= 1;
+    let sst = SsTable::open(7, None, FileObject(data.into())).unwrap();
+    let values = sst.multi_get(&keys);
+    let mut failed = 0;
+    for (key, value) in keys.iter().zip(values) {
+        if sst.find_block_idx(key).unwrap() == 1 {
+            assert_eq!(
+                corruption_of(value.err().unwrap()),
+                SsTableError::Corruption {
+                    sst_id: 7,
+                    block_idx: 1
+                }
+            );
+            failed += 1;
+        } else {
+            assert!(value.is_ok());
+        }
+    }
+    assert!(failed > 0);
+}
+
+fn sst_meta_offset(data: &[u8]) -> usize {
+    // The meta block offset starts the 52 byte footer.
+    let mut footer = &data[data.len() - 52..];
+    footer.get_u64_le() as usize
+}
+
+/// Rewrite `sst` in format version 1: big-endian block metas, no filter or properties section and
+/// a 24 byte footer without magic number.
+fn to_format_v1(sst: &SsTable) -> FileObject {
+    let data = &sst.file.0;
+    let mut buf = data[..sst.data_end].to_vec();
+    for (idx, meta) in block_metas(sst).into_iter().enumerate() {
+        let mut iter = BlockIterator::create_and_seek_to_first(sst.read_block(idx).unwrap());
+        let first_key = iter.key().to_vec();
+        let mut last_key = Vec::new();
+        while iter.is_valid() {
+            last_key = iter.key().to_vec();
+            iter.next();
+        }
+        buf.put_u64(meta.offset as u64);
+        buf.put_u64(first_key.len() as u64);
+        buf.put_slice(&first_key);
+        buf.put_u64(last_key.len() as u64);
+        buf.put_slice(&last_key);
+    }
+    let range_tombstone_offset = buf.len();
+    RangeTombstone::encode_range_tombstones(&sst.range_tombstones, &mut buf);
+    let meta_checksum = checksum::crc32c(&buf[sst.data_end..]);
+    let footer_offset = buf.len();
+    buf.put_u64(range_tombstone_offset as u64);
+    buf.put_u64(sst.data_end as u64);
+    buf.put_u32(meta_checksum);
+    buf.put_u32(checksum::crc32c(&buf[footer_offset..]));
+    FileObject(buf.into())
+}
+
+#[test]
+fn test_sst_format_v1() {
+    let (_dir, sst) = generate_sst();
+    assert_eq!(sst.format_version(), SST_FORMAT_VERSION);
+    let v1 = Arc::new(SsTable::open(3, None, to_format_v1(&sst)).unwrap());
+    assert_eq!(v1.format_version(), 1);
+    assert_eq!(v1.first_key(), sst.first_key());
+    assert_eq!(v1.last_key(), sst.last_key());
+    let mut iter = SsTableIterator::create_and_seek_to_first(v1.clone()).unwrap();
+    for idx in 0..num_of_keys() {
+        assert_eq!(iter.key(), key_of(idx));
+        assert_eq!(iter.value(), value_of(idx));
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+    for idx in 0..num_of_keys() {
+        let iter = SsTableIterator::create_and_seek_to_key(v1.clone(), &key_of(idx)).unwrap();
+        assert_eq!(iter.value(), value_of(idx));
+    }
+}
+
+#[test]
+fn test_sst_prefix_filter() {
+    let dir = tempdir().unwrap();
+    let extractor: Arc<dyn PrefixExtractor> = Arc::new(FixedPrefixExtractor::new(5));
+    let mut builder = SsTableBuilder::new(128);
+    builder.set_filter_policy(Some(Arc::new(BloomFilterPolicy)), 10.0);
+    builder.set_whole_key_filtering(false);
+    builder.set_prefix_extractor(Some(extractor.clone()));
+    for user in (0..100).step_by(2) {
+        for item in 0..3 {
+            builder.add(format!("u{:03}:{}", user, item).as_bytes(), b"v");
+        }
+    }
+    let sst = builder.build_for_test(dir.path().join("1.sst")).unwrap();
+    assert_eq!(sst.properties().prefix_extractor, extractor.name());
+    let reopened = SsTable::open(1, None, FileObject(sst.file.0.clone())).unwrap();
+    let filter_of = |sst: &SsTable| match &sst.prefix_filter {
+        Some(PrefixFilter::Policy(filter)) => filter.filter.clone(),
+        _ => panic!("no prefix filter built by the policy"),
+    };
+    assert_eq!(filter_of(&reopened), filter_of(&sst));
+    assert!(reopened.key_filter.is_none());
+
+    // No false negatives, and few false positives.
+    let mut false_positives = 0;
+    for user in 0..100 {
+        let prefix = format!("u{:03}:", user);
+        let may_contain = reopened.may_contain_prefix(&*extractor, prefix.as_bytes());
+        if user % 2 == 0 {
+            assert!(may_contain);
+        } else if may_contain {
+            false_positives += 1;
+        }
+    }
+    assert!(false_positives < 5, "{} false positives", false_positives);
+
+    // Prefixes of another length, or filters of another extractor, cannot rule anything out.
+    assert!(reopened.may_contain_prefix(&*extractor, b"u001"));
+    let other = FixedPrefixExtractor::new(4);
+    assert!(reopened.may_contain_prefix(&other, b"u001"));
+}
+
+/// Rewrite `sst` in format `version` with `filter_section` in place of its filter section.
+fn with_filter_section(sst: &SsTable, version: u32, filter_section: &[u8]) -> FileObject {
+    let data = &sst.file.0;
+    let footer = Footer::decode(sst.id, &sst.file).unwrap();
+    let mut buf = data[..footer.filter_offset as usize].to_vec();
+    buf.put_slice(filter_section);
+    let properties_offset = buf.len();
+    buf.put_slice(&data[footer.properties_offset as usize..footer.offset as usize]);
+    let meta_checksum = checksum::crc32c(&buf[footer.block_meta_offset as usize..]);
+    let footer_offset = buf.len();
+    buf.put_u64_le(footer.block_meta_offset);
+    buf.put_u64_le(footer.range_tombstone_offset);
+    buf.put_u64_le(footer.filter_offset);
+    buf.put_u64_le(properties_offset as u64);
+    buf.put_u32_le(meta_checksum);
+    buf.put_u32_le(version);
+    buf.put_u32_le(checksum::crc32c(&buf[footer_offset..]));
+    buf.put_u64_le(SST_MAGIC);
+    FileObject(buf.into())
+}
+
+#[test]
+fn test_sst_legacy_prefix_filter() {
+    let dir = tempdir().unwrap();
+    let extractor: Arc<dyn PrefixExtractor> = Arc::new(FixedPrefixExtractor::new(5));
+    let mut builder = SsTableBuilder::new(128);
+    builder.set_filter_policy(Some(Arc::new(BloomFilterPolicy)), 10.0);
+    builder.set_whole_key_filtering(false);
+    builder.set_prefix_extractor(Some(extractor.clone()));
+    let mut prefixes = Vec::new();
+    for user in (0..100).step_by(2) {
+        prefixes.push(format!("u{:03}:", user).into_bytes());
+        for item in 0..3 {
+            builder.add(format!("u{:03}:{}", user, item).as_bytes(), b"v");
+        }
+    }
+    let sst = builder.build_for_test(dir.path().join("1.sst")).unwrap();
+    let prefixes: Vec<&[u8]> = prefixes.iter().map(|prefix| &prefix[..]).collect();
+    let mut filter = Vec::new();
+    LegacyBloom::build(&prefixes, 10).encode(&mut filter);
+
+    // Versions 3 and 4 stored a bloom filter of the prefixes hashed with the hash of LevelDB,
+    // alone in version 3 and as a filter of the filter section in version 4.
+    let mut section = Vec::new();
+    append_filter(&mut section, PREFIX_FILTER, |buf| buf.put_slice(&filter));
+    for (version, section) in [(3, &filter), (4, &section)] {
+        let sst = SsTable::open(1, None, with_filter_section(&sst, version, section)).unwrap();
+        assert_eq!(sst.format_version(), version);
+        assert!(sst.key_filter.is_none());
+        let mut false_positives = 0;
+        for user in 0..100 {
+            let prefix = format!("u{:03}:", user);
+            let may_contain = sst.may_contain_prefix(&*extractor, prefix.as_bytes());
+            if user % 2 == 0 {
+                assert!(may_contain);
+            } else if may_contain {
+                false_positives += 1;
+            }
+        }
+        assert!(false_positives < 5, "{} false positives", false_positives);
+        assert_eq!(sst.filter_size(), filter.len());
+    }
+    let v3 = SsTable::open(1, None, with_filter_section(&sst, 3, &[])).unwrap();
+    assert!(v3.prefix_filter.is_none());
+}
+
+/// The built-in bloom filter policy under another id, to check policies that are not built-in.
+struct CustomFilterPolicy;
+
+impl FilterPolicy for CustomFilterPolicy {
+    fn id(&self) -> u8 {
+        200
+    }
+
+    fn name(&self) -> &str {
+        "custom"
+    }
+
+    fn build(&self, hashes: &[u64], bits_per_key: f64) -> Vec<u8> {
+        BloomFilterPolicy.build(hashes, bits_per_key)
+    }
+
+    fn may_contain(&self, filter: &[u8], hash: u64) -> bool {
+        BloomFilterPolicy.may_contain(filter, hash)
+    }
+}
+
+#[test]
+fn test_sst_key_filter() {
+    let dir = tempdir().unwrap();
+    let policies: [Arc<dyn FilterPolicy>; 3] = [
+        Arc::new(BloomFilterPolicy),
+        Arc::new(XorFilterPolicy),
+        Arc::new(CustomFilterPolicy),
+    ];
+    for policy in policies {
+        let mut builder = SsTableBuilder::new(128);
+        builder.set_filter_policy(Some(policy.clone()), 10.0);
+        for idx in 0..num_of_keys() {
+            builder.add(&key_of(idx), &value_of(idx));
+        }
+        let sst = builder.build_for_test(dir.path().join("1.sst")).unwrap();
+        assert_eq!(sst.properties().filter_policy, policy.name());
+        let reopened = SsTable::open(1, None, FileObject(sst.file.0.clone())).unwrap();
+        let mut false_positives = 0;
+        for idx in 0..num_of_keys() * 5 {
+            let key = format!("key_{:03}", idx).into_bytes();
+            if idx % 5 == 0 {
+                assert!(sst.may_contain_key(&key));
+                assert!(reopened.may_contain_key(&key));
+            } else if sst.may_contain_key(&key) {
+                false_positives += 1;
+            }
+        }
+        assert!(false_positives < 20, "{} false positives", false_positives);
+        // The filters of policies that are not built-in cannot be read back.
+        assert_eq!(reopened.key_filter.is_some(), policy.id() < 128);
+    }
+}
+
+#[test]
+fn test_sst_range_filter() {
+    let dir = tempdir().unwrap();
+    let keys: [&[u8]; 6] = [b"apple", b"apply", b"banana", b"band", b"cat", b"catalog"];
+    let mut builder = SsTableBuilder::new(128);
+    builder.set_range_filter(Some(0));
+    for key in keys {
+        builder.add(key, b"v");
+    }
+    let sst = builder.build_for_test(dir.path().join("1.sst")).unwrap();
+    let sst = SsTable::open(1, None, FileObject(sst.file.0.clone())).unwrap();
+    assert!(sst.range_filter.is_some());
+
+    let may_overlap = |lower: Bound<&[u8]>, upper: Bound<&[u8]>| sst.may_overlap(lower, upper);
+    for key in keys {
+        assert!(may_overlap(Bound::Included(key), Bound::Included(key)));
+    }
+    assert!(may_overlap(
+        Bound::Included(b"apple"),
+        Bound::Excluded(b"applx")
+    ));
+    assert!(may_overlap(
+        Bound::Excluded(b"bana"),
+        Bound::Excluded(b"banb")
+    ));
+    assert!(may_overlap(Bound::Unbounded, Bound::Unbounded));
+    // "cat" is known to be the whole key.
+    assert!(!may_overlap(
+        Bound::Excluded(b"cat"),
+        Bound::Excluded(b"cata")
+    ));
+    assert!(!may_overlap(Bound::Included(b"b"), Bound::Excluded(b"ba")));
+    assert!(!may_overlap(Bound::Included(b"bb"), Bound::Included(b"c")));
+    assert!(!may_overlap(Bound::Included(b"d"), Bound::Unbounded));
+    assert!(!may_overlap(Bound::Unbounded, Bound::Excluded(b"apple")));
+    // Only "bana" of "banana" is kept.
+    assert!(may_overlap(
+        Bound::Included(b"banaz"),
+        Bound::Excluded(b"banb")
+    ));
+
+    // Keys ordered by other comparators get no range filter.
+    let mut builder = SsTableBuilder::new(128);
+    builder.set_comparator(Arc::new(ReverseBytewiseComparator));
+    builder.set_range_filter(Some(0));
+    for key in keys.iter().rev() {
+        builder.add(key, b"v");
+    }
+    let sst = builder.build_for_test(dir.path().join("2.sst")).unwrap();
+    assert!(sst.range_filter.is_none());
+
+    // Bytes kept after the prefixes rule out more ranges.
+    let mut builder = SsTableBuilder::new(128);
+    builder.set_range_filter(Some(1));
+    for key in keys {
+        builder.add(key, b"v");
+    }
+    let sst = builder.build_for_test(dir.path().join("3.sst")).unwrap();
+    assert!(sst.may_overlap(Bound::Included(b"banan"), Bound::Excluded(b"banb")));
+    assert!(!sst.may_overlap(Bound::Included(b"banaz"), Bound::Excluded(b"banb")));
+}
+
+#[test]
+fn test_sst_range_filter_long_keys() {
+    // Keys sharing and keeping more than 64 KiB.
+    let long = vec![b'k'; 70000];
+    let keys: Vec<Vec<u8>> = [&b"a"[..], b"b", b"c"]
+        .iter()
+        .map(|suffix| [&long[..], suffix].concat())
+        .collect();
+    let dir = tempdir().unwrap();
+    let mut builder = SsTableBuilder::new(128);
+    builder.set_range_filter(Some(usize::MAX));
+    for key in &keys {
+        builder.add(key, b"v");
+    }
+    let sst = builder.build_for_test(dir.path().join("1.sst")).unwrap();
+    let sst = SsTable::open(1, None, FileObject(sst.file.0.clone())).unwrap();
+    assert!(sst.range_filter.is_some());
+    for key in &keys {
+        assert!(sst.may_overlap(Bound::Included(key), Bound::Included(key)));
+    }
+    let absent = [&long[..], b"bb"].concat();
+    assert!(!sst.may_overlap(Bound::Included(&absent), Bound::Included(&absent)));
+}
+
+#[test]
+fn test_sst_unsupported_version() {
+    let (_dir, sst) = generate_sst();
+    let mut data = sst.file.0.to_vec();
+    // The version precedes the footer checksum and the magic number.
+    let pos = data.len() - 16;
+    let version = SST_FORMAT_VERSION + 1;
+    data[pos..pos + 4].copy_from_slice(&version.to_le_bytes());
+    // Without a matching checksum, the version is corrupted rather than newer.
+    let err = SsTable::open(7, None, FileObject(data.clone().into()))
+        .err()
+        .unwrap();
+    assert_eq!(
+        corruption_of(err),
+        SsTableError::MetaCorruption { sst_id: 7 }
+    );
+    let checksum = crc32c(&data[pos - 36..pos + 4]);
+    data[pos + 4..pos + 8].copy_from_slice(&checksum.to_le_bytes());
+    let err = SsTable::open(7, None, FileObject(data.into()))
+        .err()
+        .unwrap();
+    assert_eq!(
+        corruption_of(err),
+        SsTableError::UnsupportedVersion { sst_id: 7, version }
+    );
+    assert_eq!(
+        SsTableError::UnsupportedVersion { sst_id: 7, version }.to_string(),
+        format!(
+            "SST 7 has format version {}, only versions 1 to {} are supported",
+            version, SST_FORMAT_VERSION
+        )
+    );
+}
+
+/// The built-in LZ4 codec under another id, to check codecs that are not built-in.
+struct CustomCodec;
+
+impl CompressionCodec for CustomCodec {
+    fn id(&self) -> u8 {
+        200
+    }
+
+    fn name(&self) -> &str {
+        "custom"
+    }
+
+    fn compress(&self, data: &[u8]) -> Vec<u8> {
+        Lz4Codec.compress(data)
+    }
+
+    fn decompress(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        Lz4Codec.decompress(data)
+    }
+}
+
+#[test]
+fn test_sst_compression() {
+    let json_of = |idx: usize| format!(r#"{{"id":{},"name":"user","active":true}}"#, idx);
+    let build = |codec: Option<Arc<dyn CompressionCodec>>| {
+        let mut builder = SsTableBuilder::new(4096);
+        builder.set_compression(codec);
+        for idx in 0..num_of_keys() {
+            builder.add(&key_of(idx), json_of(idx).as_bytes());
+        }
+        let dir = tempdir().unwrap();
+        Arc::new(builder.build_for_test(dir.path().join("1.sst")).unwrap())
+    };
+    let raw = build(None);
+    let compressed = build(Some(Arc::new(Lz4Codec)));
+    assert!(compressed.file.size() < raw.file.size() / 2);
+
+    let mut iter = SsTableIterator::create_and_seek_to_first(compressed.clone()).unwrap();
+    for idx in 0..num_of_keys() {
+        assert_eq!(iter.key(), key_of(idx));
+        assert_eq!(iter.value(), json_of(idx).as_bytes());
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+    // Built-in codecs are found from the compression type alone.
+    let data = compressed.file.read(0, compressed.file.size()).unwrap();
+    let reopened = SsTable::open_for_test(FileObject(data.into())).unwrap();
+    let iter = SsTableIterator::create_and_seek_to_key(Arc::new(reopened), &key_of(50)).unwrap();
+    assert_eq!(iter.value(), json_of(50).as_bytes());
+
+    // Other codecs must be the one the SST was built with.
+    let custom = build(Some(Arc::new(CustomCodec)));
+    let iter = SsTableIterator::create_and_seek_to_key(custom.clone(), &key_of(50)).unwrap();
+    assert_eq!(iter.value(), json_of(50).as_bytes());
+    let data = custom.file.read(0, custom.file.size()).unwrap();
+    let reopened = SsTable::open_for_test(FileObject(data.clone().into())).unwrap();
+    let err = reopened.read_block(0).err().unwrap();
+    assert!(err.to_string().contains("unknown compression type 200"));
+    // Or given when opening the SST.
+    let reopened = SsTable::open_with_codecs(
+        0,
+        None,
+        FileObject(data.into()),
+        Arc::new(BytewiseComparator),
+        vec![Arc::new(CustomCodec)],
+    )
+    .unwrap();
+    let iter = SsTableIterator::create_and_seek_to_key(Arc::new(reopened), &key_of(50)).unwrap();
+    assert_eq!(iter.value(), json_of(50).as_bytes());
+}
+
+#[test]
+fn test_sst_properties() {
+    let mut builder = SsTableBuilder::new(128);
+    builder.set_compression(Some(Arc::new(Lz4Codec)));
+    builder.add(b"a", &Record::encode(5, ValueKind::Put, b"1"));
+    builder.add(b"b", &Record::encode(3, ValueKind::Delete, b""));
+    builder.add(
+        b"c",
+        &Record::encode_with_expiry(9, ValueKind::Put, Some(1), b"22"),
+    );
+    builder.add_range_tombstone(RangeTombstone {
+        start: Bytes::from_static(b"x"),
+        end: Bytes::from_static(b"z"),
+        seq: 2,
+    });
+    let dir = tempdir().unwrap();
+    let sst = builder.build_for_test(dir.path().join("1.sst")).unwrap();
+    let properties = sst.properties().clone();
+    assert_eq!(properties.num_entries, 3);
+    assert_eq!(properties.num_tombstones, 1);
+    assert_eq!(properties.num_expiring_entries, 1);
+    assert_eq!(properties.num_range_tombstones, 1);
+    assert_eq!(properties.raw_key_size, 3);
+    assert_eq!(properties.raw_value_size, 10 + 9 + 19);
+    assert_eq!(properties.num_data_blocks, 1);
+    assert_eq!(properties.first_key, as_bytes(b"a"));
+    assert_eq!(properties.last_key, as_bytes(b"c"));
+    assert_eq!((properties.min_seq, properties.max_seq), (2, 9));
+    assert!(properties.creation_time > 0);
+    assert_eq!(properties.compression, "lz4");
+
+    // The properties survive reopening the table.
+    let reopened = SsTable::open_for_test(FileObject(sst.file.0.clone())).unwrap();
+    assert_eq!(reopened.properties(), &properties);
+
+    // Tables of format version 1 only know their key range.
+    let v1 = SsTable::open_for_test(to_format_v1(&sst)).unwrap();
+    assert_eq!(v1.properties().num_entries, 0);
+    assert_eq!(v1.properties().first_key, as_bytes(b"a"));
+    assert_eq!(v1.properties().last_key, as_bytes(b"c"));
+}
+
+#[test]
+fn test_sst_index_size_saved() {
+    let mut builder = SsTableBuilder::new(128);
+    for idx in 0..100 {
+        let key = format!("{:03}_{}", idx, "x".repeat(64));
+        builder.add(key.as_bytes(), &Record::encode(1, ValueKind::Put, b"value"));
+    }
+    let dir = tempdir().unwrap();
+    let sst = Arc::new(builder.build_for_test(dir.path().join("1.sst")).unwrap());
+    let properties = sst.properties();
+    assert!(properties.num_data_blocks > 1);
+    assert!(properties.index_size > 0);
+    // Every separator is at most 4 bytes in place of a 68 bytes key.
+    assert!(properties.index_size_saved >= properties.num_data_blocks * 60);
+    for meta in block_metas(&sst) {
+        assert!(meta.separator.len() <= 4);
+    }
+    for idx in 0..100 {
+        let key = format!("{:03}_{}", idx, "x".repeat(64));
+        let iter = SsTableIterator::create_and_seek_to_key(sst.clone(), key.as_bytes()).unwrap();
+        assert_eq!(iter.key(), key.as_bytes());
+        // Keys between a block and its separator are found in the next block.
+        let iter =
+            SsTableIterator::create_and_seek_to_key(sst.clone(), &key.as_bytes()[..4]).unwrap();
+        assert_eq!(iter.key(), key.as_bytes());
+    }
+}
+
+/// Orders keys bytewise, with separators longer than the keys they follow.
+struct PaddingComparator;
+
+impl Comparator for PaddingComparator {
+    fn name(&self) -> &str {
+        "test.PaddingComparator"
+    }
+
+    fn compare(&self, a: &[u8], b: &[u8]) -> std::cmp::Ordering {
+        a.cmp(b)
+    }
+
+    fn shortest_separator(&self, last_key: &[u8], _next_key: &[u8]) -> Bytes {
+        [last_key, &[0; 16]].concat().into()
+    }
+}
+
+#[test]
+fn test_sst_longer_separators() {
+    let mut builder = SsTableBuilder::new(128);
+    builder.set_comparator(Arc::new(PaddingComparator));
+    for idx in 0..num_of_keys() {
+        builder.add(&key_of(idx), &value_of(idx));
+    }
+    let dir = tempdir().unwrap();
+    let sst = builder.build_for_test(dir.path().join("1.sst")).unwrap();
+    assert!(sst.num_of_blocks() > 1);
+    assert_eq!(sst.properties().index_size_saved, 0);
+    for idx in 0..num_of_keys() {
+        assert_eq!(sst.get(&key_of(idx)).unwrap().unwrap(), value_of(idx));
+    }
+}
+
+#[test]
+fn test_sst_comparator() {
+    let mut builder = SsTableBuilder::new(128);
+    builder.set_comparator(Arc::new(ReverseBytewiseComparator));
+    for idx in (0..num_of_keys()).rev() {
+        builder.add(&key_of(idx), &value_of(idx));
+    }
+    let dir = tempdir().unwrap();
+    let sst = Arc::new(builder.build_for_test(dir.path().join("1.sst")).unwrap());
+    assert_eq!(
+        sst.properties().comparator,
+        "minilsm.ReverseBytewiseComparator"
+    );
+    for idx in 0..num_of_keys() {
+        assert_eq!(sst.get(&key_of(idx)).unwrap().unwrap(), value_of(idx));
+        // Seeking past a key lands on the next smaller one.
+        let key = format!("key_{:03}", idx * 5 + 1).into_bytes();
+        let iter = SsTableIterator::create_and_seek_to_key(sst.clone(), &key).unwrap();
+        assert_eq!(iter.key(), key_of(idx));
+    }
+
+    let file = || FileObject(sst.file.0.clone());
+    let err = SsTable::open_for_test(file()).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "SST 0 was written with comparator minilsm.ReverseBytewiseComparator, not \
+         minilsm.BytewiseComparator"
+    );
+    SsTable::open_with_comparator(0, None, file(), Arc::new(ReverseBytewiseComparator)).unwrap();
+}
+
+#[test]
+fn test_sst_properties_overlap() {
+    let (_dir, sst) = generate_sst();
+    let properties = sst.properties();
+    let (first, last) = (key_of(0), key_of(num_of_keys() - 1));
+    let overlaps = |lower, upper| properties.overlaps(lower, upper, &BytewiseComparator);
+    assert!(overlaps(Bound::Unbounded, Bound::Unbounded));
+    assert!(overlaps(Bound::Included(&last), Bound::Unbounded));
+    assert!(!overlaps(Bound::Excluded(&last), Bound::Unbounded));
+    assert!(overlaps(Bound::Unbounded, Bound::Included(&first)));
+    assert!(!overlaps(Bound::Unbounded, Bound::Excluded(&first)));
+    assert!(!overlaps(Bound::Included(b"a"), Bound::Included(b"b")));
+}
+
+#[test]
+fn test_sst_partitioned_index() {
+    let (_dir, full) = generate_sst();
+    let mut builder = SsTableBuilder::new(128);
+    builder.set_index_partition_size(Some(64));
+    for idx in 0..num_of_keys() {
+        builder.add(&key_of(idx), &value_of(idx));
+    }
+    let dir = tempdir().unwrap();
+    let sst = builder.build_for_test(dir.path().join("1.sst")).unwrap();
+    assert!(sst.properties().num_index_partitions > 1);
+    assert_eq!(block_metas(&sst), block_metas(&full));
+
+    // Only the partitions are decoded when opening, index blocks go through the block cache.
+    let block_cache = Arc::new(BlockCache::new(1024));
+    let sst = Arc::new(SsTable::open(1, Some(block_cache.clone()), sst.file).unwrap());
+    let Index::Partitioned { partitions, .. } = &sst.index else {
+        panic!("index is not partitioned");
+    };
+    assert_eq!(sst.first_key(), full.first_key());
+    assert_eq!(sst.last_key(), full.last_key());
+    assert_eq!(sst.num_of_blocks(), full.num_of_blocks());
+    for key in [
+        &b"key_000"[..],
+        b"key_0",
+        b"key_250",
+        b"key_251",
+        b"key_499",
+        b"z",
+    ] {
+        assert_eq!(
+            sst.find_block_idx(key).unwrap(),
+            full.find_block_idx(key).unwrap()
+        );
+    }
+    assert!(block_cache.contains_key(&(1, partitions[0].offset as usize)));
+
+    let mut iter = SsTableIterator::create_and_seek_to_first(sst.clone()).unwrap();
+    for idx in 0..num_of_keys() {
+        assert_eq!(iter.key(), key_of(idx));
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+    for idx in 0..num_of_keys() {
+        let iter = SsTableIterator::create_and_seek_to_key(sst.clone(), &key_of(idx)).unwrap();
+        assert_eq!(iter.value(), value_of(idx));
+    }
+
+    // A corrupted index block is caught once it is read.
+    let mut data = sst.file.0.to_vec();
+    data[partitions[1].offset as usize] ^= 1;
+    let sst = SsTable::open(1, None, FileObject(data.into())).unwrap();
+    let block_idx = partitions[1].first_block;
+    assert_eq!(
+        corruption_of(sst.read_block(block_idx).err().unwrap()),
+        SsTableError::MetaCorruption { sst_id: 1 }
+    );
+    assert!(sst.read_block(0).is_ok());
+
+    // Index blocks with several restart points are binary searched.
+    let mut builder = SsTableBuilder::new(128);
+    builder.set_index_partition_size(Some(4096));
+    for idx in 0..num_of_keys() {
+        builder.add(&key_of(idx), &value_of(idx));
+    }
+    let sst = builder.build_for_test(dir.path().join("2.sst")).unwrap();
+    // More data blocks than the restart interval of index blocks.
+    assert!(full.num_of_blocks() > 16);
+    assert_eq!(block_metas(&sst), block_metas(&full));
+    for idx in 0..num_of_keys() {
+        let key = format!("key_{:03}", idx * 5 + 1).into_bytes();
+        assert_eq!(
+            sst.find_block_idx(&key).unwrap(),
+            full.find_block_idx(&key).unwrap()
+        );
+        assert_eq!(sst.get(&key_of(idx)).unwrap().unwrap(), value_of(idx));
+    }
+}
+
+#[test]
+fn test_sst_reverse_iterator() {
+    let (_dir, sst) = generate_sst();
+    let sst = Arc::new(sst);
+    assert!(sst.num_of_blocks() > 1);
+    let mut iter = SsTableIterator::create_and_seek_to_first(sst).unwrap();
+    iter.seek_to_last().unwrap();
+    for i in (0..num_of_keys()).rev() {
+        assert_eq!(iter.key(), key_of(i));
+        assert_eq!(iter.value(), value_of(i));
+        iter.prev().unwrap();
+    }
+    assert!(!iter.is_valid());
+
+    for i in 0..num_of_keys() {
+        iter.seek_for_prev(&key_of(i)).unwrap();
+        assert_eq!(iter.key(), key_of(i));
+        iter.seek_for_prev(format!("key_{:03}", i * 5 + 4).as_bytes())
+            .unwrap();
+        assert_eq!(iter.key(), key_of(i));
+    }
+    iter.seek_for_prev(b"key").unwrap();
+    assert!(!iter.is_valid());
+}
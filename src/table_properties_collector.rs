@@ -0,0 +1,37 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::value::ValueKind;
+
+/// Named counters a `TablePropertiesCollector` records in an SST. They are stored in its
+/// properties section, see `TableProperties::user_properties`, and summed across SSTs by
+/// `LsmStorage::user_properties`.
+pub type UserProperties = BTreeMap<String, u64>;
+
+/// Computes properties of the SST being built from the key-value pairs added to it.
+pub trait TablePropertiesCollector {
+    /// Called with each entry added to the SST, in key order, with its kind and user value: empty
+    /// for deletions, the encoded operands for merges (see `Record::operands`). With user
+    /// timestamps enabled, `key` is the user key and each version is passed separately.
+    fn add(&mut self, key: &[u8], kind: ValueKind, value: &[u8]);
+
+    /// Get the properties of the SST, once every key-value pair was added. Names should be
+    /// unique across collectors, the properties of later collectors replace earlier ones.
+    fn finish(&mut self) -> UserProperties;
+}
+
+/// Creates a `TablePropertiesCollector` for each SST written by flushes and compactions.
+/// Registered through `LsmStorageOptions::table_properties_collector_factories`.
+pub trait TablePropertiesCollectorFactory: Send + Sync {
+    /// The name of the collector.
+    fn name(&self) -> &str;
+
+    /// Create the collector of an SST.
+    fn create(&self) -> Box<dyn TablePropertiesCollector>;
+}
+
+impl fmt::Debug for dyn TablePropertiesCollectorFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -1 +1,22 @@
+pub mod block_hash_index_tests;
+pub mod compaction_filter_tests;
+pub mod compaction_tests;
+pub mod comparator_tests;
+pub mod compression_tests;
+pub mod corruption_tests;
 pub mod day4_tests;
+pub mod filter_policy_tests;
+pub mod get_versions_tests;
+pub mod key_exists_tests;
+pub mod large_value_tests;
+pub mod merge_operator_tests;
+pub mod multi_get_tests;
+pub mod partitioned_index_tests;
+pub mod prefix_scan_tests;
+pub mod range_delete_tests;
+pub mod range_filter_tests;
+pub mod scan_rev_tests;
+pub mod seek_tests;
+pub mod table_properties_tests;
+pub mod ttl_tests;
+pub mod user_timestamp_tests;
@@ -0,0 +1,46 @@
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+
+#[test]
+fn test_block_hash_index() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            block_size: 256,
+            block_hash_index: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let value_of = |i: usize| format!("value_{}", i);
+    for i in 0..500 {
+        storage
+            .put(format!("key_{:04}", i).as_bytes(), value_of(i).as_bytes())
+            .unwrap();
+    }
+    storage.sync().unwrap();
+    for i in (0..500).step_by(3) {
+        storage.delete(format!("key_{:04}", i).as_bytes()).unwrap();
+    }
+    storage.sync().unwrap();
+    let check = || {
+        for i in 0..500 {
+            let expected = (i % 3 != 0).then(|| Bytes::from(value_of(i)));
+            assert_eq!(
+                storage.get(format!("key_{:04}", i).as_bytes()).unwrap(),
+                expected
+            );
+        }
+        assert_eq!(storage.get(b"key_0000_").unwrap(), None);
+        assert_eq!(storage.get(b"key_9999").unwrap(), None);
+    };
+    check();
+    let table = storage.snapshot().l0_sstables[0].clone();
+    assert!(table.read_block(0).unwrap().has_hash_index());
+
+    storage.force_full_compaction().unwrap();
+    check();
+}
@@ -0,0 +1,114 @@
+use std::ops::Bound;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use parking_lot::Mutex;
+use tempfile::tempdir;
+
+use crate::compaction_filter::{
+    CompactionFilter, CompactionFilterContext, CompactionFilterFactory, FilterDecision,
+};
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+
+/// Drops keys starting with `tmp_` and upper-cases the values of keys starting with `up_`.
+struct TestFilter;
+
+impl CompactionFilter for TestFilter {
+    fn filter(&mut self, key: &[u8], value: &[u8]) -> FilterDecision {
+        if key.starts_with(b"tmp_") {
+            FilterDecision::Remove
+        } else if key.starts_with(b"up_") {
+            FilterDecision::ChangeValue(value.to_ascii_uppercase())
+        } else {
+            FilterDecision::Keep
+        }
+    }
+}
+
+#[derive(Default)]
+struct TestFilterFactory {
+    contexts: Mutex<Vec<CompactionFilterContext>>,
+}
+
+impl CompactionFilterFactory for TestFilterFactory {
+    fn name(&self) -> &str {
+        "test"
+    }
+
+    fn create(&self, context: &CompactionFilterContext) -> Box<dyn CompactionFilter> {
+        self.contexts.lock().push(context.clone());
+        Box::new(TestFilter)
+    }
+}
+
+#[test]
+fn test_compaction_filter() {
+    let dir = tempdir().unwrap();
+    let factory = Arc::new(TestFilterFactory::default());
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            compaction_filter_factory: Some(factory.clone()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    storage.put(b"a", b"value").unwrap();
+    storage.put(b"tmp_1", b"value").unwrap();
+    storage.put(b"up_1", b"value").unwrap();
+    storage.sync().unwrap();
+    storage.put(b"tmp_2", b"value").unwrap();
+    storage.sync().unwrap();
+
+    // Filters only run during compaction.
+    assert_eq!(
+        storage.get(b"tmp_1").unwrap(),
+        Some(Bytes::from_static(b"value"))
+    );
+    assert!(factory.contexts.lock().is_empty());
+
+    storage.force_full_compaction().unwrap();
+    assert_eq!(
+        *factory.contexts.lock(),
+        vec![CompactionFilterContext {
+            level: 1,
+            is_bottommost: true,
+        }]
+    );
+    assert_eq!(storage.get(b"tmp_1").unwrap(), None);
+    assert_eq!(storage.get(b"tmp_2").unwrap(), None);
+    assert_eq!(
+        storage.get(b"up_1").unwrap(),
+        Some(Bytes::from_static(b"VALUE"))
+    );
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    assert_eq!(iter.key(), b"a");
+    assert_eq!(iter.value(), b"value");
+    iter.next().unwrap();
+    assert_eq!(iter.key(), b"up_1");
+    assert_eq!(iter.value(), b"VALUE");
+    iter.next().unwrap();
+    assert!(!iter.is_valid());
+}
+
+#[test]
+fn test_compaction_filter_with_ts() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            enable_user_timestamp: true,
+            compaction_filter_factory: Some(Arc::new(TestFilterFactory::default())),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    storage.put_with_ts(b"tmp_1", 1, b"v1").unwrap();
+    storage.put_with_ts(b"tmp_1", 2, b"v2").unwrap();
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+    // Removing the newest version must not bring back an older one.
+    assert_eq!(storage.get_with_ts(b"tmp_1", 2).unwrap(), None);
+    assert_eq!(storage.get_with_ts(b"tmp_1", 1).unwrap(), None);
+}
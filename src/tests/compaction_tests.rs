@@ -0,0 +1,54 @@
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+
+#[test]
+fn test_full_compaction() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            block_size: 128,
+            target_sst_size: 512,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for i in 0..100 {
+        storage
+            .put(format!("key_{:03}", i).as_bytes(), b"old")
+            .unwrap();
+    }
+    storage.sync().unwrap();
+    for i in 0..100 {
+        let key = format!("key_{:03}", i);
+        if i % 2 == 0 {
+            storage.delete(key.as_bytes()).unwrap();
+        } else {
+            storage.put(key.as_bytes(), b"new").unwrap();
+        }
+    }
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+
+    let snapshot = storage.snapshot();
+    assert!(snapshot.l0_sstables.is_empty());
+    assert!(snapshot.levels[0].len() > 1);
+
+    assert_eq!(storage.get(b"key_000").unwrap(), None);
+    assert_eq!(
+        storage.get(b"key_001").unwrap(),
+        Some(Bytes::from_static(b"new"))
+    );
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    for i in (1..100).step_by(2) {
+        assert_eq!(iter.key(), format!("key_{:03}", i).as_bytes());
+        assert_eq!(iter.value(), b"new");
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+}
@@ -0,0 +1,129 @@
+use std::ops::Bound;
+use std::sync::Arc;
+
+use tempfile::tempdir;
+
+use crate::comparator::{Comparator, ReverseBytewiseComparator, U64BigEndianComparator};
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+
+fn scan_keys(storage: &LsmStorage, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> Vec<Vec<u8>> {
+    let mut iter = storage.scan(lower, upper).unwrap();
+    let mut keys = Vec::new();
+    while iter.is_valid() {
+        keys.push(iter.key().to_vec());
+        iter.next().unwrap();
+    }
+    keys
+}
+
+fn open_with(
+    path: &std::path::Path,
+    comparator: Arc<dyn Comparator>,
+) -> anyhow::Result<LsmStorage> {
+    LsmStorage::open_with_options(
+        path,
+        LsmStorageOptions {
+            block_size: 64,
+            comparator,
+            ..Default::default()
+        },
+    )
+}
+
+#[test]
+fn test_reverse_comparator() {
+    let dir = tempdir().unwrap();
+    let storage = open_with(dir.path(), Arc::new(ReverseBytewiseComparator)).unwrap();
+    let key_of = |i: usize| format!("key_{:03}", i).into_bytes();
+    for i in 0..50 {
+        storage.put(&key_of(i), b"1").unwrap();
+    }
+    storage.sync().unwrap();
+    for i in 50..100 {
+        storage.put(&key_of(i), b"1").unwrap();
+    }
+    // Ranges go from the larger key to the smaller one.
+    storage.delete_range(&key_of(60), &key_of(40)).unwrap();
+    let check = || {
+        let expected: Vec<_> = (0..100)
+            .rev()
+            .filter(|i| !(41..=60).contains(i))
+            .map(key_of)
+            .collect();
+        assert_eq!(
+            scan_keys(&storage, Bound::Unbounded, Bound::Unbounded),
+            expected
+        );
+        assert_eq!(
+            scan_keys(
+                &storage,
+                Bound::Included(&key_of(70)),
+                Bound::Excluded(&key_of(65))
+            ),
+            (66..=70).rev().map(key_of).collect::<Vec<_>>()
+        );
+        assert!(storage.get(&key_of(10)).unwrap().is_some());
+        assert!(storage.get(&key_of(50)).unwrap().is_none());
+    };
+    check();
+    storage.sync().unwrap();
+    check();
+    storage.force_full_compaction().unwrap();
+    check();
+    let snapshot = storage.snapshot();
+    assert!(snapshot.levels[0][0].first_key() > snapshot.levels[0][0].last_key());
+}
+
+#[test]
+fn test_u64_comparator() {
+    let dir = tempdir().unwrap();
+    let storage = open_with(dir.path(), Arc::new(U64BigEndianComparator)).unwrap();
+    for key in [&[1, 0][..], &[2], &[0xff], &[1, 0, 0], &[3]] {
+        storage.put(key, b"1").unwrap();
+    }
+    storage.sync().unwrap();
+    storage.put(&[4], b"1").unwrap();
+    let expected: Vec<Vec<u8>> = vec![
+        vec![2],
+        vec![3],
+        vec![4],
+        vec![0xff],
+        vec![1, 0],
+        vec![1, 0, 0],
+    ];
+    assert_eq!(
+        scan_keys(&storage, Bound::Unbounded, Bound::Unbounded),
+        expected
+    );
+    storage.force_full_compaction().unwrap();
+    assert_eq!(
+        scan_keys(&storage, Bound::Excluded(&[3]), Bound::Included(&[1, 0])),
+        &expected[2..5]
+    );
+}
+
+#[test]
+fn test_comparator_mismatch() {
+    let dir = tempdir().unwrap();
+    drop(open_with(dir.path(), Arc::new(ReverseBytewiseComparator)).unwrap());
+    let err = LsmStorage::open(dir.path()).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "the storage was created with comparator minilsm.ReverseBytewiseComparator, it cannot be \
+         opened with minilsm.BytewiseComparator"
+    );
+    open_with(dir.path(), Arc::new(ReverseBytewiseComparator)).unwrap();
+
+    // Keys with timestamps are ordered bytewise.
+    let dir = tempdir().unwrap();
+    assert!(LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            enable_user_timestamp: true,
+            comparator: Arc::new(U64BigEndianComparator),
+            ..Default::default()
+        },
+    )
+    .is_err());
+}
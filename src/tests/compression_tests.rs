@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::compression::Lz4Codec;
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+
+#[test]
+fn test_compression_per_level() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            compression_per_level: vec![None, Some(Arc::new(Lz4Codec))],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let value_of = |i: usize| format!(r#"{{"id":{},"name":"user","tags":["a","b"]}}"#, i);
+    for i in 0..500 {
+        storage
+            .put(format!("key_{:03}", i).as_bytes(), value_of(i).as_bytes())
+            .unwrap();
+    }
+    storage.sync().unwrap();
+    let l0_size = storage.snapshot().l0_sstables[0].file().size();
+
+    storage.force_full_compaction().unwrap();
+    let snapshot = storage.snapshot();
+    let l1_size: u64 = snapshot.levels[0].iter().map(|t| t.file().size()).sum();
+    assert!(l1_size < l0_size / 2);
+    for i in [0, 250, 499] {
+        assert_eq!(
+            storage.get(format!("key_{:03}", i).as_bytes()).unwrap(),
+            Some(Bytes::from(value_of(i)))
+        );
+    }
+}
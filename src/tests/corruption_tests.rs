@@ -0,0 +1,88 @@
+use std::ops::Bound;
+use std::sync::Arc;
+
+use tempfile::tempdir;
+
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+use crate::table::{FileObject, SsTable, SsTableError};
+
+#[test]
+fn test_corrupted_block() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            block_size: 128,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for i in 0..100 {
+        storage
+            .put(format!("key_{:03}", i).as_bytes(), b"value")
+            .unwrap();
+    }
+    storage.sync().unwrap();
+
+    // Flip the last byte of the first block, its checksum no longer matches.
+    {
+        let mut guard = storage.inner.write();
+        let mut snapshot = guard.as_ref().clone();
+        let table = &snapshot.l0_sstables[0];
+        let mut data = table.file().read(0, table.file().size()).unwrap();
+        let block = table.read_block(0).unwrap();
+        data[block.encode().len() - 1] ^= 1;
+        let file = FileObject::create(&storage.path_of_sst(table.sst_id()), data).unwrap();
+        snapshot.l0_sstables[0] = Arc::new(SsTable::open(table.sst_id(), None, file).unwrap());
+        *guard = Arc::new(snapshot);
+    }
+    let sst_id = storage.snapshot().l0_sstables[0].sst_id();
+    let expected = SsTableError::Corruption {
+        sst_id,
+        block_idx: 0,
+    };
+
+    let err = storage.get(b"key_000").unwrap_err();
+    assert_eq!(err.downcast_ref::<SsTableError>(), Some(&expected));
+    assert_eq!(
+        storage.get(b"key_099").unwrap().as_deref(),
+        Some(&b"value"[..])
+    );
+    // Keys given more than once to `multi_get` all get the typed error.
+    let values = storage.multi_get(&[b"key_000", b"key_099", b"key_000"]);
+    for idx in [0, 2] {
+        let err = values[idx].as_ref().unwrap_err();
+        assert_eq!(err.downcast_ref::<SsTableError>(), Some(&expected));
+    }
+    assert_eq!(values[1].as_ref().unwrap().as_deref(), Some(&b"value"[..]));
+    let err = storage
+        .scan(Bound::Unbounded, Bound::Unbounded)
+        .err()
+        .unwrap();
+    assert_eq!(err.downcast_ref::<SsTableError>(), Some(&expected));
+
+    // A scan starting past the corrupted block works.
+    let mut iter = storage
+        .scan(Bound::Included(b"key_090"), Bound::Unbounded)
+        .unwrap();
+    while iter.is_valid() {
+        iter.next().unwrap();
+    }
+}
+
+#[test]
+fn test_malformed_record() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open(dir.path()).unwrap();
+    storage.put(b"a", b"value").unwrap();
+    // A record shorter than its header.
+    storage.snapshot().memtable.put(b"b", b"x");
+
+    assert!(storage.get(b"b").is_err());
+    assert!(storage.contains(b"b").is_err());
+    assert!(storage.multi_get(&[b"a", b"b"])[1].is_err());
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    assert_eq!(iter.key(), b"a");
+    assert!(iter.next().is_err());
+}
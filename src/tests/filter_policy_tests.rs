@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use tempfile::tempdir;
+
+use crate::filter_policy::{BloomFilterPolicy, FilterBitsAllocation, XorFilterPolicy};
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+
+fn key_of(i: usize) -> Vec<u8> {
+    format!("key_{:04}", i).into_bytes()
+}
+
+#[test]
+fn test_filter_policy_per_level() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            block_size: 256,
+            filter_policy_per_level: vec![
+                Some(Arc::new(BloomFilterPolicy)),
+                Some(Arc::new(XorFilterPolicy)),
+            ],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for i in (0..1000).step_by(2) {
+        storage.put(&key_of(i), b"v").unwrap();
+    }
+    storage.sync().unwrap();
+    {
+        let snapshot = storage.inner.read().clone();
+        let table = &snapshot.l0_sstables[0];
+        assert_eq!(table.properties().filter_policy, "bloom");
+        assert!(table.may_contain_key(&key_of(10)));
+    }
+
+    storage.force_full_compaction().unwrap();
+    let snapshot = storage.inner.read().clone();
+    let tables = &snapshot.levels[0];
+    assert!(tables
+        .iter()
+        .all(|table| table.properties().filter_policy == "xor8"));
+    // Every key is found, and most absent keys are ruled out by the filters alone.
+    let mut false_positives = 0;
+    for i in 0..1000 {
+        let key = key_of(i);
+        let candidates = tables
+            .iter()
+            .filter(|table| table.may_contain_key(&key))
+            .count();
+        if i % 2 == 0 {
+            assert!(candidates >= 1);
+            assert_eq!(storage.get(&key).unwrap().unwrap(), "v");
+        } else {
+            false_positives += candidates;
+            assert!(storage.get(&key).unwrap().is_none());
+        }
+    }
+    assert!(false_positives < 10, "{} false positives", false_positives);
+}
+
+#[test]
+fn test_no_filter_policy() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            filter_policy_per_level: vec![None],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    storage.put(b"a", b"1").unwrap();
+    storage.sync().unwrap();
+    let snapshot = storage.inner.read().clone();
+    let table = &snapshot.l0_sstables[0];
+    assert_eq!(table.properties().filter_policy, "");
+    assert!(table.may_contain_key(b"b"));
+    assert!(storage.get(b"b").unwrap().is_none());
+}
+
+#[test]
+fn test_filter_bits_allocation_stats() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            filter_bits_allocation: FilterBitsAllocation::Optimal,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for i in 0..2000 {
+        storage.put(&key_of(i), b"v").unwrap();
+    }
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+    for i in 0..100 {
+        storage.put(&key_of(i), b"again").unwrap();
+    }
+    storage.sync().unwrap();
+
+    let stats = storage.stats();
+    assert_eq!(stats.levels.len(), 2);
+    let (l0, l1) = (&stats.levels[0], &stats.levels[1]);
+    assert_eq!((l0.num_sstables, l0.num_entries), (1, 100));
+    assert_eq!(l1.num_entries, 2000);
+    // The small level gets more bits per key than the large one, for the same memory.
+    assert!(l0.target_filter_bits_per_key > 10.0 && l1.target_filter_bits_per_key < 10.0);
+    let memory = l0.target_filter_bits_per_key * 100.0 + l1.target_filter_bits_per_key * 2000.0;
+    assert!((memory - 10.0 * 2100.0).abs() < 1e-6);
+
+    // SSTs are written with the bits per key allocated to their level.
+    for i in 2000..2100 {
+        storage.put(&key_of(i), b"v").unwrap();
+    }
+    storage.sync().unwrap();
+    let snapshot = storage.inner.read().clone();
+    let bits = snapshot.l0_sstables[1].filter_size() as f64 * 8.0 / 100.0;
+    assert!(
+        (bits - l0.target_filter_bits_per_key).abs() < 1.0,
+        "{} bits per key, {} allocated",
+        bits,
+        l0.target_filter_bits_per_key
+    );
+    assert_eq!(storage.stats().levels[0].num_sstables, 2);
+}
@@ -0,0 +1,67 @@
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::LsmStorage;
+
+#[test]
+fn test_get_versions_across_memtable_and_ssts() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open(dir.path()).unwrap();
+    storage.put(b"a", b"1").unwrap();
+    storage.put(b"b", b"x").unwrap();
+    storage.sync().unwrap();
+    storage.put(b"a", b"2").unwrap();
+    storage.sync().unwrap();
+    storage.delete(b"a").unwrap();
+    storage.sync().unwrap();
+    storage.put(b"a", b"3").unwrap();
+
+    let versions = storage.get_versions(b"a", usize::MAX).unwrap();
+    assert_eq!(
+        versions,
+        vec![
+            (5, Some(Bytes::from_static(b"3"))),
+            (4, None),
+            (3, Some(Bytes::from_static(b"2"))),
+            (1, Some(Bytes::from_static(b"1"))),
+        ]
+    );
+    assert_eq!(storage.get_versions(b"a", 2).unwrap(), versions[..2]);
+    assert_eq!(
+        storage.get_versions(b"b", usize::MAX).unwrap(),
+        vec![(2, Some(Bytes::from_static(b"x")))]
+    );
+    assert!(storage.get_versions(b"c", usize::MAX).unwrap().is_empty());
+
+    // Reads only see the latest version.
+    assert_eq!(storage.get(b"a").unwrap(), Some(Bytes::from_static(b"3")));
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    assert_eq!(iter.key(), b"a");
+    assert_eq!(iter.value(), b"3");
+    iter.next().unwrap();
+    assert_eq!(iter.key(), b"b");
+    iter.next().unwrap();
+    assert!(!iter.is_valid());
+}
+
+#[test]
+fn test_sync_empty_memtable() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open(dir.path()).unwrap();
+    storage.sync().unwrap();
+    assert!(storage.snapshot().l0_sstables.is_empty());
+    storage.put(b"a", b"1").unwrap();
+    storage.sync().unwrap();
+    storage.sync().unwrap();
+    assert_eq!(storage.snapshot().l0_sstables.len(), 1);
+    // Range tombstones alone are flushed.
+    storage.delete_range(b"a", b"b").unwrap();
+    storage.sync().unwrap();
+    assert_eq!(storage.snapshot().l0_sstables.len(), 2);
+    assert!(storage.get_versions(b"a", usize::MAX).unwrap()[0]
+        .1
+        .is_none());
+}
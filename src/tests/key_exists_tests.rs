@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+use crate::merge_operator::BytesAppendOperator;
+
+fn key_of(i: usize) -> Vec<u8> {
+    format!("key_{:04}", i).into_bytes()
+}
+
+#[test]
+fn test_key_may_exist() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open(dir.path()).unwrap();
+    for i in (0..1000).step_by(2) {
+        storage.put(&key_of(i), b"v").unwrap();
+    }
+    storage.sync().unwrap();
+    storage.delete(&key_of(2)).unwrap();
+    storage.sync().unwrap();
+    storage.put(&key_of(1), b"v").unwrap();
+    storage.delete(&key_of(4)).unwrap();
+
+    // The memtable decides the keys it holds.
+    assert!(storage.key_may_exist(&key_of(1)).unwrap());
+    assert!(!storage.key_may_exist(&key_of(4)).unwrap());
+    // A deletion in an SST is not seen without reading its block.
+    assert!(storage.key_may_exist(&key_of(2)).unwrap());
+    assert!(!storage.contains(&key_of(2)).unwrap());
+    // Every present key may exist, and the filters rule out most absent keys.
+    let mut false_positives = 0;
+    for i in 5..1000 {
+        let may_exist = storage.key_may_exist(&key_of(i)).unwrap();
+        if i % 2 == 0 {
+            assert!(may_exist);
+        } else if may_exist {
+            false_positives += 1;
+        }
+    }
+    assert!(false_positives < 20, "{} false positives", false_positives);
+}
+
+#[test]
+fn test_contains() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            block_size: 256,
+            merge_operator: Some(Arc::new(BytesAppendOperator {
+                separator: b",".to_vec(),
+            })),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for i in 0..300 {
+        storage.put(&key_of(i), b"v").unwrap();
+    }
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+    for i in (0..300).step_by(5) {
+        storage.delete(&key_of(i)).unwrap();
+    }
+    storage.delete_range(&key_of(100), &key_of(150)).unwrap();
+    storage.sync().unwrap();
+    for i in (0..400).step_by(7) {
+        storage.merge(&key_of(i), b"m").unwrap();
+    }
+
+    for i in 0..400 {
+        let key = key_of(i);
+        assert_eq!(
+            storage.contains(&key).unwrap(),
+            storage.get(&key).unwrap().is_some(),
+            "key {}",
+            i
+        );
+    }
+    assert!(storage.contains(&key_of(1)).unwrap());
+    assert!(!storage.contains(&key_of(5)).unwrap());
+    assert!(!storage.contains(&key_of(101)).unwrap());
+    assert!(storage.contains(&key_of(350)).unwrap());
+    assert!(!storage.contains(&key_of(351)).unwrap());
+}
@@ -0,0 +1,22 @@
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::lsm_storage::LsmStorage;
+
+#[test]
+fn test_large_values() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open(dir.path()).unwrap();
+    let value_of = |idx: u8| Bytes::from(vec![idx; (4 << 20) + idx as usize]);
+    for idx in 0..3 {
+        storage.put(&[b'k', idx], &value_of(idx)).unwrap();
+    }
+    storage.sync().unwrap();
+    storage.put(b"k\x01", &value_of(7)).unwrap();
+    assert_eq!(storage.get(b"k\x01").unwrap(), Some(value_of(7)));
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+    assert_eq!(storage.get(b"k\x00").unwrap(), Some(value_of(0)));
+    assert_eq!(storage.get(b"k\x01").unwrap(), Some(value_of(7)));
+    assert_eq!(storage.get(b"k\x02").unwrap(), Some(value_of(2)));
+}
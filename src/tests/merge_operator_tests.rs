@@ -0,0 +1,102 @@
+use std::ops::Bound;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+use crate::merge_operator::{BytesAppendOperator, U64AddOperator};
+
+fn u64_of(value: Option<Bytes>) -> u64 {
+    u64::from_be_bytes(value.unwrap().as_ref().try_into().unwrap())
+}
+
+#[test]
+fn test_merge_counter() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            merge_operator: Some(Arc::new(U64AddOperator)),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let one = 1u64.to_be_bytes();
+
+    storage.merge(b"counter", &one).unwrap();
+    storage.merge(b"counter", &one).unwrap();
+    assert_eq!(u64_of(storage.get(b"counter").unwrap()), 2);
+    storage.sync().unwrap();
+    storage.merge(b"counter", &one).unwrap();
+    storage.sync().unwrap();
+    storage.put(b"other", &10u64.to_be_bytes()).unwrap();
+    storage.sync().unwrap();
+    storage.merge(b"other", &one).unwrap();
+    storage.merge(b"counter", &one).unwrap();
+    assert_eq!(u64_of(storage.get(b"counter").unwrap()), 4);
+    assert_eq!(u64_of(storage.get(b"other").unwrap()), 11);
+
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    assert_eq!(iter.key(), b"counter");
+    assert_eq!(iter.value(), 4u64.to_be_bytes());
+    iter.next().unwrap();
+    assert_eq!(iter.key(), b"other");
+    assert_eq!(iter.value(), 11u64.to_be_bytes());
+    iter.next().unwrap();
+    assert!(!iter.is_valid());
+
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+    assert_eq!(u64_of(storage.get(b"counter").unwrap()), 4);
+    storage.merge(b"counter", &one).unwrap();
+    assert_eq!(u64_of(storage.get(b"counter").unwrap()), 5);
+
+    storage.delete(b"counter").unwrap();
+    storage.merge(b"counter", &one).unwrap();
+    assert_eq!(u64_of(storage.get(b"counter").unwrap()), 1);
+}
+
+#[test]
+fn test_merge_versions() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            merge_operator: Some(Arc::new(BytesAppendOperator {
+                separator: b",".to_vec(),
+            })),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    storage.put(b"list", b"a").unwrap();
+    storage.sync().unwrap();
+    storage.merge(b"list", b"b").unwrap();
+    storage.sync().unwrap();
+    storage.merge(b"list", b"c").unwrap();
+    assert_eq!(
+        storage.get(b"list").unwrap(),
+        Some(Bytes::from_static(b"a,b,c"))
+    );
+    assert_eq!(
+        storage.get_versions(b"list", usize::MAX).unwrap(),
+        vec![
+            (3, Some(Bytes::from_static(b"a,b,c"))),
+            (2, Some(Bytes::from_static(b"a,b"))),
+            (1, Some(Bytes::from_static(b"a"))),
+        ]
+    );
+    assert_eq!(
+        storage.get_versions(b"list", 1).unwrap(),
+        vec![(3, Some(Bytes::from_static(b"a,b,c")))]
+    );
+}
+
+#[test]
+fn test_merge_without_operator() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open(dir.path()).unwrap();
+    assert!(storage.merge(b"key", b"value").is_err());
+}
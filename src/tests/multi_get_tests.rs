@@ -0,0 +1,125 @@
+use std::io;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+use crate::merge_operator::{BytesAppendOperator, MergeOperator};
+
+fn key_of(i: usize) -> Vec<u8> {
+    format!("key_{:04}", i).into_bytes()
+}
+
+#[test]
+fn test_multi_get() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            block_size: 256,
+            merge_operator: Some(Arc::new(BytesAppendOperator {
+                separator: b",".to_vec(),
+            })),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    // Keys spread over L1, L0 and the memtable, with updates, deletions and merges on top.
+    for i in 0..300 {
+        storage.put(&key_of(i), b"l1").unwrap();
+    }
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+    for i in (0..300).step_by(3) {
+        storage.put(&key_of(i), b"l0").unwrap();
+    }
+    for i in (0..300).step_by(5) {
+        storage.delete(&key_of(i)).unwrap();
+    }
+    storage.sync().unwrap();
+    for i in (0..300).step_by(7) {
+        storage.merge(&key_of(i), b"mem").unwrap();
+    }
+
+    // Keys out of order, given twice, and absent.
+    let keys: Vec<Vec<u8>> = (0..400)
+        .rev()
+        .chain((0..400).step_by(11))
+        .map(key_of)
+        .collect();
+    let keys: Vec<&[u8]> = keys.iter().map(|key| &key[..]).collect();
+    let values = storage.multi_get(&keys);
+    assert_eq!(values.len(), keys.len());
+    for (key, value) in keys.iter().zip(values) {
+        assert_eq!(value.unwrap(), storage.get(key).unwrap());
+    }
+    assert_eq!(
+        storage
+            .multi_get(&[&key_of(7), &key_of(3), &key_of(5), &key_of(350)])
+            .into_iter()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>(),
+        vec![
+            Some(Bytes::from_static(b"l1,mem")),
+            Some(Bytes::from_static(b"l0")),
+            None,
+            None,
+        ]
+    );
+    assert!(storage.multi_get(&[]).is_empty());
+}
+
+#[test]
+fn test_multi_get_with_user_timestamp() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            enable_user_timestamp: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let values = storage.multi_get(&[b"a", b"b"]);
+    assert_eq!(values.len(), 2);
+    let errors: Vec<_> = values.into_iter().map(|value| value.unwrap_err()).collect();
+    assert_eq!(errors[0].to_string(), errors[1].to_string());
+}
+
+/// Fails every merge, with an I/O error as the cause.
+struct FailingOperator;
+
+impl MergeOperator for FailingOperator {
+    fn name(&self) -> &str {
+        "failing"
+    }
+
+    fn full_merge(&self, _: &[u8], _: Option<&[u8]>, _: &[&[u8]]) -> anyhow::Result<Vec<u8>> {
+        Err(anyhow::Error::new(io::Error::other("disk")).context("merge failed"))
+    }
+}
+
+#[test]
+fn test_multi_get_shared_error() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            merge_operator: Some(Arc::new(FailingOperator)),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    storage.merge(b"a", b"1").unwrap();
+    storage.put(b"b", b"2").unwrap();
+
+    // Keys given more than once get the error with its causes.
+    let values = storage.multi_get(&[b"a", b"b", b"a"]);
+    assert_eq!(values[1].as_ref().unwrap().as_deref(), Some(&b"2"[..]));
+    for idx in [0, 2] {
+        let err = values[idx].as_ref().unwrap_err();
+        assert_eq!(format!("{:#}", err), "merge failed: disk");
+        assert!(err.root_cause().downcast_ref::<io::Error>().is_some());
+    }
+}
@@ -0,0 +1,60 @@
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+
+#[test]
+fn test_partitioned_index() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            block_size: 128,
+            index_partition_size: Some(256),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let value_of = |i: usize| format!("value_{}", i);
+    for i in 0..1000 {
+        storage
+            .put(format!("key_{:04}", i).as_bytes(), value_of(i).as_bytes())
+            .unwrap();
+    }
+    storage.sync().unwrap();
+    for i in (0..1000).step_by(3) {
+        storage.delete(format!("key_{:04}", i).as_bytes()).unwrap();
+    }
+    storage.sync().unwrap();
+    let check = || {
+        for i in [0, 1, 500, 998, 999] {
+            let expected = (i % 3 != 0).then(|| Bytes::from(value_of(i)));
+            assert_eq!(
+                storage.get(format!("key_{:04}", i).as_bytes()).unwrap(),
+                expected
+            );
+        }
+        let mut iter = storage
+            .scan(Bound::Excluded(b"key_0100"), Bound::Included(b"key_0200"))
+            .unwrap();
+        let mut count = 0;
+        while iter.is_valid() {
+            count += 1;
+            iter.next().unwrap();
+        }
+        assert_eq!(count, 67);
+    };
+    check();
+    assert!(
+        storage.snapshot().l0_sstables[0]
+            .properties()
+            .num_index_partitions
+            > 1
+    );
+
+    storage.force_full_compaction().unwrap();
+    check();
+}
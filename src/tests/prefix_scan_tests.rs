@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use tempfile::tempdir;
+
+use crate::comparator::ReverseBytewiseComparator;
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+use crate::prefix_extractor::{DelimiterPrefixExtractor, PrefixExtractor};
+
+fn open(path: &std::path::Path) -> LsmStorage {
+    LsmStorage::open_with_options(
+        path,
+        LsmStorageOptions {
+            block_size: 64,
+            prefix_extractor: Some(Arc::new(DelimiterPrefixExtractor::new(b':'))),
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+fn scan_prefix(storage: &LsmStorage, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut iter = storage.scan_prefix(prefix).unwrap();
+    let mut entries = Vec::new();
+    while iter.is_valid() {
+        entries.push((iter.key().to_vec(), iter.value().to_vec()));
+        iter.next().unwrap();
+    }
+    entries
+}
+
+fn entry(key: &str, value: &str) -> (Vec<u8>, Vec<u8>) {
+    (key.as_bytes().to_vec(), value.as_bytes().to_vec())
+}
+
+#[test]
+fn test_scan_prefix() {
+    let dir = tempdir().unwrap();
+    let storage = open(dir.path());
+    storage.put(b"a:1", b"1").unwrap();
+    storage.put(b"b:1", b"1").unwrap();
+    storage.put(b"b:2", b"2").unwrap();
+    storage.sync().unwrap();
+    storage.put(b"b:2", b"22").unwrap();
+    storage.put(b"b:3", b"3").unwrap();
+    storage.put(b"b;", b"after").unwrap();
+    storage.put(b"c:1", b"1").unwrap();
+    storage.sync().unwrap();
+    storage.delete(b"b:1").unwrap();
+    storage.put(b"b:4", b"4").unwrap();
+
+    assert_eq!(
+        scan_prefix(&storage, b"b:"),
+        vec![entry("b:2", "22"), entry("b:3", "3"), entry("b:4", "4")]
+    );
+    // Prefixes which are not extracted are scanned without the filters.
+    assert_eq!(
+        scan_prefix(&storage, b"b"),
+        vec![
+            entry("b:2", "22"),
+            entry("b:3", "3"),
+            entry("b:4", "4"),
+            entry("b;", "after")
+        ]
+    );
+    assert_eq!(scan_prefix(&storage, b"a:1"), vec![entry("a:1", "1")]);
+    assert!(scan_prefix(&storage, b"d:").is_empty());
+    assert_eq!(scan_prefix(&storage, b"").len(), 6);
+}
+
+#[test]
+fn test_scan_prefix_skips_tables() {
+    let dir = tempdir().unwrap();
+    let storage = open(dir.path());
+    // Every SST covers the key range of every user, only its prefix filter tells them apart.
+    for user in 0..8 {
+        storage.put(b"a", b"first").unwrap();
+        for item in 0..20 {
+            let key = format!("user{}:{:02}", user, item);
+            storage.put(key.as_bytes(), b"v").unwrap();
+        }
+        storage.put(b"z", b"last").unwrap();
+        storage.sync().unwrap();
+    }
+
+    let extractor = DelimiterPrefixExtractor::new(b':');
+    let snapshot = storage.inner.read().clone();
+    let tables = &snapshot.l0_sstables;
+    for user in 0..8 {
+        let prefix = format!("user{}:", user);
+        let candidates = tables
+            .iter()
+            .filter(|table| table.may_contain_prefix(&extractor, prefix.as_bytes()))
+            .count();
+        // The SST holding the keys of the user, and maybe a false positive.
+        assert!(
+            (1..=2).contains(&candidates),
+            "{} SSTs may hold {}",
+            candidates,
+            prefix
+        );
+        assert_eq!(tables[user].properties().prefix_extractor, extractor.name());
+        assert!(tables[user].may_contain_prefix(&extractor, prefix.as_bytes()));
+        let entries = scan_prefix(&storage, prefix.as_bytes());
+        assert_eq!(entries.len(), 20);
+        assert!(entries
+            .iter()
+            .all(|(key, _)| key.starts_with(prefix.as_bytes())));
+    }
+
+    storage.force_full_compaction().unwrap();
+    assert_eq!(scan_prefix(&storage, b"user3:").len(), 20);
+    assert!(scan_prefix(&storage, b"user9:").is_empty());
+}
+
+#[test]
+fn test_scan_prefix_requires_bytewise_comparator() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            comparator: Arc::new(ReverseBytewiseComparator),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(storage.scan_prefix(b"a:").is_err());
+}
@@ -0,0 +1,148 @@
+use std::ops::Bound;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+use crate::merge_operator::BytesAppendOperator;
+
+fn check_scan(storage: &LsmStorage, expected: &[(&str, &str)]) {
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    for (key, value) in expected {
+        assert!(iter.is_valid());
+        assert_eq!(iter.key(), key.as_bytes());
+        assert_eq!(iter.value(), value.as_bytes());
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+}
+
+#[test]
+fn test_delete_range() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open(dir.path()).unwrap();
+    storage.put(b"a", b"1").unwrap();
+    storage.put(b"b", b"1").unwrap();
+    storage.sync().unwrap();
+    storage.put(b"c", b"1").unwrap();
+    storage.put(b"d", b"1").unwrap();
+
+    storage.delete_range(b"b", b"d").unwrap();
+    assert_eq!(storage.get(b"a").unwrap(), Some(Bytes::from_static(b"1")));
+    assert_eq!(storage.get(b"b").unwrap(), None);
+    assert_eq!(storage.get(b"c").unwrap(), None);
+    assert_eq!(storage.get(b"d").unwrap(), Some(Bytes::from_static(b"1")));
+    check_scan(&storage, &[("a", "1"), ("d", "1")]);
+
+    // Writes after the tombstone are visible, also once it is flushed.
+    storage.put(b"c", b"2").unwrap();
+    check_scan(&storage, &[("a", "1"), ("c", "2"), ("d", "1")]);
+    storage.sync().unwrap();
+    storage.put(b"b", b"2").unwrap();
+    check_scan(&storage, &[("a", "1"), ("b", "2"), ("c", "2"), ("d", "1")]);
+    storage.delete_range(b"a", b"c").unwrap();
+    check_scan(&storage, &[("c", "2"), ("d", "1")]);
+    let mut iter = storage
+        .scan(Bound::Included(b"a"), Bound::Excluded(b"c"))
+        .unwrap();
+    assert!(!iter.is_valid());
+    iter.next().unwrap();
+    assert!(!iter.is_valid());
+}
+
+#[test]
+fn test_delete_range_empty() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open(dir.path()).unwrap();
+    storage.put(b"b", b"1").unwrap();
+    storage.delete_range(b"b", b"b").unwrap();
+    assert!(storage.snapshot().memtable.range_tombstones().is_empty());
+    assert!(storage.delete_range(b"c", b"a").is_err());
+    check_scan(&storage, &[("b", "1")]);
+}
+
+#[test]
+fn test_delete_range_merge() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            merge_operator: Some(Arc::new(BytesAppendOperator::default())),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    storage.put(b"k", b"a").unwrap();
+    storage.merge(b"k", b"b").unwrap();
+    storage.sync().unwrap();
+    storage.delete_range(b"k", b"l").unwrap();
+    storage.merge(b"k", b"c").unwrap();
+    assert_eq!(storage.get(b"k").unwrap(), Some(Bytes::from_static(b"c")));
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+    check_scan(&storage, &[("k", "c")]);
+}
+
+#[test]
+fn test_delete_range_versions() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            merge_operator: Some(Arc::new(BytesAppendOperator::default())),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    storage.put(b"k", b"a").unwrap();
+    storage.sync().unwrap();
+    storage.merge(b"k", b"b").unwrap();
+    storage.sync().unwrap();
+    storage.delete_range(b"k", b"l").unwrap();
+    storage.merge(b"k", b"c").unwrap();
+    // The versions covered by the tombstone read as deletions.
+    assert_eq!(
+        storage.get_versions(b"k", usize::MAX).unwrap(),
+        vec![(4, Some(Bytes::from_static(b"c"))), (2, None), (1, None)]
+    );
+    assert_eq!(
+        storage.get_versions(b"k", 1).unwrap(),
+        vec![(4, Some(Bytes::from_static(b"c")))]
+    );
+}
+
+#[test]
+fn test_delete_range_compaction() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            block_size: 128,
+            target_sst_size: 512,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for i in 0..100 {
+        storage
+            .put(format!("key_{:03}", i).as_bytes(), b"value")
+            .unwrap();
+    }
+    storage.sync().unwrap();
+    storage.delete_range(b"key_000", b"key_090").unwrap();
+    storage.put(b"key_050", b"new").unwrap();
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+
+    let snapshot = storage.snapshot();
+    assert!(snapshot.l0_sstables.is_empty());
+    for table in &snapshot.levels[0] {
+        assert!(table.range_tombstones().is_empty());
+    }
+    let mut expected = vec![("key_050".to_string(), "new")];
+    expected.extend((90..100).map(|i| (format!("key_{:03}", i), "value")));
+    let expected: Vec<_> = expected.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    check_scan(&storage, &expected);
+}
@@ -0,0 +1,94 @@
+use std::ops::Bound;
+
+use tempfile::tempdir;
+
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+
+fn open(path: &std::path::Path, range_filter: Option<usize>) -> LsmStorage {
+    LsmStorage::open_with_options(
+        path,
+        LsmStorageOptions {
+            block_size: 64,
+            range_filter,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+fn key_of(i: usize) -> Vec<u8> {
+    format!("key_{:05}", i).into_bytes()
+}
+
+fn scan(storage: &LsmStorage, lower: &[u8], upper: &[u8]) -> Vec<Vec<u8>> {
+    let mut iter = storage
+        .scan(Bound::Included(lower), Bound::Excluded(upper))
+        .unwrap();
+    let mut keys = Vec::new();
+    while iter.is_valid() {
+        keys.push(iter.key().to_vec());
+        iter.next().unwrap();
+    }
+    keys
+}
+
+#[test]
+fn test_range_filter_skips_tables() {
+    let dir = tempdir().unwrap();
+    let storage = open(dir.path(), Some(4));
+    // Each SST covers the whole key space sparsely, with keys 1000 apart starting at its own
+    // offset.
+    for table in 0..4 {
+        for i in (table * 10..20_000).step_by(1000) {
+            storage.put(&key_of(i), b"v").unwrap();
+        }
+        storage.sync().unwrap();
+    }
+
+    let snapshot = storage.inner.read().clone();
+    let candidates = |lower: &[u8], upper: &[u8]| {
+        snapshot
+            .l0_sstables
+            .iter()
+            .filter(|table| table.may_overlap(Bound::Included(lower), Bound::Excluded(upper)))
+            .count()
+    };
+    assert_eq!(candidates(&key_of(5000), &key_of(5040)), 4);
+    assert_eq!(candidates(&key_of(5005), &key_of(5015)), 1);
+    assert_eq!(candidates(&key_of(5100), &key_of(5900)), 0);
+    assert_eq!(candidates(b"a", b"b"), 0);
+
+    assert_eq!(
+        scan(&storage, &key_of(5005), &key_of(5025)),
+        vec![key_of(5010), key_of(5020)]
+    );
+    assert!(scan(&storage, &key_of(5100), &key_of(5900)).is_empty());
+    assert_eq!(storage.get(&key_of(7030)).unwrap().unwrap(), "v");
+    assert!(storage.get(&key_of(7031)).unwrap().is_none());
+
+    storage.force_full_compaction().unwrap();
+    assert_eq!(
+        scan(&storage, &key_of(5005), &key_of(5025)),
+        vec![key_of(5010), key_of(5020)]
+    );
+    assert_eq!(scan(&storage, &key_of(0), &key_of(20_000)).len(), 80);
+}
+
+#[test]
+fn test_range_filter_disabled() {
+    let dir = tempdir().unwrap();
+    let storage = open(dir.path(), None);
+    for i in (0..20_000).step_by(1000) {
+        storage.put(&key_of(i), b"v").unwrap();
+    }
+    storage.sync().unwrap();
+    let snapshot = storage.inner.read().clone();
+    let table = &snapshot.l0_sstables[0];
+    // Only the key range of the SST is known.
+    assert!(table.may_overlap(
+        Bound::Included(&key_of(5100)),
+        Bound::Excluded(&key_of(5900))
+    ));
+    assert!(scan(&storage, &key_of(5100), &key_of(5900)).is_empty());
+}
@@ -0,0 +1,142 @@
+use std::ops::Bound;
+use std::sync::Arc;
+
+use tempfile::tempdir;
+
+use crate::iterators::{BidirectionalIterator, StorageIterator};
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+use crate::merge_operator::BytesAppendOperator;
+
+fn collect(mut iter: impl StorageIterator) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut entries = Vec::new();
+    while iter.is_valid() {
+        entries.push((iter.key().to_vec(), iter.value().to_vec()));
+        iter.next().unwrap();
+    }
+    entries
+}
+
+fn key_of(i: usize) -> Vec<u8> {
+    format!("key_{:03}", i).into_bytes()
+}
+
+/// Write versions of keys spread over L1, L0 and the memtable, with deletions, range deletions and
+/// merge operands.
+fn open_with_versions(path: &std::path::Path) -> LsmStorage {
+    let storage = LsmStorage::open_with_options(
+        path,
+        LsmStorageOptions {
+            block_size: 64,
+            merge_operator: Some(Arc::new(BytesAppendOperator {
+                separator: b",".to_vec(),
+            })),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for i in 0..100 {
+        storage.put(&key_of(i), b"v1").unwrap();
+    }
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+    for i in (0..100).step_by(3) {
+        storage.put(&key_of(i), b"v2").unwrap();
+    }
+    for i in (0..100).step_by(5) {
+        storage.delete(&key_of(i)).unwrap();
+    }
+    storage.sync().unwrap();
+    for i in (0..100).step_by(7) {
+        storage.merge(&key_of(i), b"m").unwrap();
+    }
+    storage.delete_range(&key_of(40), &key_of(50)).unwrap();
+    storage.merge(&key_of(45), b"m").unwrap();
+    storage
+}
+
+#[test]
+fn test_scan_rev() {
+    let dir = tempdir().unwrap();
+    let storage = open_with_versions(dir.path());
+    let (k10, k45, k99) = (key_of(10), key_of(45), key_of(99));
+    let bounds = [
+        (Bound::Unbounded, Bound::Unbounded),
+        (Bound::Included(&k10[..]), Bound::Excluded(&k45[..])),
+        (Bound::Excluded(&k10[..]), Bound::Included(&k45[..])),
+        (Bound::Included(&k45[..]), Bound::Included(&k99[..])),
+        (Bound::Excluded(&k99[..]), Bound::Unbounded),
+    ];
+    for (lower, upper) in bounds {
+        let mut expected = collect(storage.scan(lower, upper).unwrap());
+        assert!(!expected.is_empty() || matches!(lower, Bound::Excluded(_)));
+        expected.reverse();
+        assert_eq!(collect(storage.scan_rev(lower, upper).unwrap()), expected);
+    }
+    let entries = collect(storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap());
+    assert!(entries.contains(&(key_of(7), b"v1,m".to_vec())));
+    assert!(entries.contains(&(key_of(45), b"m".to_vec())));
+    assert!(!entries.iter().any(|(key, _)| *key == key_of(5)));
+}
+
+#[test]
+fn test_scan_turn_around() {
+    let dir = tempdir().unwrap();
+    let storage = open_with_versions(dir.path());
+    let expected = collect(storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap());
+
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    for idx in 0..expected.len() {
+        assert_eq!(iter.key(), expected[idx].0);
+        if idx > 0 {
+            iter.prev().unwrap();
+            assert_eq!(iter.key(), expected[idx - 1].0);
+            assert_eq!(iter.value(), expected[idx - 1].1);
+            iter.next().unwrap();
+            assert_eq!(iter.key(), expected[idx].0);
+            assert_eq!(iter.value(), expected[idx].1);
+        }
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+
+    iter.seek_to_last().unwrap();
+    assert_eq!(iter.key(), expected.last().unwrap().0);
+    iter.seek_for_prev(&key_of(45)).unwrap();
+    assert_eq!(iter.key(), key_of(45));
+    // Keys 40 to 49 were deleted by range, before 45 was merged into again.
+    iter.prev().unwrap();
+    assert_eq!(iter.key(), key_of(39));
+    iter.seek_for_prev(&key_of(44)).unwrap();
+    assert_eq!(iter.key(), key_of(39));
+    iter.next().unwrap();
+    assert_eq!(iter.key(), key_of(45));
+}
+
+#[test]
+fn test_scan_rev_with_ts() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            enable_user_timestamp: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    storage.put_with_ts(b"a", 1, b"a1").unwrap();
+    storage.put_with_ts(b"b", 1, b"b1").unwrap();
+    storage.put_with_ts(b"b", 3, b"b3").unwrap();
+    storage.sync().unwrap();
+    storage.delete_with_ts(b"a", 2).unwrap();
+    storage.put_with_ts(b"c", 3, b"c3").unwrap();
+
+    let mut iter = storage
+        .scan_with_ts(Bound::Unbounded, Bound::Unbounded, 2)
+        .unwrap();
+    iter.seek_to_last().unwrap();
+    assert_eq!((iter.key(), iter.value()), (&b"b"[..], &b"b1"[..]));
+    iter.prev().unwrap();
+    assert!(!iter.is_valid());
+    iter.seek_for_prev(b"bb").unwrap();
+    assert_eq!(iter.key(), b"b");
+}
@@ -0,0 +1,128 @@
+use std::ops::Bound;
+
+use tempfile::tempdir;
+
+use crate::iterators::{BidirectionalIterator, StorageIterator};
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+
+fn key_of(i: usize) -> Vec<u8> {
+    format!("key_{:03}", i).into_bytes()
+}
+
+fn open(path: &std::path::Path) -> LsmStorage {
+    LsmStorage::open_with_options(
+        path,
+        LsmStorageOptions {
+            block_size: 64,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_seek_pages() {
+    let dir = tempdir().unwrap();
+    let storage = open(dir.path());
+    for i in 0..200 {
+        storage
+            .put(&key_of(i), format!("v{}", i).as_bytes())
+            .unwrap();
+    }
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+    for i in (0..200).step_by(2) {
+        storage.delete(&key_of(i)).unwrap();
+    }
+    storage.sync().unwrap();
+    for i in (0..200).step_by(10) {
+        storage.put(&key_of(i), b"again").unwrap();
+    }
+
+    // Read pages of 7 keys, jumping between them with the same iterator.
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    let mut keys = Vec::new();
+    let mut page_start = key_of(0);
+    loop {
+        iter.seek(&page_start).unwrap();
+        let mut page = Vec::new();
+        while iter.is_valid() && page.len() < 7 {
+            page.push(iter.key().to_vec());
+            iter.next().unwrap();
+        }
+        if !iter.is_valid() {
+            keys.extend(page);
+            break;
+        }
+        page_start = iter.key().to_vec();
+        keys.extend(page);
+    }
+    let expected: Vec<_> = (0..200)
+        .filter(|i| i % 2 == 1 || i % 10 == 0)
+        .map(key_of)
+        .collect();
+    assert_eq!(keys, expected);
+
+    iter.seek(&key_of(11)).unwrap();
+    assert_eq!(iter.key(), key_of(11));
+    iter.seek(b"key_125a").unwrap();
+    assert_eq!(iter.key(), key_of(127));
+    iter.seek(&key_of(120)).unwrap();
+    assert_eq!(iter.value(), b"again");
+    iter.seek(b"z").unwrap();
+    assert!(!iter.is_valid());
+    iter.seek_to_first().unwrap();
+    assert_eq!(iter.key(), key_of(0));
+}
+
+#[test]
+fn test_seek_within_bounds() {
+    let dir = tempdir().unwrap();
+    let storage = open(dir.path());
+    for i in 0..50 {
+        storage.put(&key_of(i), b"1").unwrap();
+    }
+    storage.sync().unwrap();
+    let (lower, upper) = (key_of(10), key_of(20));
+    let mut iter = storage
+        .scan(Bound::Excluded(&lower), Bound::Excluded(&upper))
+        .unwrap();
+    iter.seek(&key_of(2)).unwrap();
+    assert_eq!(iter.key(), key_of(11));
+    iter.seek(&key_of(19)).unwrap();
+    assert_eq!(iter.key(), key_of(19));
+    iter.next().unwrap();
+    assert!(!iter.is_valid());
+    iter.seek(&key_of(20)).unwrap();
+    assert!(!iter.is_valid());
+    iter.seek_to_first().unwrap();
+    assert_eq!(iter.key(), key_of(11));
+}
+
+#[test]
+fn test_seek_with_ts() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            enable_user_timestamp: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    storage.put_with_ts(b"a", 1, b"a1").unwrap();
+    storage.put_with_ts(b"b", 1, b"b1").unwrap();
+    storage.sync().unwrap();
+    storage.put_with_ts(b"b", 3, b"b3").unwrap();
+    storage.put_with_ts(b"c", 2, b"c2").unwrap();
+
+    let mut iter = storage
+        .scan_with_ts(Bound::Unbounded, Bound::Unbounded, 2)
+        .unwrap();
+    iter.seek(b"b").unwrap();
+    assert_eq!((iter.key(), iter.value()), (&b"b"[..], &b"b1"[..]));
+    iter.next().unwrap();
+    assert_eq!(iter.key(), b"c");
+    iter.seek(b"a").unwrap();
+    assert_eq!(iter.value(), b"a1");
+}
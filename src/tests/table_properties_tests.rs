@@ -0,0 +1,243 @@
+use std::ops::Bound;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tempfile::tempdir;
+
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+use crate::table::{FileObject, SsTable};
+use crate::table_properties_collector::{
+    TablePropertiesCollector, TablePropertiesCollectorFactory, UserProperties,
+};
+use crate::value::ValueKind;
+
+fn put_range(storage: &LsmStorage, prefix: &str, range: std::ops::Range<usize>) {
+    for i in range {
+        storage
+            .put(format!("{}_{:03}", prefix, i).as_bytes(), b"value")
+            .unwrap();
+    }
+}
+
+#[test]
+fn test_read_skips_other_key_ranges() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            block_size: 128,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    put_range(&storage, "a", 0..100);
+    storage.sync().unwrap();
+    put_range(&storage, "b", 0..100);
+    storage.sync().unwrap();
+    let properties = storage.snapshot().l0_sstables[1].properties().clone();
+    assert_eq!(properties.num_entries, 100);
+    assert_eq!(&properties.first_key[..], b"b_000");
+    assert_eq!(&properties.last_key[..], b"b_099");
+
+    // Corrupt every block of the SST holding the `b` keys, reading it fails.
+    {
+        let mut guard = storage.inner.write();
+        let mut snapshot = guard.as_ref().clone();
+        let table = &snapshot.l0_sstables[1];
+        let mut data = table.file().read(0, table.file().size()).unwrap();
+        for byte in &mut data[..table.properties().data_size as usize] {
+            *byte ^= 1;
+        }
+        let file = FileObject::create(&storage.path_of_sst(table.sst_id()), data).unwrap();
+        snapshot.l0_sstables[1] = Arc::new(SsTable::open(table.sst_id(), None, file).unwrap());
+        *guard = Arc::new(snapshot);
+    }
+    assert!(storage.get(b"b_050").is_err());
+
+    // Reads outside of its key range do not touch it.
+    assert_eq!(
+        storage.get(b"a_050").unwrap().as_deref(),
+        Some(&b"value"[..])
+    );
+    assert_eq!(storage.get(b"c").unwrap(), None);
+    let mut iter = storage
+        .scan(Bound::Included(b"a_090"), Bound::Excluded(b"b"))
+        .unwrap();
+    let mut count = 0;
+    while iter.is_valid() {
+        count += 1;
+        iter.next().unwrap();
+    }
+    assert_eq!(count, 10);
+}
+
+#[test]
+fn test_compaction_moves_other_key_ranges() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            block_size: 128,
+            target_sst_size: 1024,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let l1_ids = || -> Vec<usize> {
+        storage.snapshot().levels[0]
+            .iter()
+            .map(|table| table.sst_id())
+            .collect()
+    };
+    put_range(&storage, "b", 0..100);
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+    let b_ids = l1_ids();
+    assert!(b_ids.len() > 1);
+
+    // Only the SSTs L0 overlaps are rewritten, the others keep their place in L1.
+    put_range(&storage, "a", 0..100);
+    storage.put(b"b_050", b"new").unwrap();
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+    let ids = l1_ids();
+    assert_eq!(ids[ids.len() - 1], b_ids[b_ids.len() - 1]);
+    assert!(!ids.contains(&b_ids[0]));
+    let snapshot = storage.snapshot();
+    for pair in snapshot.levels[0].windows(2) {
+        assert!(pair[0].last_key() < pair[1].first_key());
+    }
+    assert_eq!(
+        storage.get(b"a_000").unwrap().as_deref(),
+        Some(&b"value"[..])
+    );
+    assert_eq!(storage.get(b"b_050").unwrap().as_deref(), Some(&b"new"[..]));
+
+    // SSTs with entries to expire are always rewritten, here into no SST at all.
+    storage
+        .put_with_ttl(b"c", b"value", Duration::from_millis(200))
+        .unwrap();
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+    let ids = l1_ids();
+    let last = storage.snapshot().levels[0].last().unwrap().clone();
+    assert_eq!(last.properties().num_expiring_entries, 1);
+    std::thread::sleep(Duration::from_millis(250));
+    storage.force_full_compaction().unwrap();
+    assert_eq!(l1_ids(), &ids[..ids.len() - 1]);
+    assert_eq!(storage.get(b"c").unwrap(), None);
+}
+
+/// Counts the keys of each tenant, keys being prefixed by their tenant and a slash.
+struct TenantKeyCounter;
+
+impl TablePropertiesCollectorFactory for TenantKeyCounter {
+    fn name(&self) -> &str {
+        "tenant_key_counter"
+    }
+
+    fn create(&self) -> Box<dyn TablePropertiesCollector> {
+        Box::new(TenantKeyCollector(UserProperties::new()))
+    }
+}
+
+struct TenantKeyCollector(UserProperties);
+
+impl TablePropertiesCollector for TenantKeyCollector {
+    fn add(&mut self, key: &[u8], _kind: ValueKind, _value: &[u8]) {
+        let tenant = key.split(|byte| *byte == b'/').next().unwrap();
+        let name = format!("keys.{}", String::from_utf8_lossy(tenant));
+        *self.0.entry(name).or_default() += 1;
+    }
+
+    fn finish(&mut self) -> UserProperties {
+        std::mem::take(&mut self.0)
+    }
+}
+
+#[test]
+fn test_user_properties() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            table_properties_collector_factories: vec![Arc::new(TenantKeyCounter)],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    put_range(&storage, "acme/user", 0..30);
+    put_range(&storage, "globex/user", 0..10);
+    storage.sync().unwrap();
+    put_range(&storage, "acme/user", 20..40);
+    storage.sync().unwrap();
+
+    let table = storage.snapshot().l0_sstables[0].clone();
+    let expected = UserProperties::from([("keys.acme".into(), 30), ("keys.globex".into(), 10)]);
+    assert_eq!(table.properties().user_properties, expected);
+    let data = table.file().read(0, table.file().size()).unwrap();
+    let file = FileObject::create(&storage.path_of_sst(table.sst_id()), data).unwrap();
+    let reopened = SsTable::open(table.sst_id(), None, file).unwrap();
+    assert_eq!(reopened.properties().user_properties, expected);
+
+    // Properties are summed across SSTs, overwritten keys count once they are compacted.
+    let expected = UserProperties::from([("keys.acme".into(), 50), ("keys.globex".into(), 10)]);
+    assert_eq!(storage.user_properties(), expected);
+    storage.force_full_compaction().unwrap();
+    let expected = UserProperties::from([("keys.acme".into(), 40), ("keys.globex".into(), 10)]);
+    assert_eq!(storage.user_properties(), expected);
+}
+
+/// Counts the entries of each user key and kind, and the bytes of the user values.
+struct EntryCounter;
+
+impl TablePropertiesCollectorFactory for EntryCounter {
+    fn name(&self) -> &str {
+        "entry_counter"
+    }
+
+    fn create(&self) -> Box<dyn TablePropertiesCollector> {
+        Box::new(EntryCollector(UserProperties::new()))
+    }
+}
+
+struct EntryCollector(UserProperties);
+
+impl TablePropertiesCollector for EntryCollector {
+    fn add(&mut self, key: &[u8], kind: ValueKind, value: &[u8]) {
+        let name = format!("{}.{:?}", String::from_utf8_lossy(key), kind);
+        *self.0.entry(name).or_default() += 1;
+        *self.0.entry("value_bytes".into()).or_default() += value.len() as u64;
+    }
+
+    fn finish(&mut self) -> UserProperties {
+        std::mem::take(&mut self.0)
+    }
+}
+
+#[test]
+fn test_user_properties_with_ts() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            enable_user_timestamp: true,
+            table_properties_collector_factories: vec![Arc::new(EntryCounter)],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    storage.put_with_ts(b"a", 1, b"v1").unwrap();
+    storage.put_with_ts(b"a", 2, b"v22").unwrap();
+    storage.delete_with_ts(b"b", 3).unwrap();
+    storage.sync().unwrap();
+
+    // Collectors see user keys and values, without timestamps or record headers.
+    let expected = UserProperties::from([
+        ("a.Put".into(), 2),
+        ("b.Delete".into(), 1),
+        ("value_bytes".into(), 5),
+    ]);
+    assert_eq!(storage.user_properties(), expected);
+}
@@ -0,0 +1,110 @@
+use std::ops::Bound;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+use crate::merge_operator::BytesAppendOperator;
+use crate::value::Record;
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+#[test]
+fn test_put_with_ttl() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open(dir.path()).unwrap();
+    storage.put(b"a", b"plain").unwrap();
+    storage.put_with_ttl(b"b", b"live", HOUR).unwrap();
+    storage
+        .put_with_ttl(b"c", b"expired", Duration::ZERO)
+        .unwrap();
+    storage.put(b"d", b"old").unwrap();
+    storage.sync().unwrap();
+    storage
+        .put_with_ttl(b"d", b"expired", Duration::ZERO)
+        .unwrap();
+
+    assert_eq!(
+        storage.get(b"a").unwrap(),
+        Some(Bytes::from_static(b"plain"))
+    );
+    assert_eq!(
+        storage.get(b"b").unwrap(),
+        Some(Bytes::from_static(b"live"))
+    );
+    assert_eq!(storage.get(b"c").unwrap(), None);
+    // An expired value hides the older ones.
+    assert_eq!(storage.get(b"d").unwrap(), None);
+
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    assert_eq!(iter.key(), b"a");
+    assert_eq!(iter.value(), b"plain");
+    iter.next().unwrap();
+    assert_eq!(iter.key(), b"b");
+    assert_eq!(iter.value(), b"live");
+    iter.next().unwrap();
+    assert!(!iter.is_valid());
+
+    // A plain put clears the expiry.
+    storage.put(b"c", b"plain").unwrap();
+    assert_eq!(
+        storage.get(b"c").unwrap(),
+        Some(Bytes::from_static(b"plain"))
+    );
+}
+
+#[test]
+fn test_ttl_compaction() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open(dir.path()).unwrap();
+    storage.put(b"a", b"old").unwrap();
+    storage.sync().unwrap();
+    storage
+        .put_with_ttl(b"a", b"expired", Duration::ZERO)
+        .unwrap();
+    storage.put_with_ttl(b"b", b"live", HOUR).unwrap();
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+
+    assert!(storage.get_versions(b"a", 10).unwrap().is_empty());
+    assert_eq!(
+        storage.get(b"b").unwrap(),
+        Some(Bytes::from_static(b"live"))
+    );
+    let versions = storage.get_versions(b"b", 10).unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].1, Some(Bytes::from_static(b"live")));
+}
+
+#[test]
+fn test_ttl_merge() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorage::open_with_options(
+        dir.path(),
+        LsmStorageOptions {
+            merge_operator: Some(Arc::new(BytesAppendOperator::default())),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    // Operands folded onto a value keep its expiry, in the memtable and in compaction.
+    storage.put_with_ttl(b"a", b"x", HOUR).unwrap();
+    storage.merge(b"a", b"y").unwrap();
+    let raw = storage.inner.read().memtable.get(b"a").unwrap();
+    assert!(Record::decode(&raw).unwrap().expire_at.is_some());
+    storage.put_with_ttl(b"b", b"x", HOUR).unwrap();
+    storage.sync().unwrap();
+    storage.merge(b"b", b"y").unwrap();
+    storage.sync().unwrap();
+    storage.force_full_compaction().unwrap();
+
+    let snapshot = storage.inner.read().clone();
+    for key in [&b"a"[..], b"b"] {
+        let raw = snapshot.levels[0][0].get(key).unwrap().unwrap();
+        assert!(Record::decode(&raw).unwrap().expire_at.is_some());
+        assert_eq!(storage.get(key).unwrap(), Some(Bytes::from_static(b"xy")));
+    }
+}
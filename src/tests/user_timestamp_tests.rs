@@ -0,0 +1,117 @@
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::{LsmStorage, LsmStorageOptions};
+
+fn open_with_ts(path: &std::path::Path) -> LsmStorage {
+    LsmStorage::open_with_options(
+        path,
+        LsmStorageOptions {
+            enable_user_timestamp: true,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+fn check_scan(storage: &LsmStorage, read_ts: u64, expected: &[(&[u8], &[u8])]) {
+    let mut iter = storage
+        .scan_with_ts(Bound::Unbounded, Bound::Unbounded, read_ts)
+        .unwrap();
+    for (key, value) in expected {
+        assert!(iter.is_valid());
+        assert_eq!(iter.key(), *key);
+        assert_eq!(iter.value(), *value);
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+}
+
+#[test]
+fn test_read_at_user_timestamp() {
+    let dir = tempdir().unwrap();
+    let storage = open_with_ts(dir.path());
+    storage.put_with_ts(b"a", 10, b"a10").unwrap();
+    storage.put_with_ts(b"ab", 10, b"ab10").unwrap();
+    storage.sync().unwrap();
+    storage.put_with_ts(b"a", 20, b"a20").unwrap();
+    storage.delete_with_ts(b"ab", 15).unwrap();
+    storage.put_with_ts(b"a\x00", 5, b"a0_5").unwrap();
+
+    assert_eq!(storage.get_with_ts(b"a", 9).unwrap(), None);
+    assert_eq!(
+        storage.get_with_ts(b"a", 10).unwrap(),
+        Some(Bytes::from_static(b"a10"))
+    );
+    assert_eq!(
+        storage.get_with_ts(b"a", 19).unwrap(),
+        Some(Bytes::from_static(b"a10"))
+    );
+    assert_eq!(
+        storage.get_with_ts(b"a", u64::MAX).unwrap(),
+        Some(Bytes::from_static(b"a20"))
+    );
+    assert_eq!(
+        storage.get_with_ts(b"ab", 14).unwrap(),
+        Some(Bytes::from_static(b"ab10"))
+    );
+    assert_eq!(storage.get_with_ts(b"ab", 15).unwrap(), None);
+
+    check_scan(&storage, 4, &[]);
+    check_scan(
+        &storage,
+        10,
+        &[(b"a", b"a10"), (b"a\x00", b"a0_5"), (b"ab", b"ab10")],
+    );
+    check_scan(&storage, 20, &[(b"a", b"a20"), (b"a\x00", b"a0_5")]);
+
+    let mut iter = storage
+        .scan_with_ts(Bound::Excluded(b"a"), Bound::Included(b"ab"), 12)
+        .unwrap();
+    assert_eq!(iter.key(), b"a\x00");
+    iter.next().unwrap();
+    assert_eq!(iter.key(), b"ab");
+    iter.next().unwrap();
+    assert!(!iter.is_valid());
+
+    assert!(storage.put(b"a", b"1").is_err());
+    assert!(storage.get(b"a").is_err());
+    assert!(storage.get_versions(b"a", usize::MAX).is_err());
+}
+
+#[test]
+fn test_compaction_collects_old_versions() {
+    let dir = tempdir().unwrap();
+    let storage = open_with_ts(dir.path());
+    storage.put_with_ts(b"a", 1, b"a1").unwrap();
+    storage.put_with_ts(b"a", 2, b"a2").unwrap();
+    storage.put_with_ts(b"a", 3, b"a3").unwrap();
+    storage.put_with_ts(b"b", 1, b"b1").unwrap();
+    storage.delete_with_ts(b"b", 2).unwrap();
+    storage.sync().unwrap();
+
+    storage.force_full_compaction().unwrap();
+    assert_eq!(
+        storage.get_with_ts(b"a", 1).unwrap(),
+        Some(Bytes::from_static(b"a1"))
+    );
+
+    storage.set_gc_watermark(2);
+    storage.force_full_compaction().unwrap();
+    // The newest version at or below the watermark survives, older ones are gone.
+    assert_eq!(storage.get_with_ts(b"a", 1).unwrap(), None);
+    assert_eq!(
+        storage.get_with_ts(b"a", 2).unwrap(),
+        Some(Bytes::from_static(b"a2"))
+    );
+    assert_eq!(
+        storage.get_with_ts(b"a", 3).unwrap(),
+        Some(Bytes::from_static(b"a3"))
+    );
+    // The deletion shadows `b1` and is dropped along with it.
+    assert_eq!(storage.get_with_ts(b"b", 1).unwrap(), None);
+    check_scan(&storage, 3, &[(b"a", b"a3")]);
+}
@@ -0,0 +1,161 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use bytes::{Buf, BufMut, Bytes};
+
+/// The size of the header in front of every stored value, without the optional expiry.
+pub const HEADER_SIZE: usize = std::mem::size_of::<u64>() + 1;
+
+/// Set on the kind byte of records carrying an expiry.
+const EXPIRY_FLAG: u8 = 0x80;
+
+/// Get the current Unix time in milliseconds, the clock expiries are compared against.
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// What a stored value does to its key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ValueKind {
+    /// Sets the key to the user value.
+    Put = 0,
+    /// Removes the key, the user value is empty.
+    Delete = 1,
+    /// Applies merge operands on top of the previous value, see `Record::encode_operands`.
+    Merge = 2,
+}
+
+impl ValueKind {
+    fn from_u8(kind: u8) -> Option<Self> {
+        match kind {
+            0 => Some(ValueKind::Put),
+            1 => Some(ValueKind::Delete),
+            2 => Some(ValueKind::Merge),
+            _ => None,
+        }
+    }
+}
+
+/// A value as it is stored in memtables and SSTs. Every write is tagged with the sequence
+/// number it was assigned by `LsmStorage` and its kind, followed by the user value. Records
+/// written with a TTL set `EXPIRY_FLAG` on the kind and store their expiry before the user value,
+/// so records without one keep the plain layout.
+///
+/// ------------------------------------------------------------------
+/// | seq (u64) | kind (u8) | expire_at (u64, optional) | user value |
+/// ------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Record<'a> {
+    pub seq: u64,
+    pub kind: ValueKind,
+    /// The Unix time in milliseconds from which the record reads as a deletion.
+    pub expire_at: Option<u64>,
+    pub value: &'a [u8],
+}
+
+impl<'a> Record<'a> {
+    pub fn encode(seq: u64, kind: ValueKind, value: &[u8]) -> Bytes {
+        Self::encode_with_expiry(seq, kind, None, value)
+    }
+
+    pub fn encode_with_expiry(
+        seq: u64,
+        kind: ValueKind,
+        expire_at: Option<u64>,
+        value: &[u8],
+    ) -> Bytes {
+        let mut buf = Vec::with_capacity(HEADER_SIZE + 8 + value.len());
+        buf.put_u64(seq);
+        match expire_at {
+            Some(expire_at) => {
+                buf.put_u8(kind as u8 | EXPIRY_FLAG);
+                buf.put_u64(expire_at);
+            }
+            None => buf.put_u8(kind as u8),
+        }
+        buf.put_slice(value);
+        buf.into()
+    }
+
+    /// Decode a record, failing if `raw` is not one.
+    pub fn decode(raw: &'a [u8]) -> Result<Self> {
+        match Self::try_decode(raw) {
+            Some(record) => Ok(record),
+            None => bail!("malformed record of {} bytes", raw.len()),
+        }
+    }
+
+    /// Decode a record, returning `None` if `raw` is not one.
+    pub fn try_decode(raw: &'a [u8]) -> Option<Self> {
+        let mut buf = raw;
+        if buf.remaining() < HEADER_SIZE {
+            return None;
+        }
+        let seq = buf.get_u64();
+        let kind = buf.get_u8();
+        let expire_at = if kind & EXPIRY_FLAG != 0 {
+            if buf.remaining() < 8 {
+                return None;
+            }
+            Some(buf.get_u64())
+        } else {
+            None
+        };
+        Some(Record {
+            seq,
+            kind: ValueKind::from_u8(kind & !EXPIRY_FLAG)?,
+            expire_at,
+            value: buf,
+        })
+    }
+
+    /// Check if this record is a deletion marker.
+    pub fn is_tombstone(&self) -> bool {
+        self.kind == ValueKind::Delete
+    }
+
+    /// Turn the record into a deletion if it expired at or before `now`.
+    pub fn expire(self, now: u64) -> Self {
+        match self.expire_at {
+            Some(expire_at) if expire_at <= now => Record {
+                seq: self.seq,
+                kind: ValueKind::Delete,
+                expire_at: None,
+                value: &[],
+            },
+            _ => self,
+        }
+    }
+
+    /// Encode merge operands, oldest first, as the user value of a merge record. Each operand is
+    /// prefixed by its length (u32).
+    pub fn encode_operands<T: AsRef<[u8]>>(operands: &[T]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for operand in operands {
+            buf.put_u32(operand.as_ref().len() as u32);
+            buf.put_slice(operand.as_ref());
+        }
+        buf
+    }
+
+    /// Decode the merge operands of a merge record, oldest first.
+    pub fn operands(&self) -> Result<Vec<&'a [u8]>> {
+        let mut operands = Vec::new();
+        let mut buf = self.value;
+        while buf.has_remaining() {
+            if buf.remaining() < 4 {
+                bail!("truncated merge operand length");
+            }
+            let len = buf.get_u32() as usize;
+            if buf.remaining() < len {
+                bail!("truncated merge operand");
+            }
+            operands.push(&buf[..len]);
+            buf.advance(len);
+        }
+        Ok(operands)
+    }
+}